@@ -1,10 +1,12 @@
 use either::Either;
-use crate::{compiler_error::{CompilerError, CompilerErrorKind},lexer::{KeywordType, Lexer, Token, TokenKind}, source_buffer::SourceBuffer, source_reader::SourceCursor};
+use crate::{compiler_error::{CompilerError, CompilerErrorKind}, fix::Fix, lexer::{KeywordType, Lexer, Token, TokenKind}, source_buffer::SourceBuffer, source_reader::SourceCursor};
 use super::{Expr, Ident, PrimaryExpr, Program, Statement, UnaryOp, Call, BinOp};
 use std::{cell::RefCell, rc::Rc, str::FromStr};
 
 pub struct AstBuilder<'a> {
     lexer : Lexer<'a>,
+    /// tokens lexed before the parsing, read instead of the lexer
+    tokens : Option<Vec<Token<'a>>>,
     current : Option<Token<'a>>,
 }
 
@@ -12,18 +14,29 @@ impl<'a> From<Lexer<'a>> for AstBuilder<'a> {
     fn from(value: Lexer<'a>) -> Self {
         Self {
             lexer : value,
+            tokens : None,
             current : None,
         }
     }
 }
 
 impl<'a> AstBuilder<'a> {
+    /// parse the tokens of an already lexed source,
+    /// the reader of the lexer still gives the positions
+    pub fn from_tokens(source : &'a SourceBuffer, tokens : Vec<Token<'a>>) -> Self {
+        Self {
+            lexer : Lexer::new(source),
+            tokens : Some(tokens),
+            current : None,
+        }
+    }
+
     pub fn unexpected(&self, token : &Token) -> CompilerError {
         CompilerError::new(
             CompilerErrorKind::UnexpectedToken,
             format!("Unexpected token: {} (\"{}\")", token.kind.to_str(), token.span.data).as_str(),
-            token.span.source.path.to_str().unwrap(),
-            token.span.source.get_line(token.span.start.line).unwrap(),
+            token.span.source.path.to_str().unwrap_or_default(),
+            token.span.source.get_line(token.span.start.line).unwrap_or_default(),
             token.span.start.line as u32,
             token.span.start.collumn as u32,
             None)
@@ -33,24 +46,62 @@ impl<'a> AstBuilder<'a> {
         CompilerError::new(
             CompilerErrorKind::Expected,
             format!("Expected : {}", name).as_str(),
-            self.lexer.reader.source.path.to_str().unwrap(),
-            self.lexer.reader.source.get_line(self.lexer.reader.current_cursor.line).unwrap(),
+            self.lexer.reader.source.path.to_str().unwrap_or_default(),
+            self.lexer.reader.source.get_line(self.lexer.reader.current_cursor.line).unwrap_or_default(),
             self.lexer.reader.current_cursor.line as u32,
             self.lexer.reader.current_cursor.collumn as u32,
             None)
     }
 
+    /// a semicolon is missing after the last token, the fix insert it
+    pub fn missing_semicolon(&self, last : Option<&Token>) -> CompilerError {
+        match last {
+            Some(token) => CompilerError::new(
+                CompilerErrorKind::Expected,
+                "Expected : \";\"",
+                token.span.source.path.to_str().unwrap_or_default(),
+                token.span.source.get_line(token.span.start.line).unwrap_or_default(),
+                token.span.start.line as u32,
+                (token.span.start.collumn + token.span.data.chars().count()) as u32,
+                None)
+                .with_fix("add a semicolon", Fix::insert(token.span.offset() + token.span.size, ";"), token.span.source),
+            None => self.expected("\";\"")
+        }
+    }
+
     pub fn peek_token(&self) -> &Option<Token<'a>> {
         &self.current
     }
 
+    /// the current token or an error if there is none
+    pub fn current_token(&self) -> Result<&Token<'a>, CompilerError> {
+        self.current.as_ref().ok_or_else(|| self.expected("token"))
+    }
+
+    /// the first token after the cursor of the reader, the reader is moved after it like the lexer does
+    fn next_lexed_token(&mut self) -> Option<Result<Token<'a>, CompilerError>> {
+        let tokens = self.tokens.as_ref()?;
+        let remaining = self.lexer.reader.current_cursor.data_ref.len();
+        let token = tokens.get(tokens.partition_point(|t| t.span.start.data_ref.len() > remaining))?.clone();
+        self.lexer.reader.goto(token.span.start);
+        let end = token.span.start.data_ref.len() - token.span.size;
+        while self.lexer.reader.current_cursor.data_ref.len() > end {
+            self.lexer.reader.next_char();
+        }
+        Some(Ok(token))
+    }
+
     pub fn next_token(&mut self) -> Result<&Option<Token<'a>>, CompilerError> {
-        match self.lexer.next() {
+        let next = match self.tokens {
+            Some(_) => self.next_lexed_token(),
+            None => self.lexer.next()
+        };
+        match next {
             Some(ret) => match ret {
                 Ok(t) => self.current = Some(t),
                 Err(e) => return Err(e)
             },
-            None => return Ok(&None)
+            None => self.current = None
         }
         Ok(&self.current)
     }
@@ -70,8 +121,8 @@ impl<'a> AstBuilder<'a> {
     }
 
     pub fn parse_statment(&mut self) -> Result<Statement<'a>, CompilerError>{
-        let first_token = self.peek_token().clone();
-        let first_token = first_token.as_ref().unwrap();
+        let first_token = self.current_token()?.clone();
+        let first_token = &first_token;
         match &first_token.kind {
             TokenKind::Keyword(k) => match k {
                 KeywordType::Return => {
@@ -79,63 +130,67 @@ impl<'a> AstBuilder<'a> {
                         return Err(self.expected("expr"))
                     }
                     let value = self.parse_expr()?;
+                    let last = self.current.clone();
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::Semicolon) {
-                        return Err(self.expected("\";\""))
+                        return Err(self.missing_semicolon(last.as_ref()))
                     }
                     return Ok(Statement::Return(super::Return {
                         return_kw : first_token.clone(),
                         value,
-                        semicolon : self.peek_token().as_ref().unwrap().clone()
+                        semicolon : self.current_token()?.clone()
                     }));
 
                 },
                 KeywordType::Break => {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::Semicolon) {
-                        return Err(self.expected("\";\""))
+                        return Err(self.missing_semicolon(Some(first_token)))
                     }
                     return Ok(Statement::Break(super::Break {
                         break_kw: first_token.clone(),
-                        semicolon: self.peek_token().as_ref().unwrap().clone() 
+                        semicolon: self.current_token()?.clone() 
                     }));
                 },
                 KeywordType::Continue => {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::Semicolon) {
-                        return Err(self.expected("\";\""))
+                        return Err(self.missing_semicolon(Some(first_token)))
                     }
                     return Ok(Statement::Continue(super::Continue {
                         continue_kw: first_token.clone(),
-                        semicolon: self.peek_token().as_ref().unwrap().clone() 
+                        semicolon: self.current_token()?.clone() 
                     }));
                 },
                 KeywordType::Let => {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::Ident) {
                         return Err(self.expected("identifier"))
                     }
-                    let ident = Ident::from(self.peek_token().as_ref().unwrap().clone()); 
+                    let ident = Ident::from(self.current_token()?.clone()); 
+                    let mut last = ident.clone();
                     let _type = if self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::Colon) {
-                        let colon_token = self.peek_token().as_ref().unwrap().clone();
+                        let colon_token = self.current_token()?.clone();
                         if self.next_token()?.as_ref().map_or(false, |x| x.kind != TokenKind::Ident) {
                             return Err(self.expected("identifier"))
                         }                      
-                        let _type = self.peek_token().as_ref().unwrap().clone();
+                        let _type = self.current_token()?.clone();
+                        last = _type.clone();
                         self.next_token()?;
                         Ok(Some((colon_token, _type)))
                     }else {
                         Ok(None)
                     }?;
                     let val = if self.peek_token().as_ref().map_or(false, |x| x.kind == TokenKind::Equal) {
-                        let eq_token = self.peek_token().as_ref().unwrap().clone();
+                        let eq_token = self.current_token()?.clone();
                         if self.next_token()?.is_none() {
                             return Err(self.expected("expression"))
                         }
                         let expr = self.parse_expr()?;
+                        last = self.current_token()?.clone();
                         self.next_token()?;
                         Ok(Some((eq_token, expr)))
                     }else {
                         Ok(None)
                     }?;
                     if !self.peek_token().as_ref().map_or(false, |x| x.kind == TokenKind::Semicolon) {
-                        return Err(self.expected("\";\""))
+                        return Err(self.missing_semicolon(Some(&last)))
                     }
                     return Ok(Statement::VarDecl(super::VarDecl { 
                         let_kw: first_token.clone(),
@@ -144,24 +199,30 @@ impl<'a> AstBuilder<'a> {
                         type_name : _type.as_ref().map(|x| x.1.clone()),
                         eq_token: val.as_ref().map(|v| v.0.clone()),
                         value: val.as_ref().map(|v| v.1.clone()),
-                        semicolon: self.peek_token().as_ref().unwrap().clone()
+                        semicolon: self.current_token()?.clone()
                     }))
                 },
-                _ => {
-                    let expr = Box::new(self.parse_expr()?);
-                    let semicolon = if expr.require_semicolon() {
-                        if self.next_token()?.as_ref().map_or(false, |t| t.kind != TokenKind::Semicolon) {
-                            return Err(self.expected("\";\""));
-                        }else {
-                            Some(self.peek_token().as_ref().unwrap().clone())
-                        }
-                    }else {
-                        None
-                    };
-                    Ok(Statement::Expr(super::ExprStmt { 
-                        expr,
-                        semicolon 
-                    }))
+                _ => self.parse_expr_statement()
+            },
+            TokenKind::Ident if first_token.span.data == "test" => {
+                let cursor : SourceCursor<'a> = unsafe { std::mem::transmute(first_token.span.start) };
+                if !self.next_token()?.as_ref().is_some_and(|x| x.kind == TokenKind::Str) {
+                    // a variable named test
+                    self.lexer.reader.goto(cursor);
+                    self.next_token()?;
+                    return self.parse_expr_statement()
+                }
+                let name = super::StrLit::parse(self.current_token()?.clone())?;
+                if !self.next_token()?.as_ref().is_some_and(|x| x.kind == TokenKind::LCurly) {
+                    return Err(self.expected("\"{\""))
+                }
+                match self.parse_expr()? {
+                    Expr::BlockExpr(body) => Ok(Statement::Test(super::Test {
+                        test_kw : first_token.clone(),
+                        name,
+                        body
+                    })),
+                    _ => Err(self.expected("block"))
                 }
             },
             TokenKind::Semicolon => {
@@ -169,40 +230,44 @@ impl<'a> AstBuilder<'a> {
                     semicolon : first_token.clone()
                 }))
             },
-            _ => {
-                let expr = Box::new(self.parse_expr()?);
-                let semicolon = if expr.require_semicolon() {
-                    if self.next_token()?.as_ref().map_or(false, |t| t.kind != TokenKind::Semicolon) {
-                        return Err(self.expected("\";\""));
-                    }else {
-                        Some(self.peek_token().as_ref().unwrap().clone())
-                    }
-                }else {
-                    None
-                };
-                Ok(Statement::Expr(super::ExprStmt { 
-                    expr,
-                    semicolon 
-                }))
-            }
+            _ => self.parse_expr_statement()
         }
     }
 
+    fn parse_expr_statement(&mut self) -> Result<Statement<'a>, CompilerError> {
+        let expr = Box::new(self.parse_expr()?);
+        let semicolon = if expr.require_semicolon() {
+            let last = self.current.clone();
+            if !self.next_token()?.as_ref().map_or(false, |t| t.kind == TokenKind::Semicolon) {
+                return Err(self.missing_semicolon(last.as_ref()));
+            }else {
+                Some(self.current_token()?.clone())
+            }
+        }else {
+            None
+        };
+        Ok(Statement::Expr(super::ExprStmt { 
+            expr,
+            semicolon 
+        }))
+    }
+
     pub fn parse_call(&mut self) -> Result<Call<'a>, CompilerError> {
-        let ident = self.next_token()?.as_ref().unwrap().clone();
+        self.next_token()?;
+        let ident = self.current_token()?.clone();
             if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::LParan) {
                 return Err(self.expected("\"(\""))
             }
-            let lparen = self.peek_token().as_ref().unwrap().clone();
+            let lparen = self.current_token()?.clone();
             if self.next_token()?.is_none() {
                 return Err(self.expected("\")\""))
-            }else if self.peek_token().as_ref().unwrap().kind == TokenKind::RParan {
+            }else if self.current_token()?.kind == TokenKind::RParan {
                 return Ok(Call{
                     name : ident,
                     lparen,
                     first_arg : None,
                     other_args : vec![],
-                    rparen : self.peek_token().as_ref().unwrap().clone()
+                    rparen : self.current_token()?.clone()
                 })
             }
             let first_arg = Some(Box::new(self.parse_expr()?));
@@ -211,12 +276,12 @@ impl<'a> AstBuilder<'a> {
             loop {
                 if self.peek_token().is_none() {
                     return Err(self.expected("\")\""))
-                }else if self.peek_token().as_ref().unwrap().kind == TokenKind::RParan {
+                }else if self.current_token()?.kind == TokenKind::RParan {
                     break;
-                }else if !(self.peek_token().as_ref().unwrap().kind == TokenKind::Comma) {
+                }else if !(self.current_token()?.kind == TokenKind::Comma) {
                     return Err(self.expected("\",\""))
                 }
-                let comma = self.peek_token().as_ref().unwrap().clone();
+                let comma = self.current_token()?.clone();
                 self.next_token()?;
                 other_args.push((comma, self.parse_expr()?));
                 self.next_token()?;
@@ -226,7 +291,7 @@ impl<'a> AstBuilder<'a> {
                 lparen,
                 first_arg,
                 other_args,
-                rparen : self.peek_token().as_ref().unwrap().clone()
+                rparen : self.current_token()?.clone()
             })
     }
 
@@ -265,7 +330,7 @@ impl<'a> AstBuilder<'a> {
     }
 
     pub fn parse_expr(&mut self) -> Result<Expr<'a>, CompilerError> {
-        let token = self.peek_token().as_ref().unwrap().clone();
+        let token = self.current_token()?.clone();
         match &token.kind {
             TokenKind::LCurly  => {
                 let mut statements : Vec<Statement> = Vec::new();
@@ -278,11 +343,15 @@ impl<'a> AstBuilder<'a> {
                     if token.as_ref().map(|x| x.kind == TokenKind::RCurly).unwrap() {
                         break;
                     }
-                    let current_cursor  : SourceCursor<'a> = unsafe { std::mem::transmute(self.peek_token().as_ref().unwrap().span.start.clone()) };
+                    let current_cursor  : SourceCursor<'a> = unsafe { std::mem::transmute(self.current_token()?.span.start.clone()) };
                     if let Ok(expr) = self.parse_expr() {
-                        let cursor2 : SourceCursor<'a> = unsafe { std::mem::transmute(self.next_token()?.as_ref().unwrap().span.start.clone()) };
+                        let last = self.current.clone();
+                        let cursor2 : SourceCursor<'a> = match self.next_token()? {
+                            Some(t) => unsafe { std::mem::transmute(t.span.start.clone()) },
+                            None => return Err(self.expected("\"}\""))
+                        };
                         if self.peek_token().as_ref().map_or(false, |t| t.kind == TokenKind::RCurly){
-                            let rcurly = self.peek_token().as_ref().unwrap().clone();
+                            let rcurly = self.current_token()?.clone();
                             return Ok(Expr::BlockExpr(super::Block { 
                                 lcurly,
                                 body: statements,
@@ -292,12 +361,12 @@ impl<'a> AstBuilder<'a> {
                         }else {
                             let semicolon = if expr.require_semicolon(){
                                 if self.peek_token().as_ref().map_or(false, |t| t.kind != TokenKind::Semicolon) {
-                                    return Err(self.expected("\";\""));
+                                    return Err(self.missing_semicolon(last.as_ref()));
                                 }
-                                Some(self.peek_token().as_ref().unwrap().clone())
+                                Some(self.current_token()?.clone())
                             }else {
+                                // the next token is read again by the loop
                                 self.lexer.reader.goto(cursor2);
-                                self.next_token()?;
                                 None
                             };
                             statements.push(Statement::Expr(super::ExprStmt { 
@@ -311,7 +380,7 @@ impl<'a> AstBuilder<'a> {
                         statements.push(self.parse_statment()?);
                     }
                 }
-                let rcurly = self.peek_token().as_ref().unwrap().clone();
+                let rcurly = self.current_token()?.clone();
                 Ok(Expr::BlockExpr(super::Block { 
                     lcurly,
                     body: statements,
@@ -324,7 +393,7 @@ impl<'a> AstBuilder<'a> {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::LParan) {
                         return Err(self.expected("\"(\""))
                     }
-                    let lparen = self.peek_token().as_ref().unwrap().clone();
+                    let lparen = self.current_token()?.clone();
                     if self.next_token()?.is_none() {
                         return Err(self.expected("expr"))
                     }
@@ -332,7 +401,7 @@ impl<'a> AstBuilder<'a> {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::RParan) {
                         return Err(self.expected("\")\""))
                     }
-                    let rparen = self.peek_token().as_ref().unwrap().clone();
+                    let rparen = self.current_token()?.clone();
                     if self.next_token()?.is_none() {
                         return Err(self.expected("expression"))
                     }
@@ -363,7 +432,7 @@ impl<'a> AstBuilder<'a> {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::LParan) {
                         return Err(self.expected("\"(\""))
                     }
-                    let lparen = self.peek_token().as_ref().unwrap().clone();
+                    let lparen = self.current_token()?.clone();
                     if self.next_token()?.is_none() {
                         return Err(self.expected("expr"))
                     }
@@ -371,7 +440,7 @@ impl<'a> AstBuilder<'a> {
                     if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::RParan) {
                         return Err(self.expected("\")\""))
                     }
-                    let rparen = self.peek_token().as_ref().unwrap().clone();
+                    let rparen = self.current_token()?.clone();
                     if self.next_token()?.is_none() {
                         return Err(self.expected("expression"))
                     }
@@ -397,13 +466,13 @@ impl<'a> AstBuilder<'a> {
             },
             TokenKind::Ident => {
                 let ident = token.clone();
-                let current_cursor  : SourceCursor<'a> = unsafe { std::mem::transmute(self.peek_token().as_ref().unwrap().span.start.clone()) };
+                let current_cursor  : SourceCursor<'a> = unsafe { std::mem::transmute(self.current_token()?.span.start.clone()) };
                 if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::Equal) {
                     self.lexer.reader.goto(current_cursor);
                     self.next_token()?;
                     return self.parse_arithmetic_expr()
                 }
-                let eq_token = self.peek_token().as_ref().unwrap().clone();
+                let eq_token = self.current_token()?.clone();
                 if self.next_token()?.is_none() {
                     return Err(self.expected("expression"))
                 }
@@ -419,7 +488,7 @@ impl<'a> AstBuilder<'a> {
     }
 
     pub fn parse_arithmetic_expr(&mut self) -> Result<Expr<'a>, CompilerError> {
-        let token = self.peek_token().as_ref().unwrap();
+        let token = self.current_token()?;
         // parse unary op
         let unary_op = match &token.kind {
             TokenKind::Plus => Some(UnaryOp{
@@ -442,50 +511,29 @@ impl<'a> AstBuilder<'a> {
                 }
             }
         }
-        let token = self.peek_token().as_ref().unwrap();
-        let start_cursor = unsafe { std::mem::transmute(self.peek_token().as_ref().unwrap().span.start.clone()) };
+        let token = self.current_token()?;
+        let start_cursor = unsafe { std::mem::transmute(self.current_token()?.span.start.clone()) };
         // parse primary expression
         let primary = match &token.kind {
-            TokenKind::Int => {
-                let (base, value) = match token.span.data.get(..2) {
-                    Some("0x") => (16, &token.span.data[2..]),
-                    Some("0b") => (2, &token.span.data[2..]),
-                    _ => (10, token.span.data)
-                };
-                let value = match i128::from_str_radix(value, base) {
-                    Ok(i) => Ok(i),
-                    Err(_) => Err(CompilerError::new(
-                            CompilerErrorKind::BadToken,
-                            "cannot parse integer litteral",
-                            token.span.source.path.to_str().unwrap(),
-                            token.span.source.get_line(token.span.start.line).unwrap(),
-                            token.span.start.line as u32,
-                            token.span.start.collumn as u32,
-                            None))
-                }?;
-                let token = token.clone();
-                Ok(PrimaryExpr::IntLit(super::IntLit { 
-                    token,
-                    value
-                }))
-            },
+            TokenKind::Int => Ok(PrimaryExpr::IntLit(super::IntLit::parse(token.clone())?)),
+            TokenKind::Str => Ok(PrimaryExpr::StrLit(super::StrLit::parse(token.clone())?)),
             TokenKind::LParan => {
                     let lparen = token.clone();
                     self.next_token()?;
                     if self.peek_token().as_ref().map_or(false, |x| x.kind == TokenKind::RParan) {
                         return Ok(Expr::PrimaryExpr(PrimaryExpr::VoidLit(super::VoidLit { 
                             lparen,
-                            rparen: self.peek_token().as_ref().unwrap().clone()
+                            rparen: self.current_token()?.clone()
                         })))
                     }
                     let sub_expr = self.parse_expr()?;
-                    if !self.peek_token().as_ref().map_or(false, |x| x.kind == TokenKind::RParan) {
+                    if !self.next_token()?.as_ref().map_or(false, |x| x.kind == TokenKind::RParan) {
                         return Err(self.expected("\")\""))
                     }
                     Ok(PrimaryExpr::Paren(super::ParenExpr {
                         lparen,
                         expr : Box::new(sub_expr),
-                        rparen : self.peek_token().as_ref().unwrap().clone()
+                        rparen : self.current_token()?.clone()
                     }))
             },
             TokenKind::Ident => {
@@ -509,26 +557,10 @@ impl<'a> AstBuilder<'a> {
             }),
             None => Expr::PrimaryExpr(primary)
         };
-        let cursor = self.peek_token().as_ref().unwrap().span.start;
+        let cursor = self.current_token()?.span.start;
         let token = self.next_token()?;
         let bin_op_kind = token.as_ref()
-            .map(|t| t.kind.clone())
-            .and_then(|k| match k {
-                TokenKind::Plus => Some(super::BinOpKind::Add),
-                TokenKind::Minus => Some(super::BinOpKind::Sub),
-                TokenKind::Times => Some(super::BinOpKind::Mul),
-                TokenKind::Divider => Some(super::BinOpKind::Div),
-                TokenKind::DoubleEqual => Some(super::BinOpKind::Equal),
-                TokenKind::NotEqual => Some(super::BinOpKind::NotEqual),
-                TokenKind::Greater => Some(super::BinOpKind::Greater),
-                TokenKind::GreaterEqual => Some(super::BinOpKind::GreaterEqual),
-                TokenKind::LesserEqual => Some(super::BinOpKind::LesserEqual),
-                TokenKind::Lesser => Some(super::BinOpKind::Lesser),
-                TokenKind::LShift => Some(super::BinOpKind::LShift),
-                TokenKind::RShift => Some(super::BinOpKind::RShift),
-                _ => None
-            }
-        );
+            .and_then(|t| super::BinOpKind::from_token(&t.kind));
 
         match bin_op_kind {
             Some(bin_op_kind) => { 
@@ -567,6 +599,22 @@ mod tests {
     use core::panic;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_from_tokens() {
+        // backtracks on the calls, the else and the variable named test
+        let buf = SourceBuffer::from_string("tokens.jol", "let test = 1;\ntest;\nif (test) f(test) else { test + 2 }\ntest \"a\" { print(\"b\"); }".to_string());
+        let tokens = Lexer::new(&buf).collect::<Result<Vec<_>, CompilerError>>().unwrap();
+        let program = AstBuilder::from_tokens(&buf, tokens).parse_program().unwrap();
+        assert_eq!(program, AstBuilder::from(Lexer::new(&buf)).parse_program().unwrap());
+        // the positions of the errors don't change either
+        let buf = SourceBuffer::from_string("tokens.jol", "let a = (1 + ".to_string());
+        let tokens = Lexer::new(&buf).collect::<Result<Vec<_>, CompilerError>>().unwrap();
+        assert_eq!(
+            AstBuilder::from_tokens(&buf, tokens).parse_program().unwrap_err().to_string(),
+            AstBuilder::from(Lexer::new(&buf)).parse_program().unwrap_err().to_string()
+        );
+    }
+
     #[test]
     fn test_var_decl() {
         let buf = SourceBuffer {
@@ -1421,6 +1469,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_paren_expr() {
+        // the closing parenthesis ends the sub expression and the expression goes on after it
+        let buf = SourceBuffer {
+            path : PathBuf::from("test1.jol"),
+            buffer : String::from("let a = (1 + 2) * 3;")
+        };
+        let program = match AstBuilder::from(Lexer::new(&buf)).parse_program() {
+            Ok(p) => p,
+            Err(e) => panic!("{}", e)
+        };
+        let value = match &program.0[..] {
+            [Statement::VarDecl(VarDecl { value : Some(value), .. })] => value,
+            p => panic!("{:?}", p)
+        };
+        match value {
+            Expr::BinExpr(BinExpr { left, op : BinOp { kind : BinOpKind::Mul, .. }, .. }) => match left.as_ref() {
+                Expr::PrimaryExpr(PrimaryExpr::Paren(paren)) => {
+                    assert_eq!(paren.rparen.span.data, ")");
+                    assert!(matches!(paren.expr.as_ref(), Expr::BinExpr(BinExpr { op : BinOp { kind : BinOpKind::Add, .. }, .. })));
+                },
+                e => panic!("{:?}", e)
+            },
+            e => panic!("{:?}", e)
+        }
+    }
+
     // TODO : rewrite the rst of the tests
 
     /*
@@ -1,10 +1,9 @@
 mod builder;
 use std::str::FromStr;
-pub mod generators;
 pub use builder::AstBuilder;
 use either::Either;
 use anyhow::Result;
-use crate::{compiler_error::{CompilerError, CompilerErrorKind}, lexer::Token};
+use crate::{compiler_error::{CompilerError, CompilerErrorKind}, lexer::{Token, TokenKind}, source_span::SourceSpan};
 
 pub type Ident<'a> = Token<'a>;
 
@@ -75,6 +74,14 @@ pub struct Noop<'a> {
     pub semicolon : Token<'a>
 }
 
+/// test "name" { ... }, only compiled by `jolang test`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Test<'a> {
+    pub test_kw : Token<'a>,
+    pub name : StrLit<'a>,
+    pub body : Block<'a>
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Statement<'a> {
     Return(Return<'a>),
@@ -82,7 +89,8 @@ pub enum Statement<'a> {
     Continue(Continue<'a>),
     Noop(Noop<'a>),
     VarDecl(VarDecl<'a>),
-    Expr(ExprStmt<'a>)
+    Expr(ExprStmt<'a>),
+    Test(Test<'a>)
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -157,6 +165,7 @@ pub enum PrimaryExpr<'a> {
     Ident(Ident<'a>),
     IntLit(IntLit<'a>),
     VoidLit(VoidLit<'a>),
+    StrLit(StrLit<'a>),
     /// (Expr) (e. g. (5 + 5))
     Paren(ParenExpr<'a>)
 }
@@ -201,6 +210,89 @@ pub struct VoidLit<'a> {
     pub rparen : Token<'a>
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct StrLit<'a> {
+    pub token : Token<'a>,
+    /// the text without the quotes and with the escape sequences replaced
+    pub value : String
+}
+
+impl<'a> Program<'a> {
+    pub fn new(statements : Vec<Statement<'a>>) -> Self {
+        Self(statements)
+    }
+
+    pub fn statements(&self) -> &Vec<Statement<'a>> {
+        &self.0
+    }
+}
+
+impl<'a> IntLit<'a> {
+    /// parse a decimal, hexadecimal (0x) or binary (0b) litteral
+    pub fn parse(token : Token<'a>) -> Result<Self, CompilerError> {
+        let (base, value) = match token.span.data.get(..2) {
+            Some("0x") => (16, &token.span.data[2..]),
+            Some("0b") => (2, &token.span.data[2..]),
+            _ => (10, token.span.data)
+        };
+        match i128::from_str_radix(value, base) {
+            Ok(value) => Ok(Self {
+                token,
+                value
+            }),
+            Err(_) => Err(CompilerError::from_span(
+                CompilerErrorKind::BadToken,
+                "cannot parse integer litteral",
+                &token.span,
+                None))
+        }
+    }
+}
+
+impl<'a> StrLit<'a> {
+    /// parse a string litteral, \n, \t, \" and \\ are the escape sequences
+    pub fn parse(token : Token<'a>) -> Result<Self, CompilerError> {
+        let data = token.span.data;
+        let mut value = String::new();
+        let mut chars = data[1..data.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c) if c == '"' || c == '\\' => value.push(c),
+                _ => return Err(CompilerError::from_span(
+                    CompilerErrorKind::BadToken,
+                    "unknown escape sequence in string litteral",
+                    &token.span,
+                    None))
+            }
+        }
+        Ok(Self {
+            token,
+            value
+        })
+    }
+}
+
+impl Statement<'_> {
+    /// span of the first token of the statement
+    pub fn span(&self) -> &SourceSpan<'_> {
+        match self {
+            Self::Return(stmt) => &stmt.return_kw.span,
+            Self::Break(stmt) => &stmt.break_kw.span,
+            Self::Continue(stmt) => &stmt.continue_kw.span,
+            Self::Noop(stmt) => &stmt.semicolon.span,
+            Self::VarDecl(stmt) => &stmt.let_kw.span,
+            Self::Expr(stmt) => stmt.expr.span(),
+            Self::Test(stmt) => &stmt.test_kw.span
+        }
+    }
+}
+
 impl Expr<'_> {
     pub fn require_semicolon(&self) -> bool{
         match self {
@@ -214,9 +306,64 @@ impl Expr<'_> {
             _ => true
         }
     }
+
+    /// span of the first token of the expression
+    pub fn span(&self) -> &SourceSpan<'_> {
+        match self {
+            Self::WhileExpr(expr) => &expr.while_kw.span,
+            Self::LoopExpr(expr) => &expr.loop_kw.span,
+            Self::BlockExpr(expr) => &expr.lcurly.span,
+            Self::IfExpr(expr) => &expr.if_kw.span,
+            Self::AssignExpr(expr) => &expr.name.span,
+            Self::BinExpr(expr) => expr.left.span(),
+            Self::UnaryExpr(expr) => &expr.op.token.span,
+            Self::PrimaryExpr(expr) => expr.span()
+        }
+    }
+}
+
+impl PrimaryExpr<'_> {
+    /// span of the first token of the expression
+    pub fn span(&self) -> &SourceSpan<'_> {
+        match self {
+            Self::Call(call) => &call.name.span,
+            Self::Ident(ident) => &ident.span,
+            Self::IntLit(lit) => &lit.token.span,
+            Self::VoidLit(lit) => &lit.lparen.span,
+            Self::StrLit(lit) => &lit.token.span,
+            Self::Paren(expr) => &expr.lparen.span
+        }
+    }
+}
+
+impl<'a> Call<'a> {
+    pub fn args(&self) -> Vec<&Expr<'a>> {
+        self.first_arg.iter()
+            .map(|x| x.as_ref())
+            .chain(self.other_args.iter().map(|x| &x.1))
+            .collect()
+    }
 }
 
 impl BinOpKind {
+    pub fn from_token(kind : &TokenKind) -> Option<Self> {
+        match kind {
+            TokenKind::Plus => Some(Self::Add),
+            TokenKind::Minus => Some(Self::Sub),
+            TokenKind::Times => Some(Self::Mul),
+            TokenKind::Divider => Some(Self::Div),
+            TokenKind::DoubleEqual => Some(Self::Equal),
+            TokenKind::NotEqual => Some(Self::NotEqual),
+            TokenKind::Greater => Some(Self::Greater),
+            TokenKind::GreaterEqual => Some(Self::GreaterEqual),
+            TokenKind::LesserEqual => Some(Self::LesserEqual),
+            TokenKind::Lesser => Some(Self::Lesser),
+            TokenKind::LShift => Some(Self::LShift),
+            TokenKind::RShift => Some(Self::RShift),
+            _ => None
+        }
+    }
+
     pub fn precedence(&self) -> u8 {
         match self {
             Self::Add => 1,
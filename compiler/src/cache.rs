@@ -0,0 +1,214 @@
+//! cache of the compiled objects, used to run the same sources without compiling them again
+use std::fs::{self, File};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use anyhow::Result;
+use jolang_shared::ir::{reader::read, writer::write, IrObject};
+use crate::{compile, source_buffer::SourceBuffer};
+
+/// default bound of the total size of the cached objects (64 MiB)
+pub const DEFAULT_MAX_SIZE : u64 = 64 * 1024 * 1024;
+const EXTENSION : &str = "joo";
+
+pub struct Cache {
+    dir : PathBuf,
+    /// the oldest objects are removed when the cache gets bigger
+    max_size : u64
+}
+
+/// a cached object
+struct Entry {
+    path : PathBuf,
+    size : u64,
+    /// last time the object was used
+    used : SystemTime
+}
+
+impl Cache {
+    pub fn new(dir : PathBuf, max_size : u64) -> Self {
+        Self {
+            dir,
+            max_size
+        }
+    }
+
+    /// the cache of the user : $JOLANG_CACHE_DIR, $XDG_CACHE_HOME/jolang or ~/.cache/jolang
+    /// the size bound can be set in bytes with $JOLANG_CACHE_SIZE
+    pub fn user() -> Option<Self> {
+        let dir = std::env::var_os("JOLANG_CACHE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("XDG_CACHE_HOME").map(|d| PathBuf::from(d).join("jolang")))
+            .or_else(|| std::env::var_os("HOME").map(|d| PathBuf::from(d).join(".cache").join("jolang")))?;
+        let max_size = std::env::var("JOLANG_CACHE_SIZE").ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_MAX_SIZE);
+        Some(Self::new(dir, max_size))
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// key of a source compiled with the given flags by this version of the compiler
+    pub fn key(source : &str, flags : &[&str]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(jolang_shared::VERSION_STR.as_bytes());
+        for flag in flags {
+            hasher.update(&[0]);
+            hasher.update(flag.as_bytes());
+        }
+        hasher.update(&[0]);
+        hasher.update(source.as_bytes());
+        hasher.finalize().to_hex().to_string()
+    }
+
+    fn path(&self, key : &str) -> PathBuf {
+        self.dir.join(key).with_extension(EXTENSION)
+    }
+
+    /// the cached object of a key, it becomes the most recently used one
+    pub fn get(&self, key : &str) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let object = fs::read(&path).ok()?;
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(object)
+    }
+
+    /// add an object to the cache and evict the oldest ones if the cache is too big
+    pub fn insert(&self, key : &str, object : &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // another process can read the object while it is written
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        fs::write(&tmp, object)?;
+        fs::rename(&tmp, self.path(key))?;
+        self.evict()
+    }
+
+    /// compile a source or read its object from the cache
+    pub fn compile(&self, source : &SourceBuffer) -> Result<IrObject> {
+        let key = Self::key(&source.buffer, &[]);
+        if let Some(object) = self.get(&key) {
+            // a corrupted object is compiled again
+            if let Ok(object) = read(&mut Cursor::new(object)) {
+                return Ok(object)
+            }
+        }
+        let mut object = Cursor::new(vec![]);
+        write(compile(source)?, &mut object)?;
+        let object = object.into_inner();
+        // the source can still be run without a cache
+        let _ = self.insert(&key, &object);
+        read(&mut Cursor::new(object))
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries = vec![];
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(e.into())
+        };
+        for entry in dir {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != EXTENSION) {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            entries.push(Entry {
+                path,
+                size : metadata.len(),
+                used : metadata.modified()?
+            });
+        }
+        Ok(entries)
+    }
+
+    /// total size of the cached objects
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|e| e.size).sum())
+    }
+
+    /// remove the least recently used objects until the cache fits in its bound
+    pub fn evict(&self) -> Result<()> {
+        let mut entries = self.entries()?;
+        let mut size : u64 = entries.iter().map(|e| e.size).sum();
+        entries.sort_by(|a, b| a.used.cmp(&b.used).then_with(|| a.path.cmp(&b.path)));
+        for entry in entries {
+            if size <= self.max_size {
+                break;
+            }
+            fs::remove_file(&entry.path)?;
+            size -= entry.size;
+        }
+        Ok(())
+    }
+
+    /// remove every cached object, returns the number of removed objects
+    pub fn clean(&self) -> Result<usize> {
+        let entries = self.entries()?;
+        for entry in &entries {
+            fs::remove_file(&entry.path)?;
+        }
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread::sleep, time::Duration};
+
+    fn cache(name : &str, max_size : u64) -> Cache {
+        let dir = std::env::temp_dir().join(format!("jolang-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Cache::new(dir, max_size)
+    }
+
+    #[test]
+    fn test_key() {
+        let key = Cache::key("print(1);", &[]);
+        assert_eq!(key, Cache::key("print(1);", &[]));
+        assert_ne!(key, Cache::key("print(2);", &[]));
+        assert_ne!(key, Cache::key("print(1);", &["-O1"]));
+        // the flags and the source can't be confused
+        assert_ne!(Cache::key("a", &["b"]), Cache::key("b", &["a"]));
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = cache("get", DEFAULT_MAX_SIZE);
+        assert_eq!(cache.get("a"), None);
+        cache.insert("a", b"object a").unwrap();
+        assert_eq!(cache.get("a"), Some(b"object a".to_vec()));
+        cache.insert("a", b"new object").unwrap();
+        assert_eq!(cache.get("a"), Some(b"new object".to_vec()));
+        cache.insert("b", b"object b").unwrap();
+        assert_eq!(cache.size().unwrap(), 18);
+        assert_eq!(cache.clean().unwrap(), 2);
+        assert_eq!(cache.get("b"), None);
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_eviction() {
+        let cache = cache("evict", 10);
+        cache.insert("a", b"aaaa").unwrap();
+        sleep(Duration::from_millis(20));
+        cache.insert("b", b"bbbb").unwrap();
+        sleep(Duration::from_millis(20));
+        // a is used so b is the least recently used
+        assert!(cache.get("a").is_some());
+        sleep(Duration::from_millis(20));
+        cache.insert("c", b"cccc").unwrap();
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+        // an object bigger than the bound is not kept
+        cache.insert("d", b"dddddddddddd").unwrap();
+        assert!(cache.get("d").is_none());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
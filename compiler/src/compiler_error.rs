@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::fmt;
+use crate::{error_codes, fix::Fix, source_buffer::SourceBuffer, source_span::SourceSpan};
 
 macro_rules! enum_str {
     (
@@ -13,7 +14,7 @@ macro_rules! enum_str {
         }
 
         impl $name {
-            fn to_str(&self) -> &'static str {
+            pub fn to_str(&self) -> &'static str {
                 match self {
                     $($name::$variant => stringify!($variant)),*
                 }
@@ -32,11 +33,59 @@ enum_str!{
         UnderlaredVariable,
         ReeclaretedVariable,
         UnknownFunction,
+        UnknownType,
+        OutsideOfLoop,
+        MismatchedTypes,
+        BadArgumentCount,
+    }
+}
+
+impl CompilerErrorKind {
+    /// all the kinds, in the order of their codes
+    pub const ALL : [CompilerErrorKind; 10] = [
+        Self::BadToken,
+        Self::UnexpectedToken,
+        Self::Expected,
+        Self::UnderlaredVariable,
+        Self::ReeclaretedVariable,
+        Self::UnknownFunction,
+        Self::UnknownType,
+        Self::OutsideOfLoop,
+        Self::MismatchedTypes,
+        Self::BadArgumentCount
+    ];
+
+    /// stable code of the error, codes must never be reused or changed
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadToken => "E0001",
+            Self::UnexpectedToken => "E0002",
+            Self::Expected => "E0003",
+            Self::UnderlaredVariable => "E0004",
+            Self::ReeclaretedVariable => "E0005",
+            Self::UnknownFunction => "E0006",
+            Self::UnknownType => "E0007",
+            Self::OutsideOfLoop => "E0008",
+            Self::MismatchedTypes => "E0009",
+            Self::BadArgumentCount => "E0010",
+        }
+    }
+
+    pub fn from_code(code : &str) -> Option<Self> {
+        let code = code.trim().to_uppercase();
+        Self::ALL.iter()
+            .find(|k| k.code() == code)
+            .cloned()
+    }
+
+    /// long description of the error with examples
+    pub fn explain(&self) -> &'static str {
+        error_codes::explanation(self)
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct CompilerError {
+struct ErrorData {
     kind : CompilerErrorKind,
     message : String,
     file : String,
@@ -44,7 +93,15 @@ pub struct CompilerError {
     line_number : u32,
     col_number : u32,
     /// message, snippet
-    hint : Option<(String, String)>
+    hint : Option<(String, String)>,
+    /// edit of the source that fixes the error
+    fix : Option<Fix>
+}
+
+/// the data is boxed so the results of the compiler stay small
+#[derive(Debug, Clone)]
+pub struct CompilerError {
+    data : Box<ErrorData>
 }
 
 impl CompilerError {
@@ -57,40 +114,98 @@ impl CompilerError {
         }
 
         Self {
-            kind,
-            message: message.to_string(),
-            file: file.to_string(),
-            line: line.to_string(),
-            line_number,
-            col_number : if col_number == 0 { 1 } else { col_number }, 
-            hint
+            data : Box::new(ErrorData {
+                kind,
+                message: message.to_string(),
+                file: file.to_string(),
+                line: line.to_string(),
+                line_number,
+                col_number : if col_number == 0 { 1 } else { col_number }, 
+                hint,
+                fix : None
+            })
         }
     }
+
+    pub fn from_span(kind: CompilerErrorKind, message: &str, span : &SourceSpan, hint : Option<(&str, &str)>) -> Self {
+        Self::new(
+            kind,
+            message,
+            span.source.path.to_str().unwrap_or_default(),
+            span.source.get_line(span.start.line).unwrap_or_default(),
+            span.start.line as u32,
+            span.start.collumn as u32,
+            hint)
+    }
+
+    /// attach a fix to the error, the hint shows the line once fixed
+    pub fn with_fix(mut self, message : &str, fix : Fix, source : &SourceBuffer) -> Self {
+        self.data.hint = Some((message.to_string(), fix.preview(&source.buffer)));
+        self.data.fix = Some(fix);
+        self
+    }
+
+    /// give the file and the source line to an error that was made without the source (e.g. by the generator)
+    pub fn with_source(mut self, source : &SourceBuffer) -> Self {
+        self.data.file = source.path.to_str().unwrap_or_default().to_string();
+        self.data.line = source.get_line(self.data.line_number as usize).unwrap_or_default().to_string();
+        self
+    }
+
+    pub fn kind(&self) -> &CompilerErrorKind {
+        &self.data.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.data.message
+    }
+
+    pub fn line_number(&self) -> u32 {
+        self.data.line_number
+    }
+
+    pub fn col_number(&self) -> u32 {
+        self.data.col_number
+    }
+
+    /// the source line of the error
+    pub fn line(&self) -> &str {
+        &self.data.line
+    }
+
+    pub fn fix(&self) -> Option<&Fix> {
+        self.data.fix.as_ref()
+    }
 }
 
 impl fmt::Display for CompilerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut formatted = format!(
             "\x1b[91;1mError\x1b[0;1m[{}]: {}\x1b[0;0m\n  \x1b[36;1m-->\x1b[0m {}:{}:{}\n    \x1b[36;1m|\n{:4}|\x1b[0m {}\n    \x1b[36;1m| \x1b[91m{}\n\x1b[0m",
-            self.kind.to_str(),
-            self.message,
-            self.file,
-            self.line_number,
-            self.col_number,
-            self.line_number,
-            self.line,
-            " ".repeat((self.col_number - 1) as usize) + "^~~~"
+            self.data.kind.code(),
+            self.data.message,
+            self.data.file,
+            self.data.line_number,
+            self.data.col_number,
+            self.data.line_number,
+            self.data.line,
+            " ".repeat((self.data.col_number - 1) as usize) + "^~~~"
         );
 
-        if let Some(hint) = &self.hint {
+        if let Some(hint) = &self.data.hint {
             formatted.push_str(&format!(
                 "\x1b[92;1mhelp\x1b[0m: {}\n    \x1b[36;1m|\n{:4}| {}\n    |\x1b[0m\n",
                 hint.0,
-                self.line_number,
+                self.data.line_number,
                 hint.1
             ));
         }
 
+        formatted.push_str(&format!(
+            "\x1b[36;1m=\x1b[0m for more information about this error, try `jolang explain {}`\n",
+            self.data.kind.code()
+        ));
+
         write!(f, "{}", formatted)
     }
 }
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use crate::ast::{Call, Expr, PrimaryExpr, Program, Statement};
+use crate::compiler_error::{CompilerError, CompilerErrorKind};
+use crate::lexer::{LosslessToken, Token};
+use super::{CstElement, CstNode, NodeKind};
+
+/// build the cst from an ast and the lossless tokens of the same source
+pub struct CstBuilder<'a> {
+    tokens : Vec<Option<LosslessToken<'a>>>,
+    // byte offset : index in tokens
+    offsets : HashMap<usize, usize>
+}
+
+impl<'a> CstBuilder<'a> {
+    pub fn new(tokens : Vec<LosslessToken<'a>>) -> Self {
+        Self {
+            offsets : tokens.iter()
+                .enumerate()
+                .map(|(i, t)| (t.token.span.offset(), i))
+                .collect(),
+            tokens : tokens.into_iter().map(Some).collect()
+        }
+    }
+
+    pub fn build(mut self, program : &Program<'a>) -> Result<CstNode<'a>, CompilerError> {
+        let mut children = vec![];
+        for s in program.statements() {
+            children.push(self.statement(s)?);
+        }
+        // every token must be in the tree for the round trip
+        if let Some(t) = self.tokens.iter().flatten().next() {
+            return Err(CompilerError::from_span(
+                CompilerErrorKind::UnexpectedToken,
+                "token missing from the syntax tree",
+                &t.token.span,
+                None))
+        }
+        Ok(CstNode::new(NodeKind::Program, children))
+    }
+
+    fn token(&mut self, token : &Token<'a>) -> Result<CstElement<'a>, CompilerError> {
+        self.offsets.get(&token.span.offset())
+            .and_then(|i| self.tokens[*i].take())
+            .map(CstElement::Token)
+            .ok_or_else(|| CompilerError::from_span(
+                CompilerErrorKind::UnexpectedToken,
+                "token used twice in the syntax tree",
+                &token.span,
+                None))
+    }
+
+    fn opt_token(&mut self, token : &Option<Token<'a>>, children : &mut Vec<CstElement<'a>>) -> Result<(), CompilerError> {
+        if let Some(t) = token {
+            children.push(self.token(t)?);
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement : &Statement<'a>) -> Result<CstElement<'a>, CompilerError> {
+        let (kind, children) = match statement {
+            Statement::Return(ret) => (NodeKind::Return, vec![
+                self.token(&ret.return_kw)?,
+                self.expr(&ret.value)?,
+                self.token(&ret.semicolon)?
+            ]),
+            Statement::Break(b) => (NodeKind::Break, vec![
+                self.token(&b.break_kw)?,
+                self.token(&b.semicolon)?
+            ]),
+            Statement::Continue(c) => (NodeKind::Continue, vec![
+                self.token(&c.continue_kw)?,
+                self.token(&c.semicolon)?
+            ]),
+            Statement::Noop(n) => (NodeKind::Noop, vec![self.token(&n.semicolon)?]),
+            Statement::VarDecl(decl) => {
+                let mut children = vec![
+                    self.token(&decl.let_kw)?,
+                    self.token(&decl.name)?
+                ];
+                self.opt_token(&decl.colon_token, &mut children)?;
+                self.opt_token(&decl.type_name, &mut children)?;
+                self.opt_token(&decl.eq_token, &mut children)?;
+                if let Some(v) = &decl.value {
+                    children.push(self.expr(v)?);
+                }
+                children.push(self.token(&decl.semicolon)?);
+                (NodeKind::VarDecl, children)
+            },
+            Statement::Expr(stmt) => {
+                let mut children = vec![self.expr(&stmt.expr)?];
+                self.opt_token(&stmt.semicolon, &mut children)?;
+                (NodeKind::ExprStmt, children)
+            },
+            Statement::Test(test) => (NodeKind::Test, vec![
+                self.token(&test.test_kw)?,
+                self.token(&test.name.token)?,
+                self.expr(&Expr::BlockExpr(test.body.clone()))?
+            ])
+        };
+        Ok(CstElement::Node(CstNode::new(kind, children)))
+    }
+
+    fn expr(&mut self, expr : &Expr<'a>) -> Result<CstElement<'a>, CompilerError> {
+        let (kind, children) = match expr {
+            Expr::WhileExpr(w) => (NodeKind::While, vec![
+                self.token(&w.while_kw)?,
+                self.token(&w.lparen)?,
+                self.expr(&w.cond)?,
+                self.token(&w.rparen)?,
+                self.expr(&w.body)?
+            ]),
+            Expr::LoopExpr(l) => (NodeKind::Loop, vec![
+                self.token(&l.loop_kw)?,
+                self.expr(&l.body)?
+            ]),
+            Expr::BlockExpr(b) => {
+                let mut children = vec![self.token(&b.lcurly)?];
+                for s in &b.body {
+                    children.push(self.statement(s)?);
+                }
+                if let Some(ret) = &b.ret {
+                    children.push(self.expr(ret)?);
+                }
+                children.push(self.token(&b.rcurly)?);
+                (NodeKind::Block, children)
+            },
+            Expr::IfExpr(i) => {
+                let mut children = vec![
+                    self.token(&i.if_kw)?,
+                    self.token(&i.lparen)?,
+                    self.expr(&i.cond)?,
+                    self.token(&i.rparen)?,
+                    self.expr(&i.then)?
+                ];
+                self.opt_token(&i.else_kw, &mut children)?;
+                if let Some(e) = &i._else {
+                    children.push(self.expr(e)?);
+                }
+                (NodeKind::If, children)
+            },
+            Expr::AssignExpr(a) => (NodeKind::Assign, vec![
+                self.token(&a.name)?,
+                self.token(&a.eq_token)?,
+                self.expr(&a.value)?
+            ]),
+            Expr::BinExpr(b) => (NodeKind::Binary, vec![
+                self.expr(&b.left)?,
+                self.token(&b.op.token)?,
+                self.expr(&b.right)?
+            ]),
+            Expr::UnaryExpr(u) => (NodeKind::Unary, vec![
+                self.token(&u.op.token)?,
+                self.primary(&u.primary)?
+            ]),
+            Expr::PrimaryExpr(p) => return self.primary(p)
+        };
+        Ok(CstElement::Node(CstNode::new(kind, children)))
+    }
+
+    fn primary(&mut self, expr : &PrimaryExpr<'a>) -> Result<CstElement<'a>, CompilerError> {
+        let (kind, children) = match expr {
+            PrimaryExpr::Call(c) => (NodeKind::Call, self.call(c)?),
+            PrimaryExpr::Ident(i) => (NodeKind::Ident, vec![self.token(i)?]),
+            PrimaryExpr::IntLit(lit) => (NodeKind::IntLit, vec![self.token(&lit.token)?]),
+            PrimaryExpr::StrLit(lit) => (NodeKind::StrLit, vec![self.token(&lit.token)?]),
+            PrimaryExpr::VoidLit(lit) => (NodeKind::VoidLit, vec![
+                self.token(&lit.lparen)?,
+                self.token(&lit.rparen)?
+            ]),
+            PrimaryExpr::Paren(p) => (NodeKind::Paren, vec![
+                self.token(&p.lparen)?,
+                self.expr(&p.expr)?,
+                self.token(&p.rparen)?
+            ])
+        };
+        Ok(CstElement::Node(CstNode::new(kind, children)))
+    }
+
+    fn call(&mut self, call : &Call<'a>) -> Result<Vec<CstElement<'a>>, CompilerError> {
+        let mut children = vec![
+            self.token(&call.name)?,
+            self.token(&call.lparen)?
+        ];
+        if let Some(arg) = &call.first_arg {
+            children.push(self.expr(arg)?);
+        }
+        for (comma, arg) in &call.other_args {
+            children.push(self.token(comma)?);
+            children.push(self.expr(arg)?);
+        }
+        children.push(self.token(&call.rparen)?);
+        Ok(children)
+    }
+}
@@ -0,0 +1,288 @@
+//! conversion of the cst to the ast
+use std::{iter::Peekable, slice::Iter};
+use crate::ast::{self, BinOp, BinOpKind, Expr, IntLit, PrimaryExpr, Program, Statement, StrLit, UnaryOp, UnaryOpKind};
+use crate::compiler_error::{CompilerError, CompilerErrorKind};
+use crate::lexer::{Token, TokenKind};
+use super::{CstElement, CstNode, NodeKind};
+
+/// error for a tree that doesn't have the shape of its kind (e.g. after an edit)
+fn malformed(node : &CstNode, expected : &str) -> CompilerError {
+    let message = format!("malformed {:?} node, expected : {}", node.kind, expected);
+    match node.first_token() {
+        Some(t) => CompilerError::from_span(CompilerErrorKind::Expected, &message, &t.token.span, None),
+        None => CompilerError::new(CompilerErrorKind::Expected, &message, "", "", 0, 0, None)
+    }
+}
+
+/// reads the children of a node in order
+struct Children<'c, 'a> {
+    node : &'c CstNode<'a>,
+    iter : Peekable<Iter<'c, CstElement<'a>>>
+}
+
+impl<'c, 'a> Children<'c, 'a> {
+    fn new(node : &'c CstNode<'a>) -> Self {
+        Self {
+            node,
+            iter : node.children.iter().peekable()
+        }
+    }
+
+    fn token(&mut self, expected : &str) -> Result<Token<'a>, CompilerError> {
+        match self.iter.next() {
+            Some(CstElement::Token(t)) => Ok(t.token.clone()),
+            _ => Err(malformed(self.node, expected))
+        }
+    }
+
+    /// the next token if it has the given kind
+    fn token_if(&mut self, kind : TokenKind) -> Option<Token<'a>> {
+        match self.iter.peek() {
+            Some(CstElement::Token(t)) if t.token.kind == kind => {
+                self.iter.next();
+                Some(t.token.clone())
+            },
+            _ => None
+        }
+    }
+
+    fn node(&mut self, expected : &str) -> Result<&'c CstNode<'a>, CompilerError> {
+        match self.iter.next() {
+            Some(CstElement::Node(n)) => Ok(n),
+            _ => Err(malformed(self.node, expected))
+        }
+    }
+
+    /// the next node if it is accepted by the filter
+    fn node_if(&mut self, filter : impl Fn(&NodeKind) -> bool) -> Option<&'c CstNode<'a>> {
+        match self.iter.peek() {
+            Some(CstElement::Node(n)) if filter(&n.kind) => {
+                self.iter.next();
+                Some(n)
+            },
+            _ => None
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr<'a>, CompilerError> {
+        lower_expr(self.node("expression")?)
+    }
+
+    fn end(&mut self) -> Result<(), CompilerError> {
+        match self.iter.next() {
+            None => Ok(()),
+            Some(_) => Err(malformed(self.node, "end of node"))
+        }
+    }
+}
+
+pub fn lower_program<'a>(node : &CstNode<'a>) -> Result<Program<'a>, CompilerError> {
+    if node.kind != NodeKind::Program {
+        return Err(malformed(node, "program"))
+    }
+    let mut children = Children::new(node);
+    let mut statements = vec![];
+    while let Some(s) = children.node_if(|_| true) {
+        statements.push(lower_statement(s)?);
+    }
+    children.end()?;
+    Ok(Program::new(statements))
+}
+
+fn lower_statement<'a>(node : &CstNode<'a>) -> Result<Statement<'a>, CompilerError> {
+    let mut c = Children::new(node);
+    let statement = match node.kind {
+        NodeKind::Return => Statement::Return(ast::Return {
+            return_kw : c.token("return")?,
+            value : c.expr()?,
+            semicolon : c.token("\";\"")?
+        }),
+        NodeKind::Break => Statement::Break(ast::Break {
+            break_kw : c.token("break")?,
+            semicolon : c.token("\";\"")?
+        }),
+        NodeKind::Continue => Statement::Continue(ast::Continue {
+            continue_kw : c.token("continue")?,
+            semicolon : c.token("\";\"")?
+        }),
+        NodeKind::Noop => Statement::Noop(ast::Noop {
+            semicolon : c.token("\";\"")?
+        }),
+        NodeKind::VarDecl => {
+            let let_kw = c.token("let")?;
+            let name = c.token("identifier")?;
+            let colon_token = c.token_if(TokenKind::Colon);
+            let type_name = match colon_token {
+                Some(_) => Some(c.token("type")?),
+                None => None
+            };
+            let eq_token = c.token_if(TokenKind::Equal);
+            let value = match eq_token {
+                Some(_) => Some(c.expr()?),
+                None => None
+            };
+            Statement::VarDecl(ast::VarDecl {
+                let_kw,
+                name,
+                colon_token,
+                type_name,
+                eq_token,
+                value,
+                semicolon : c.token("\";\"")?
+            })
+        },
+        NodeKind::ExprStmt => Statement::Expr(ast::ExprStmt {
+            expr : Box::new(c.expr()?),
+            semicolon : c.token_if(TokenKind::Semicolon)
+        }),
+        NodeKind::Test => {
+            let test_kw = c.token("test")?;
+            let name = StrLit::parse(c.token("string")?)?;
+            match c.expr()? {
+                Expr::BlockExpr(body) => Statement::Test(ast::Test {
+                    test_kw,
+                    name,
+                    body
+                }),
+                _ => return Err(malformed(node, "block"))
+            }
+        },
+        _ => return Err(malformed(node, "statement"))
+    };
+    c.end()?;
+    Ok(statement)
+}
+
+fn lower_expr<'a>(node : &CstNode<'a>) -> Result<Expr<'a>, CompilerError> {
+    if node.kind.is_primary() {
+        return Ok(Expr::PrimaryExpr(lower_primary(node)?))
+    }
+    let mut c = Children::new(node);
+    let expr = match node.kind {
+        NodeKind::While => Expr::WhileExpr(ast::While {
+            while_kw : c.token("while")?,
+            lparen : c.token("\"(\"")?,
+            cond : Box::new(c.expr()?),
+            rparen : c.token("\")\"")?,
+            body : Box::new(c.expr()?)
+        }),
+        NodeKind::Loop => Expr::LoopExpr(ast::Loop {
+            loop_kw : c.token("loop")?,
+            body : Box::new(c.expr()?)
+        }),
+        NodeKind::Block => {
+            let lcurly = c.token("\"{\"")?;
+            let mut body = vec![];
+            while let Some(s) = c.node_if(|k| k.is_statement()) {
+                body.push(lower_statement(s)?);
+            }
+            let ret = match c.node_if(|_| true) {
+                Some(e) => Some(Box::new(lower_expr(e)?)),
+                None => None
+            };
+            Expr::BlockExpr(ast::Block {
+                lcurly,
+                body,
+                ret,
+                rcurly : c.token("\"}\"")?
+            })
+        },
+        NodeKind::If => {
+            let if_kw = c.token("if")?;
+            let lparen = c.token("\"(\"")?;
+            let cond = Box::new(c.expr()?);
+            let rparen = c.token("\")\"")?;
+            let then = Box::new(c.expr()?);
+            let else_kw = c.token_if(TokenKind::Keyword(crate::lexer::KeywordType::Else));
+            let _else = match else_kw {
+                Some(_) => Some(Box::new(c.expr()?)),
+                None => None
+            };
+            Expr::IfExpr(ast::If {
+                if_kw,
+                lparen,
+                cond,
+                rparen,
+                then,
+                else_kw,
+                _else
+            })
+        },
+        NodeKind::Assign => Expr::AssignExpr(ast::Assignment {
+            name : c.token("identifier")?,
+            eq_token : c.token("\"=\"")?,
+            value : Box::new(c.expr()?)
+        }),
+        NodeKind::Binary => {
+            let left = Box::new(c.expr()?);
+            let token = c.token("operator")?;
+            let kind = BinOpKind::from_token(&token.kind).ok_or_else(|| malformed(node, "operator"))?;
+            Expr::BinExpr(ast::BinExpr {
+                left,
+                op : BinOp {
+                    token,
+                    kind
+                },
+                right : Box::new(c.expr()?)
+            })
+        },
+        NodeKind::Unary => {
+            let token = c.token("\"+\" or \"-\"")?;
+            let kind = match token.kind {
+                TokenKind::Plus => UnaryOpKind::Plus,
+                TokenKind::Minus => UnaryOpKind::Minus,
+                _ => return Err(malformed(node, "\"+\" or \"-\""))
+            };
+            Expr::UnaryExpr(ast::UnaryExpr {
+                op : UnaryOp {
+                    token,
+                    kind
+                },
+                primary : lower_primary(c.node("primary expression")?)?
+            })
+        },
+        _ => return Err(malformed(node, "expression"))
+    };
+    c.end()?;
+    Ok(expr)
+}
+
+fn lower_primary<'a>(node : &CstNode<'a>) -> Result<PrimaryExpr<'a>, CompilerError> {
+    let mut c = Children::new(node);
+    let expr = match node.kind {
+        NodeKind::Call => {
+            let name = c.token("identifier")?;
+            let lparen = c.token("\"(\"")?;
+            let first_arg = match c.node_if(|_| true) {
+                Some(arg) => Some(Box::new(lower_expr(arg)?)),
+                None => None
+            };
+            let mut other_args = vec![];
+            while let Some(comma) = c.token_if(TokenKind::Comma) {
+                other_args.push((comma, c.expr()?));
+            }
+            PrimaryExpr::Call(ast::Call {
+                name,
+                lparen,
+                first_arg,
+                other_args,
+                rparen : c.token("\")\"")?
+            })
+        },
+        NodeKind::Ident => PrimaryExpr::Ident(c.token("identifier")?),
+        NodeKind::IntLit => PrimaryExpr::IntLit(IntLit::parse(c.token("integer")?)?),
+        NodeKind::StrLit => PrimaryExpr::StrLit(StrLit::parse(c.token("string")?)?),
+        NodeKind::VoidLit => PrimaryExpr::VoidLit(ast::VoidLit {
+            lparen : c.token("\"(\"")?,
+            rparen : c.token("\")\"")?
+        }),
+        NodeKind::Paren => PrimaryExpr::Paren(ast::ParenExpr {
+            lparen : c.token("\"(\"")?,
+            expr : Box::new(c.expr()?),
+            rparen : c.token("\")\"")?
+        }),
+        _ => return Err(malformed(node, "primary expression"))
+    };
+    c.end()?;
+    Ok(expr)
+}
@@ -0,0 +1,215 @@
+//! lossless concrete syntax tree : every token with its whitespaces and comments
+mod builder;
+mod lower;
+use std::fmt;
+use builder::CstBuilder;
+use crate::{ast::{AstBuilder, Program}, compiler_error::CompilerError, lexer::{Lexer, LosslessToken, Trivia}, source_buffer::SourceBuffer};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NodeKind {
+    Program,
+    // statements
+    Return,
+    Break,
+    Continue,
+    Noop,
+    VarDecl,
+    ExprStmt,
+    Test,
+    // expressions
+    While,
+    Loop,
+    Block,
+    If,
+    Assign,
+    Binary,
+    Unary,
+    Call,
+    Paren,
+    Ident,
+    IntLit,
+    VoidLit,
+    StrLit
+}
+
+impl NodeKind {
+    pub fn is_statement(&self) -> bool {
+        matches!(self,
+            Self::Return
+                | Self::Break
+                | Self::Continue
+                | Self::Noop
+                | Self::VarDecl
+                | Self::ExprStmt
+                | Self::Test)
+    }
+
+    pub fn is_primary(&self) -> bool {
+        matches!(self,
+            Self::Call
+                | Self::Paren
+                | Self::Ident
+                | Self::IntLit
+                | Self::VoidLit
+                | Self::StrLit)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CstElement<'a> {
+    Node(CstNode<'a>),
+    Token(LosslessToken<'a>)
+}
+
+/// the children are in the order of the source
+#[derive(Debug, PartialEq, Clone)]
+pub struct CstNode<'a> {
+    pub kind : NodeKind,
+    pub children : Vec<CstElement<'a>>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cst<'a> {
+    pub root : CstNode<'a>,
+    /// trivia after the last token
+    pub eof : Vec<Trivia<'a>>
+}
+
+impl<'a> CstNode<'a> {
+    pub fn new(kind : NodeKind, children : Vec<CstElement<'a>>) -> Self {
+        Self {
+            kind,
+            children
+        }
+    }
+
+    /// all the tokens of the node in the order of the source
+    pub fn tokens(&self) -> Vec<&LosslessToken<'a>> {
+        let mut tokens = vec![];
+        for child in &self.children {
+            match child {
+                CstElement::Node(n) => tokens.extend(n.tokens()),
+                CstElement::Token(t) => tokens.push(t)
+            }
+        }
+        tokens
+    }
+
+    pub fn first_token(&self) -> Option<&LosslessToken<'a>> {
+        self.children.iter()
+            .find_map(|c| match c {
+                CstElement::Node(n) => n.first_token(),
+                CstElement::Token(t) => Some(t)
+            })
+    }
+
+    /// the direct child nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &CstNode<'a>> {
+        self.children.iter()
+            .filter_map(|c| match c {
+                CstElement::Node(n) => Some(n),
+                _ => None
+            })
+    }
+}
+
+impl<'a> Cst<'a> {
+    pub fn parse(source : &'a SourceBuffer) -> Result<Self, CompilerError> {
+        let (tokens, eof) = Lexer::new(source).lossless()?;
+        if tokens.is_empty() {
+            // only whitespaces and comments
+            return Ok(Self {
+                root : CstNode::new(NodeKind::Program, vec![]),
+                eof
+            })
+        }
+        let program = AstBuilder::from(Lexer::new(source)).parse_program()?;
+        Ok(Self {
+            root : CstBuilder::new(tokens).build(&program)?,
+            eof
+        })
+    }
+
+    pub fn to_ast(&self) -> Result<Program<'a>, CompilerError> {
+        lower::lower_program(&self.root)
+    }
+}
+
+impl fmt::Display for CstElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Node(n) => write!(f, "{}", n),
+            Self::Token(t) => write!(f, "{}", t)
+        }
+    }
+}
+
+impl fmt::Display for CstNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root)?;
+        for t in &self.eof {
+            write!(f, "{}", t)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn source(code : &str) -> SourceBuffer {
+        SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : code.to_string()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cases = [
+            include_str!("../../../exemple/fibonacci.jol"),
+            "",
+            "  // only a comment",
+            "let a=1;let b : i8 = -a+2*(3 - a);\r\n/* block\n comment */ print( b ) ;",
+            "{ let x = 5; x } ; loop { if (1) { break; } else { continue; } }\n\n// end\n",
+            "while(input() != 0x10){print(pow(2, 0b11));} return ();",
+            "#!/usr/bin/env jolang\r\nprint(1);",
+            r#"test "sum"{ assert_eq(1+1, 2, "a \"message\""); }"#,
+        ];
+        for code in cases {
+            let buf = source(code);
+            let cst = Cst::parse(&buf).unwrap();
+            assert_eq!(cst.to_string(), code);
+            if !cst.root.children.is_empty() {
+                let ast = AstBuilder::from(Lexer::new(&buf)).parse_program().unwrap();
+                assert_eq!(cst.to_ast().unwrap(), ast);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trivia() {
+        let buf = source("// header\nlet a = 1; // trailing\n\nprint(a);\n");
+        let cst = Cst::parse(&buf).unwrap();
+        let tokens = cst.root.tokens();
+        assert_eq!(tokens[0].leading.iter().map(|t| t.text).collect::<String>(), "// header\n");
+        let semicolon = tokens[4];
+        assert_eq!(semicolon.token.span.data, ";");
+        assert_eq!(semicolon.trailing.iter().map(|t| t.text).collect::<String>(), " // trailing");
+        assert_eq!(tokens[5].leading.iter().map(|t| t.text).collect::<String>(), "\n\n");
+        assert_eq!(cst.eof.iter().map(|t| t.text).collect::<String>(), "\n");
+        assert_eq!(cst.root.nodes().map(|n| n.kind).collect::<Vec<_>>(), vec![NodeKind::VarDecl, NodeKind::ExprStmt]);
+    }
+}
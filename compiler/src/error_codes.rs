@@ -0,0 +1,198 @@
+//! long explanations of the compiler errors (printed by `jolang explain`)
+use crate::compiler_error::CompilerErrorKind;
+
+static E0001 : &str = r#"a character sequence that is not a valid token was found
+
+this happens with characters that are not part of the language, with
+unterminated block comments and with integer litterals that cannot be parsed
+
+wrong:
+
+    let price = 5 $ 3;
+
+correct:
+
+    let price = 5 * 3;
+"#;
+
+static E0002 : &str = r#"a valid token was found at a place where it is not allowed
+
+wrong:
+
+    let value = );
+
+correct:
+
+    let value = (1);
+"#;
+
+static E0003 : &str = r#"the parser expected a specific token or construct but found
+something else or the end of the file
+
+the most common case is a missing semicolon at the end of a statement
+
+wrong:
+
+    let n = input()
+    print(n);
+
+correct:
+
+    let n = input();
+    print(n);
+"#;
+
+static E0004 : &str = r#"a variable was used before being declared with `let`
+
+variables must be declared before they can be read or assigned, and they are
+only visible in the block where they are declared
+
+wrong:
+
+    counter = 0;
+    print(counter);
+
+correct:
+
+    let counter = 0;
+    print(counter);
+"#;
+
+static E0005 : &str = r#"a variable was declared twice in the same scope
+
+to change the value of an existing variable use an assignment instead
+
+wrong:
+
+    let total = 1;
+    let total = 2;
+
+correct:
+
+    let total = 1;
+    total = 2;
+"#;
+
+static E0006 : &str = r#"a function that does not exist was called
+
+only the builtin functions are available : print, input, pow and randint
+
+wrong:
+
+    prnt(5);
+
+correct:
+
+    print(5);
+"#;
+
+static E0007 : &str = r#"a variable was declared with a type that does not exist
+
+the available types are i8, i16, i32 and i64
+
+wrong:
+
+    let big : i256 = 0;
+
+correct:
+
+    let big : i64 = 0;
+"#;
+
+static E0008 : &str = r#"`break` or `continue` was used outside of a loop
+
+wrong:
+
+    let n = 0;
+    if (n > 10) {
+        break;
+    }
+
+correct:
+
+    let n = 0;
+    loop {
+        if (n > 10) {
+            break;
+        }
+        n = n + 1;
+    }
+"#;
+
+static E0009 : &str = r#"an expression that does not produce a value was used where a value is
+expected
+
+calls to functions returning nothing (like print) and blocks without a
+trailing expression do not have a value
+
+wrong:
+
+    let result = print(5);
+
+correct:
+
+    print(5);
+    let result = 5;
+"#;
+
+static E0010 : &str = r#"a function was called with the wrong number of arguments
+
+wrong:
+
+    let cube = pow(3);
+
+correct:
+
+    let cube = pow(3, 3);
+"#;
+
+pub fn explanation(kind : &CompilerErrorKind) -> &'static str {
+    match kind {
+        CompilerErrorKind::BadToken => E0001,
+        CompilerErrorKind::UnexpectedToken => E0002,
+        CompilerErrorKind::Expected => E0003,
+        CompilerErrorKind::UnderlaredVariable => E0004,
+        CompilerErrorKind::ReeclaretedVariable => E0005,
+        CompilerErrorKind::UnknownFunction => E0006,
+        CompilerErrorKind::UnknownType => E0007,
+        CompilerErrorKind::OutsideOfLoop => E0008,
+        CompilerErrorKind::MismatchedTypes => E0009,
+        CompilerErrorKind::BadArgumentCount => E0010,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile, source_buffer::SourceBuffer};
+    use std::path::PathBuf;
+
+    /// the indented code after a `wrong:` or `correct:` line
+    fn example(text : &str, label : &str) -> String {
+        text.split(label).nth(1).unwrap()
+            .lines()
+            .skip_while(|line| line.is_empty())
+            .take_while(|line| line.is_empty() || line.starts_with("    "))
+            .map(|line| line.trim_start_matches("    "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_examples() {
+        for kind in CompilerErrorKind::ALL {
+            let text = explanation(&kind);
+            let source = |label| SourceBuffer {
+                path : PathBuf::from("test.jol"),
+                buffer : example(text, label)
+            };
+            match compile(&source("\nwrong:\n")) {
+                Ok(_) => panic!("the wrong example of {} compiles", kind.code()),
+                Err(e) => assert_eq!(e.kind().code(), kind.code(), "wrong example of {}", kind.code())
+            }
+            if let Err(e) = compile(&source("\ncorrect:\n")) {
+                panic!("the correct example of {} fails with {}", kind.code(), e.kind().code());
+            }
+        }
+    }
+}
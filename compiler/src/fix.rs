@@ -0,0 +1,215 @@
+//! machine applicable fixes for compiler errors (used by `jolang fix`)
+use crate::{compile, compiler_error::CompilerError, source_buffer::SourceBuffer, source_span::SourceSpan};
+
+/// maximum number of fixes applied to a file, prevent looping on a fix that does not fix anything
+pub const MAX_FIXES : usize = 256;
+
+/// replace `len` bytes of the source at `offset` by `replacement`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub offset : usize,
+    pub len : usize,
+    pub replacement : String
+}
+
+impl Fix {
+    pub fn insert(offset : usize, text : &str) -> Self {
+        Self {
+            offset,
+            len : 0,
+            replacement : text.to_string()
+        }
+    }
+
+    pub fn replace(span : &SourceSpan, text : &str) -> Self {
+        Self {
+            offset : span.offset(),
+            len : span.size,
+            replacement : text.to_string()
+        }
+    }
+
+    pub fn apply(&self, source : &str) -> String {
+        let mut result = String::with_capacity(source.len() + self.replacement.len());
+        result.push_str(&source[..self.offset]);
+        result.push_str(&self.replacement);
+        result.push_str(&source[self.offset + self.len..]);
+        result
+    }
+
+    /// the line containing the fix after applying it
+    pub fn preview(&self, source : &str) -> String {
+        let start = source[..self.offset].rfind('\n').map_or(0, |x| x + 1);
+        let end = source[self.offset + self.len..].find('\n')
+            .map_or(source.len(), |x| x + self.offset + self.len);
+        format!("{}{}{}", &source[start..self.offset], self.replacement, &source[self.offset + self.len..end])
+    }
+}
+
+/// levenshtein distance between two strings
+fn distance(a : &str, b : &str) -> usize {
+    let b : Vec<char> = b.chars().collect();
+    let mut row : Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            }else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// the candidate the most similar to name, if it is close enough to be a typo
+pub fn closest_name<'a, I : IntoIterator<Item = &'a str>>(name : &str, candidates : I) -> Option<&'a str> {
+    // short names are too ambiguous to guess
+    let max = name.chars().count() / 3;
+    candidates.into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (distance(name, c), c))
+        .filter(|(d, _)| *d <= max)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+pub struct FixResult {
+    /// the source with all the fixes applied
+    pub source : String,
+    /// the errors that were fixed, in order
+    pub fixed : Vec<CompilerError>,
+    /// the first error that could not be fixed
+    pub remaining : Option<CompilerError>
+}
+
+/// apply the fixes of the errors one at a time until the source compile or an error has no fix
+pub fn fix_source(source : &SourceBuffer) -> FixResult {
+    let mut current = source.buffer.clone();
+    let mut fixed = vec![];
+    let mut remaining = None;
+    for _ in 0..MAX_FIXES {
+        let buffer = SourceBuffer {
+            path : source.path.clone(),
+            buffer : current.clone()
+        };
+        match compile(&buffer) {
+            Ok(_) => break,
+            Err(e) => match e.fix() {
+                Some(fix) => {
+                    current = fix.apply(&current);
+                    fixed.push(e);
+                },
+                None => {
+                    remaining = Some(e);
+                    break;
+                }
+            }
+        }
+    }
+    FixResult {
+        source : current,
+        fixed,
+        remaining
+    }
+}
+
+/// unified diff of two texts
+pub fn diff(path : &str, old : &str, new : &str) -> String {
+    const CONTEXT : usize = 3;
+    let a : Vec<&str> = old.lines().collect();
+    let b : Vec<&str> = new.lines().collect();
+    // longest common subsequence table
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            }else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    // (prefix, line, line number in a, line number in b)
+    let mut ops : Vec<(char, &str, usize, usize)> = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', a[i], i, j));
+            i += 1;
+            j += 1;
+        }else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', a[i], i, j));
+            i += 1;
+        }else {
+            ops.push(('+', b[j], i, j));
+            j += 1;
+        }
+    }
+    let mut output = String::new();
+    let mut k = 0;
+    while k < ops.len() {
+        if ops[k].0 == ' ' {
+            k += 1;
+            continue;
+        }
+        if output.is_empty() {
+            output.push_str(&format!("--- {}\n+++ {}\n", path, path));
+        }
+        // extend the hunk while the changes are close enough
+        let start = k.saturating_sub(CONTEXT);
+        let mut end = k;
+        let mut last_change = k;
+        while end < ops.len() && end <= last_change + 2 * CONTEXT {
+            if ops[end].0 != ' ' {
+                last_change = end;
+            }
+            end += 1;
+        }
+        let end = (last_change + CONTEXT + 1).min(ops.len());
+        let hunk = &ops[start..end];
+        let old_count = hunk.iter().filter(|o| o.0 != '+').count();
+        let new_count = hunk.iter().filter(|o| o.0 != '-').count();
+        output.push_str(&format!("@@ -{},{} +{},{} @@\n",
+            hunk[0].2 + if old_count == 0 { 0 } else { 1 },
+            old_count,
+            hunk[0].3 + if new_count == 0 { 0 } else { 1 },
+            new_count));
+        for (prefix, line, _, _) in hunk {
+            output.push_str(&format!("{}{}\n", prefix, line));
+        }
+        k = end;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler_error::CompilerErrorKind;
+
+    #[test]
+    fn test_fix() {
+        let cases = [
+            ("let a = 5\nprint(a);", "let a = 5;\nprint(a);"),
+            ("let a : i8\nreturn 0;", "let a : i8;\nreturn 0;"),
+            ("print(1)\nreturn 0", "print(1);\nreturn 0;"),
+            ("loop { break }", "loop { break; }"),
+            ("count = 5;\nprint(count);", "let count = 5;\nprint(count);"),
+            ("prnt(5);", "print(5);"),
+            ("let total = 1; print(totl);", "let total = 1; print(total);"),
+        ];
+        for (code, expected) in cases {
+            let result = fix_source(&SourceBuffer::from_string("test.jol", code.to_string()));
+            assert!(result.remaining.is_none(), "{}", code);
+            assert_eq!(result.source, expected);
+        }
+        let result = fix_source(&SourceBuffer::from_string("test.jol", "print(a = 5);".to_string()));
+        assert_eq!(result.remaining.map(|e| e.kind().clone()), Some(CompilerErrorKind::UnderlaredVariable));
+        assert_eq!(diff("test.jol", "a\nb\nc\n", "a\nb;\nc\n"), "--- test.jol\n+++ test.jol\n@@ -1,3 +1,3 @@\n a\n-b\n+b;\n c\n");
+    }
+}
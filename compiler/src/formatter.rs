@@ -0,0 +1,311 @@
+//! source formatter (used by `jolang fmt`), comments are kept
+use crate::compiler_error::CompilerError;
+use crate::cst::{Cst, CstElement, CstNode, NodeKind};
+use crate::lexer::{LosslessToken, Trivia, TriviaKind};
+use crate::source_buffer::SourceBuffer;
+
+const INDENT : &str = "    ";
+
+/// format a source, the source must be valid
+pub fn format(source : &SourceBuffer) -> Result<String, CompilerError> {
+    let cst = Cst::parse(source)?;
+    let mut writer = Writer::new();
+    writer.node(&cst.root);
+    writer.trivia(&cst.eof);
+    writer.newline();
+    let mut output = writer.output.trim_end().to_string();
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+struct Writer {
+    output : String,
+    indent : usize,
+    /// nothing was written on the current line
+    line_start : bool,
+    /// no line was written in the current block, blank lines are removed
+    block_start : bool,
+    /// line comment that must be written before the end of the line
+    pending_comment : Option<String>
+}
+
+impl Writer {
+    fn new() -> Self {
+        Self {
+            output : String::new(),
+            indent : 0,
+            line_start : true,
+            block_start : true,
+            pending_comment : None
+        }
+    }
+
+    fn write(&mut self, text : &str) {
+        if self.line_start {
+            self.output.push_str(&INDENT.repeat(self.indent));
+            self.line_start = false;
+        }
+        self.output.push_str(text);
+        self.block_start = false;
+    }
+
+    fn space(&mut self) {
+        if !self.line_start && !self.output.ends_with(' ') {
+            self.output.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        if let Some(comment) = self.pending_comment.take() {
+            self.space();
+            self.write(&comment);
+        }
+        if !self.line_start {
+            while self.output.ends_with(' ') {
+                self.output.pop();
+            }
+            self.output.push('\n');
+            self.line_start = true;
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.block_start && !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    /// comments before a token, the newlines are only used to keep blank lines
+    fn trivia(&mut self, trivia : &[Trivia]) {
+        let mut newlines = 0;
+        let mut after_comment = false;
+        for t in trivia {
+            match t.kind {
+                TriviaKind::Newline => {
+                    newlines += 1;
+                    if after_comment {
+                        self.newline();
+                        after_comment = false;
+                    }
+                },
+                TriviaKind::Whitespace => (),
+                TriviaKind::BlockComment if !self.line_start => {
+                    self.space();
+                    self.write(t.text);
+                    self.space();
+                },
+                TriviaKind::LineComment
+                    | TriviaKind::Skipped
+                    if !self.line_start && self.pending_comment.is_none()
+                    => self.pending_comment = Some(t.text.trim_end().to_string()),
+                _ => {
+                    self.newline();
+                    if newlines >= 2 {
+                        self.blank_line();
+                    }
+                    self.write(t.text.trim_end());
+                    if t.kind == TriviaKind::BlockComment {
+                        self.space();
+                        after_comment = true;
+                    }else {
+                        self.newline();
+                    }
+                    newlines = 0;
+                }
+            }
+        }
+        if self.line_start && newlines >= 2 {
+            self.blank_line();
+        }
+    }
+
+    fn token(&mut self, token : &LosslessToken) {
+        self.trivia(&token.leading);
+        if self.pending_comment.is_some() {
+            // the rest of the expression continues on the next line
+            self.newline();
+            self.write(INDENT);
+        }
+        self.write(token.token.span.data);
+        for t in &token.trailing {
+            match t.kind {
+                TriviaKind::LineComment => self.pending_comment = Some(t.text.trim_end().to_string()),
+                TriviaKind::BlockComment
+                    | TriviaKind::Skipped
+                    => {
+                    self.space();
+                    self.write(t.text);
+                },
+                _ => ()
+            }
+        }
+    }
+
+    fn element(&mut self, element : &CstElement) {
+        match element {
+            CstElement::Node(n) => self.node(n),
+            CstElement::Token(t) => self.token(t)
+        }
+    }
+
+    /// write the children with a space between them except inside parentheses
+    fn spaced(&mut self, node : &CstNode) {
+        for (i, child) in node.children.iter().enumerate() {
+            if i > 0
+                && !is_token(child, ";")
+                && !is_token(child, ")")
+                && !is_token(&node.children[i - 1], "(") {
+                self.space();
+            }
+            self.element(child);
+        }
+    }
+
+    fn node(&mut self, node : &CstNode) {
+        match node.kind {
+            NodeKind::Program => {
+                for child in &node.children {
+                    self.element(child);
+                    self.newline();
+                }
+            },
+            NodeKind::Block => self.block(node),
+            NodeKind::Return
+                | NodeKind::Break
+                | NodeKind::Continue
+                | NodeKind::Noop
+                | NodeKind::VarDecl
+                | NodeKind::ExprStmt
+                | NodeKind::Test
+                | NodeKind::While
+                | NodeKind::Loop
+                | NodeKind::If
+                | NodeKind::Assign
+                | NodeKind::Binary
+                => self.spaced(node),
+            NodeKind::Unary
+                | NodeKind::Paren
+                | NodeKind::Ident
+                | NodeKind::IntLit
+                | NodeKind::VoidLit
+                | NodeKind::StrLit
+                => {
+                for child in &node.children {
+                    self.element(child);
+                }
+            },
+            NodeKind::Call => {
+                for child in &node.children {
+                    self.element(child);
+                    if is_token(child, ",") {
+                        self.space();
+                    }
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, node : &CstNode) {
+        let (first, rest) = match node.children.split_first() {
+            Some(x) => x,
+            None => return
+        };
+        let (last, body) = match rest.split_last() {
+            Some(x) => x,
+            None => return self.element(first)
+        };
+        self.element(first);
+        let empty = body.is_empty() && match last {
+            CstElement::Token(t) => t.leading.iter().all(|t| matches!(t.kind, TriviaKind::Whitespace | TriviaKind::Newline)),
+            _ => false
+        };
+        if empty {
+            return self.element(last)
+        }
+        self.indent += 1;
+        self.newline();
+        self.block_start = true;
+        for child in body {
+            self.element(child);
+            self.newline();
+        }
+        if let CstElement::Token(t) = last {
+            // comments before the closing brace are in the block
+            self.trivia(&t.leading);
+            if self.output.ends_with("\n\n") {
+                self.output.pop();
+            }
+        }
+        self.indent -= 1;
+        self.newline();
+        self.block_start = false;
+        match last {
+            CstElement::Token(t) => self.token(&LosslessToken {
+                leading : vec![],
+                token : t.token.clone(),
+                trailing : t.trailing.clone()
+            }),
+            e => self.element(e)
+        }
+    }
+}
+
+fn is_token(element : &CstElement, text : &str) -> bool {
+    matches!(element, CstElement::Token(t) if t.token.span.data == text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn source(code : &str) -> SourceBuffer {
+        SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : code.to_string()
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let cases = [
+            ("let a=1;let b:i8=-a+2*(3-a);", "let a = 1;\nlet b : i8 = -a + 2 * (3 - a);\n"),
+            ("if(a==1){print(a);}else if(a<0){return 1;}else{}", "if (a == 1) {\n    print(a);\n} else if (a < 0) {\n    return 1;\n} else {}\n"),
+            ("loop{  while(1){break;}\n\n\n  continue ;}", "loop {\n    while (1) {\n        break;\n    }\n\n    continue;\n}\n"),
+            ("// header\n\nlet a = pow(2,3); // trailing\n/* block */ print( a );\n", "// header\n\nlet a = pow(2, 3); // trailing\n/* block */ print(a);\n"),
+            ("let a = { let b = 1;\n b };", "let a = {\n    let b = 1;\n    b\n};\n"),
+            ("let a = 1 + // one\n 2;", "let a = 1 + // one\n    2;\n"),
+            ("let a = 1 +\n// one\n 2;", "let a = 1 + // one\n    2;\n"),
+            ("/* a */\n/* b */ let a = 1; /* c */", "/* a */\n/* b */ let a = 1; /* c */\n"),
+            ("{\n    print(1);\n    // last\n}", "{\n    print(1);\n    // last\n}\n"),
+            ("#!/usr/bin/env jolang\nlet a=1;", "#!/usr/bin/env jolang\nlet a = 1;\n"),
+            ("test \"a\"{assert(1,\"one\");}", "test \"a\" {\n    assert(1, \"one\");\n}\n"),
+            ("", ""),
+            ("// only a comment", "// only a comment\n"),
+        ];
+        for (code, expected) in cases {
+            let formatted = format(&source(code)).unwrap();
+            assert_eq!(formatted, expected);
+            // formatting is idempotent
+            assert_eq!(format(&source(&formatted)).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn test_format_keeps_semantics() {
+        let example = include_str!("../../exemple/fibonacci.jol");
+        let formatted = format(&source(example)).unwrap();
+        assert_eq!(format(&source(&formatted)).unwrap(), formatted);
+        assert_eq!(
+            format!("{:?}", compile(&source(example)).unwrap()),
+            format!("{:?}", compile(&source(&formatted)).unwrap()));
+        for comment in ["// calulate the first", "the following code is useless", "// test signed addition"] {
+            assert!(formatted.contains(comment), "{}", comment);
+        }
+    }
+}
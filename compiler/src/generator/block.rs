@@ -1,33 +1,45 @@
 /// block that are not completly built yet
 use index_list::IndexList;
-use jolang_shared::ir::instructions::Instruction;
+use jolang_shared::ir::instructions::{operand::Size, Instruction};
 
 #[derive(Debug, Default)]
 pub struct Block {
-    // args size
-    pub args : Vec<u64>,
-    pub instructions : IndexList<Instruction>,
-    // size of the stack at the end of the block (after the last instruction)
-    pub stack_types : Vec<u64>
+    // types on the stack when entering the block (None until a branch targets the block)
+    pub args : Option<Vec<Size>>,
+    /// the instructions with the line and the collumn of the source they come from (0 if unknown)
+    pub instructions : IndexList<(Instruction, (u32, u32))>,
+    // types on the stack at the end of the block (after the last instruction)
+    pub stack_types : Vec<Size>
 }
 
 impl Block {
-    pub fn new(args : Vec<u64>) -> Self {
+    pub fn new() -> Self {
         Self {
-            args : args.clone(),
+            args : None,
             instructions : IndexList::new(),
-            stack_types : args.clone()
+            stack_types : Vec::new()
         }
     }
 
     pub fn into_ir_block(self) -> jolang_shared::ir::block::Block {
         jolang_shared::ir::block::Block {
-            args : self.args,
-            instructions : self.instructions
+            instructions : self.instructions.iter().map(|(i, _)| *i).collect()
         }
     }
 
-    pub fn stack_size(&self) -> u64 {
-        self.stack_types.len() as u64
+    /// the line and the collumn of each instruction
+    pub fn locations(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.instructions.iter().map(|(_, location)| *location)
+    }
+
+    pub fn stack_size(&self) -> usize {
+        self.stack_types.len()
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        matches!(self.instructions.get_last().map(|(i, _)| i),
+            Some(Instruction::Br(_))
+                | Some(Instruction::Briz(_, _))
+                | Some(Instruction::Iret(_)))
     }
 }
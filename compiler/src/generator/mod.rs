@@ -1,18 +1,25 @@
 mod block;
-use jolang_shared::{ffi::JolangExtern, ir::{instructions::{operand::{BlkId, FnId}, Instruction}, IrObject}};
-use core::panic;
-use std::{borrow::{Borrow, BorrowMut}, cell::{RefCell, Ref, RefMut}};
-use index_list::{IndexList, ListIndex};
-use crate::scope::Scope;
+use jolang_shared::ir::{instructions::{operand::{BlkId, Size}, Instruction}, section::SourceLocation, verifier::type_size, IrExternalFn, IrObject};
+use index_list::ListIndex;
+use crate::{compiler_error::{CompilerError, CompilerErrorKind}, hir::Hir, scope::{ScopeId, VarId}};
 use block::Block;
 
 pub struct IrGenerator {
-    blocks : Vec<RefCell<Block>>,
-    ext_fn : Vec<(String, u8, bool)>,
+    blocks : Vec<Block>,
+    ext_fn : Vec<IrExternalFn>,
+    local_vars : Vec<u8>,
     current_block : Option<BlkId>,
     current_pos : Option<ListIndex>,
-    // data for the generation
-    current_scopes : IndexList<Scope>
+    // loop scope, continue target, break target
+    loops : Vec<(ScopeId, BlkId, BlkId)>,
+    /// line and collumn of the code being generated, given to the new instructions
+    location : (u32, u32)
+}
+
+impl Default for IrGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl IrGenerator {
@@ -20,112 +27,172 @@ impl IrGenerator {
         Self {
             blocks : Vec::new(),
             ext_fn : Vec::new(),
+            local_vars : Vec::new(),
             current_block : None,
             current_pos : None,
-            current_scopes : IndexList::new()
+            loops : Vec::new(),
+            location : (0, 0)
         }
     }
 
-    pub fn decl_var(&mut self, name : String, size : u64) -> u64 {
-        let offset = self.get_current_block().map_or(0, |b| b.stack_size() - 1);
-        self.current_scopes.get_mut_first().map(|x| x.decl_var(name, offset, size));
-        offset
+    /// allocate a local variable slot
+    pub fn add_var_slot(&mut self, size : Size) -> VarId {
+        self.local_vars.push(size as u8);
+        (self.local_vars.len() - 1) as VarId
     }
 
-    pub fn update_var(&mut self, name : String) -> u64 {
-        let offset = self.get_current_block().unwrap().stack_size() - 1;
-        let mut index = self.current_scopes.first_index();
-        while index.is_some(){
-            let scope = self.current_scopes.get_mut(index).unwrap();
-            if scope.get_var_offset(&name).is_some() {
-                scope.update_var(&name, offset);
-                return offset;
-            }
-            index = self.current_scopes.next_index(index);
-        }
-        panic!("unknown variable : {}", name);
+    /// the source line and collumn of the next instructions
+    pub fn set_location(&mut self, line : usize, collumn : usize) {
+        self.location = (line as u32, collumn as u32);
+    }
+
+    pub fn get_location(&self) -> (usize, usize) {
+        (self.location.0 as usize, self.location.1 as usize)
+    }
+
+    /// an error at the current location, the source is given by the caller with CompilerError::with_source
+    pub fn error(&self, kind : CompilerErrorKind, message : &str) -> CompilerError {
+        CompilerError::new(kind, message, "", "", self.location.0, self.location.1, None)
     }
 
     pub fn into_ir(self) -> IrObject{
-        IrObject { 
-            blocks: self.blocks.iter()
-                .map(|b| b.take().into_ir_block())
-                .collect::<Vec<_>>(),
-            ext_fn: self.ext_fn
+        let mut ir = IrObject::new();
+        for (id, b) in self.blocks.iter().enumerate() {
+            for (i, (line, column)) in b.locations().enumerate() {
+                if line != 0 {
+                    ir.source_map.push(SourceLocation { block : id as u32, instruction : i as u32, line, column });
+                }
+            }
         }
+        ir.blocks = self.blocks.into_iter()
+            .map(|b| b.into_ir_block())
+            .collect::<Vec<_>>();
+        ir.ext_fn = self.ext_fn;
+        ir.local_vars = self.local_vars;
+        ir
+    }
+
+    pub fn get_current_block_id(&self) -> Option<BlkId> {
+        self.current_block
     }
 
-    pub fn get_current_block<'b>(&'b self) -> Option<Ref<'b, Block>> {
-        self.current_block.as_ref().and_then(|id| self.blocks.get(*id as usize).map(|b| b.borrow()))
+    pub fn get_current_block(&self) -> Option<&Block> {
+        self.current_block.and_then(|id| self.blocks.get(id as usize))
     }
 
-    pub fn get_current_block_mut<'b>(&'b self) -> Option<RefMut<'b, Block>> {
-        self.current_block.as_ref().and_then(|id| self.blocks.get(*id as usize).map(|b| b.borrow_mut()))
+    pub fn get_current_block_mut(&mut self) -> Option<&mut Block> {
+        self.current_block.and_then(|id| self.blocks.get_mut(id as usize))
     }
 
     pub fn add(&mut self, i : Instruction) -> Option<ListIndex> {
+        // the code is unreachable, it was already checked in the hir
+        self.current_block?;
         match i {
-            Instruction::Iconst(size, ..)
-                => self.inc_stack(size),
-            Instruction::Dup()
-                => self.get_current_block()
-                    .and_then(|b| b.stack_types.last().copied())
-                    .and_then(|s| self.inc_stack(s)),
-            Instruction::Icast(target)
-                => self.get_current_block_mut()
-                    .and_then(|mut b| b.stack_types.pop())
-                    .and_then(|_| self.inc_stack(target)),
-            Instruction::Dupx(pos)
-                => self.get_current_block()
-                    .and_then(|b| b.stack_types.get(pos as usize).copied())
-                    .and_then(|s| self.inc_stack(s)),
-            Instruction::Swap() 
-                => self.get_current_block_mut()
-                    .and_then(|mut b| b.stack_types.pop().and_then(|x| b.stack_types.pop().map(|x2| (x,x2))))
-                    .and_then(|(s1,s2)| {self.inc_stack(s1); self.inc_stack(s2); None}),
-            Instruction::Add()
-                | Instruction::Sub()
-                | Instruction::Mul()
-                | Instruction::Div()
-                | Instruction::Eq()
-                | Instruction::Ne()
-                | Instruction::Gt()
-                | Instruction::Ge()
-                | Instruction::Lt()
-                | Instruction::Le()
-                | Instruction::Lsh()
-                | Instruction::Rsh()
-                => self.dec_stack(),
-            Instruction::Ret()
-                | Instruction::Reti()
-                | Instruction::Br(_)
-                | Instruction::Neg()
-                | Instruction::Briz(_, _)
-                => None,
+            Instruction::Nop() => (),
+            Instruction::Pop(_)
+                | Instruction::Iret(_)
+                => { self.pop_stack(); },
+            Instruction::Dup(_)
+                => if let Some(s) = self.stack_top() { self.push_stack(s) },
+            Instruction::Swap(_) => {
+                let s1 = self.pop_stack();
+                let s2 = self.pop_stack();
+                if let Some(s) = s1 {
+                    self.push_stack(s);
+                }
+                if let Some(s) = s2 {
+                    self.push_stack(s);
+                }
+            },
+            Instruction::Br(target) => self.branch_to(target),
+            Instruction::Briz(target1, target2) => {
+                self.pop_stack();
+                self.branch_to(target1);
+                self.branch_to(target2);
+            },
             Instruction::Call(f) => {
-                if let Some(argc) = self.ext_fn.get(f as usize).map(|x| x.1)  {
-                    for _ in 0..argc {
-                        self.dec_stack();
-                    }
+                let (argc, ret) = match self.ext_fn.get(f as usize) {
+                    Some(f) => (f.sig.args.len(), type_size(&f.sig.ret)),
+                    None => (0, None)
+                };
+                for _ in 0..argc {
+                    self.pop_stack();
                 }
-                if self.ext_fn.get(f as usize).map_or(false, |x| x.2) {
-                    self.inc_stack(64)
-                }else{
-                    None
+                if let Some(s) = ret {
+                    self.push_stack(s);
                 }
+            },
+            Instruction::Varref() => {
+                self.pop_stack();
+                self.push_stack(0);
+            },
+            Instruction::Iconst(size, _) => self.push_stack(size),
+            Instruction::Iload(size) => {
+                self.pop_stack();
+                self.push_stack(size);
+            },
+            Instruction::Istore(_) => {
+                self.pop_stack();
+                self.pop_stack();
+            },
+            Instruction::Inot(size)
+                | Instruction::Ineg(size)
+                => {
+                self.pop_stack();
+                self.push_stack(size);
+            },
+            Instruction::Ior(size)
+                | Instruction::Iand(size)
+                | Instruction::Ixor(size)
+                | Instruction::Ilshr(size)
+                | Instruction::Iashr(size)
+                | Instruction::Ishl(size)
+                | Instruction::Iadd(size)
+                | Instruction::Isub(size)
+                | Instruction::Imul(size)
+                | Instruction::Idiv(size)
+                | Instruction::Udiv(size)
+                | Instruction::Irem(size)
+                | Instruction::Urem(size)
+                => {
+                self.pop_stack();
+                self.pop_stack();
+                self.push_stack(size);
+            },
+            Instruction::Ieq(_)
+                | Instruction::Ine(_)
+                | Instruction::Ige(_)
+                | Instruction::Igt(_)
+                | Instruction::Uge(_)
+                | Instruction::Ugt(_)
+                | Instruction::Ilt(_)
+                | Instruction::Ile(_)
+                | Instruction::Ule(_)
+                | Instruction::Ult(_)
+                => {
+                self.pop_stack();
+                self.pop_stack();
+                self.push_stack(1);
+            },
+            Instruction::Iconv(_, to)
+                | Instruction::Uconv(_, to)
+                => {
+                self.pop_stack();
+                self.push_stack(to);
             }
         };
-        let pos = self.get_current_block_mut().map(|mut b| match self.current_pos{
+        let current_pos = self.current_pos;
+        let location = self.location;
+        let pos = self.get_current_block_mut().map(|b| match current_pos {
             Some(pos) => {
-                b.instructions.insert_after(pos, i)
+                b.instructions.insert_after(pos, (i, location))
             },
             None => {
-                b.instructions.insert_first(i)
+                b.instructions.insert_first((i, location))
             }
         });
         match i {
-            Instruction::Ret()
-                | Instruction::Reti()
+            Instruction::Iret(_)
                 | Instruction::Br(_)
                 | Instruction::Briz(_, _)
             => {
@@ -141,114 +208,173 @@ impl IrGenerator {
     }
 
     pub fn append_block(&mut self) -> BlkId {
-        self.blocks.push(RefCell::new(Block::new(self.var_sizes())));
-            return (self.blocks.len() as BlkId) - 1
+        self.blocks.push(Block::new());
+        (self.blocks.len() as BlkId) - 1
+    }
+
+    /// set the stack the block receive from its predecessors if it is not known yet
+    fn branch_to(&mut self, block : BlkId) {
+        let stack = self.get_current_block().map_or_else(Vec::new, |b| b.stack_types.clone());
+        if let Some(b) = self.blocks.get_mut(block as usize) {
+            if b.args.is_none() {
+                if b.instructions.is_empty() {
+                    b.stack_types = stack.clone();
+                }
+                b.args = Some(stack);
+            }
+        }
+    }
+
+    /// set the entry stack of a block that is only reached by a branch generated later (e.g. a loop exit)
+    pub fn set_block_args(&mut self, block : BlkId, args : Vec<Size>) {
+        if let Some(b) = self.blocks.get_mut(block as usize) {
+            if b.instructions.is_empty() {
+                b.stack_types = args.clone();
+            }
+            b.args = Some(args);
+        }
+    }
+
+    pub fn get_block_args(&self, block : BlkId) -> Option<&Vec<Size>> {
+        self.blocks.get(block as usize).and_then(|b| b.args.as_ref())
     }
-    
+
     pub fn goto_end(&mut self, block : BlkId) {
         self.current_block = Some(block);
         let pos = self.get_current_block().map(|x| x.instructions.last_index());
-        self.current_pos = pos;
+        self.current_pos = pos.filter(|p| p.is_some());
+        self.leave_terminated_block();
     }
 
     pub fn goto_begin(&mut self, block : BlkId) {
         self.current_block = Some(block);
-        let pos = self.get_current_block().map(|x| x.instructions.first_index());
-        self.current_pos = pos;
+        self.current_pos = None;
+        self.leave_terminated_block();
     }
 
-    pub fn enter_scope(&mut self, scope : Scope) {
-        self.current_scopes.insert_first(scope);
-    }
-
-    // get a variable offset from the top of the stack
-    pub fn get_var_offset(&mut self, name : String) -> Option<u64> {
-        self.current_scopes.iter()
-            .filter_map(|s| s.get_var_offset(&name))
-            .next()
-            .map(|x| x)
+    fn leave_terminated_block(&mut self) {
+        if self.get_current_block().is_some_and(|b| b.is_terminated()) {
+            self.current_block = None;
+            self.current_pos = None;
+        }
     }
 
-    pub fn get_var_size(&mut self, name : String) -> Option<u64> {
-        self.current_scopes.iter()
-            .filter_map(|s| s.get_var_size(&name))
-            .next()
-            .map(|x| x)
+    pub fn is_terminated(&self) -> bool {
+        self.get_current_block().is_none_or(|b| b.is_terminated())
     }
 
-
-
-    pub fn exit_scope(&mut self) {
-        self.current_scopes.remove_first();
+    pub fn push_loop(&mut self, scope : ScopeId, continue_block : BlkId, break_block : BlkId) {
+        self.loops.push((scope, continue_block, break_block));
     }
 
-    pub fn get_scopes(&self) -> &IndexList<Scope> {
-        &self.current_scopes
+    pub fn pop_loop(&mut self) {
+        self.loops.pop();
     }
 
-    pub fn get_scopes_mut(&mut self) -> &mut IndexList<Scope> {
-        &mut self.current_scopes
+    /// continue and break targets of a loop
+    pub fn get_loop(&self, scope : ScopeId) -> Option<(BlkId, BlkId)> {
+        self.loops.iter()
+            .rev()
+            .find(|l| l.0 == scope)
+            .map(|l| (l.1, l.2))
     }
 
-    pub fn get_externs(&self) -> &Vec<(String, u8, bool)> {
+    pub fn get_externs(&self) -> &Vec<IrExternalFn> {
         &self.ext_fn
     }
 
-    pub fn decl_extern(&mut self, name : String, func : &Box<dyn JolangExtern>) -> FnId{
-        self.ext_fn.push((name, func.arg_count(), func.returns()));
-        (self.ext_fn.len() -1) as FnId
+    pub fn stack_size(&self) -> usize {
+        self.get_current_block().map_or(0, |b| b.stack_size())
     }
 
-    pub fn var_sizes(&self) -> Vec<u64> {
-        self.current_scopes.iter()
-            .flat_map(|x| x.var_sizes())
-            .collect::<Vec<_>>()
+    pub fn stack_top(&self) -> Option<Size> {
+        self.get_current_block().and_then(|b| b.stack_types.last().copied())
     }
 
-    pub fn stack_size(&self) -> Option<u64> {
-        self.get_current_block().map(|b| b.stack_size())
+    pub fn stack_types(&self) -> Vec<Size> {
+        self.get_current_block().map_or_else(Vec::new, |b| b.stack_types.clone())
     }
 
-    pub fn inc_stack(&mut self, size : u64) -> Option<u64>{
-        self.get_current_block_mut()
-            .map(|mut x| {x.stack_types.push(size); x.stack_size()})
+    fn push_stack(&mut self, size : Size) {
+        if let Some(b) = self.get_current_block_mut() {
+            b.stack_types.push(size)
+        }
     }
 
-    pub fn dec_stack(&mut self) -> Option<u64>{
-        self.get_current_block_mut()
-            .map(|mut x| {x.stack_types.pop(); x.stack_size()})
+    fn pop_stack(&mut self) -> Option<Size> {
+        self.get_current_block_mut().and_then(|b| b.stack_types.pop())
     }
 
-
-    pub fn pass_vars(&mut self) {
-        // the var to pass are alredy passed so we do not need to duplicate them
-        if self.get_current_block().map_or(false, |b| b.instructions.is_empty()) {
-            return
-        }
-        let offsets : Vec<_> = self.current_scopes.iter()
-            .flat_map(|s| s.get_vars().values())
-            .map(|v| v.0)
-            .collect();
-        for v in offsets{
-            self.add(Instruction::Dupx(v));
+    /// convert the value on top of the stack to the given size
+    pub fn cast(&mut self, size : Size) {
+        if let Some(current) = self.stack_top() {
+            if current != size {
+                self.add(Instruction::Iconv(current, size));
+            }
         }
     }
 
-    pub fn recive_vars(&mut self) {
-        let mut pos : u64 = 0;
-        let mut index = self.current_scopes.first_index();
-        while index.is_some() {
-            self.current_scopes.get_mut(index).unwrap().get_vars_mut().values_mut()
-                .for_each(|x| {
-                    x.0 = pos;
-                    pos = pos + 1;
-                });
-            index =  self.current_scopes.next_index(index);
+    /// pop the values on the stack until it has the given size
+    pub fn truncate_stack(&mut self, size : usize) {
+        while self.stack_size() > size {
+            match self.stack_top() {
+                Some(s) => self.add(Instruction::Pop(s)),
+                None => break
+            };
         }
-    } 
+    }
+}
+
+impl From<&Hir> for IrGenerator {
+    /// the symbols of the hir get the first variable slots
+    fn from(hir : &Hir) -> Self {
+        let mut generator = Self::new();
+        generator.ext_fn = hir.ext_fn.clone();
+        generator.local_vars = hir.symbols.iter()
+            .map(|s| s.size as u8)
+            .collect();
+        generator
+    }
 }
 
 pub trait Generate {
-    // size hint indicate the expected size (for example expression) but can be ignored
-    fn generate(&self, generator : &mut IrGenerator, size_hint : Option<u64>);
+    /// the hir is already checked, an error means that it is inconsistent
+    fn generate(&self, generator : &mut IrGenerator) -> Result<(), CompilerError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile, compile_with, source_buffer::SourceBuffer};
+    use jolang_shared::ir::{assembler::assemble, passes::PassManager, verifier::errors};
+
+    fn source(code : &str) -> SourceBuffer {
+        SourceBuffer::from_string("test.jol", code.to_string())
+    }
+
+    #[test]
+    fn test_assemble_printed_ir() {
+        let example = include_str!("../../../exemple/fibonacci.jol");
+        let printed = format!("{:?}", compile(&source(example)).unwrap());
+        let object = assemble(&printed).unwrap();
+        assert_eq!(format!("{:?}", object), printed);
+    }
+
+    #[test]
+    fn test_generated_ir_is_valid() {
+        let programs = [
+            include_str!("../../../exemple/fibonacci.jol"),
+            "let a : i8 = 1; let b : i64 = a + 2; print(b);",
+            "let a = input(); if (a > 2) { print(1); } else if (a == 0) { return 3; } else { print(2); }",
+            "let i = 0; while (i < 10) { i = i + 1; if (i == 5) { continue; } if (i == 8) { break; } print(i); }",
+            "loop { let a = input(); if (a) { break; } } return pow(2, 3);",
+            "let a : i16 = 3; { let b : i32 = a << 2; print(b >> 1); } return -a;",
+        ];
+        for program in programs {
+            let object = compile(&source(program)).unwrap();
+            assert_eq!(errors(&object), vec![], "{}", program);
+            let optimized = compile_with(&source(program), &PassManager::for_level(2).unwrap()).unwrap();
+            assert_eq!(errors(&optimized), vec![], "{}", program);
+            assert_eq!(optimized.get_metadata("build_flags"), Some("-O2"));
+        }
+    }
 }
@@ -0,0 +1,764 @@
+use jolang_shared::ffi::jolang_std::JOLANG_STD;
+use jolang_shared::ir::{instructions::operand::{FnId, Size}, verifier::type_size, IrExternalFn};
+use crate::ast::{self, BinOpKind, PrimaryExpr, Statement, UnaryOpKind};
+use crate::compiler_error::{CompilerError, CompilerErrorKind};
+use crate::fix::{closest_name, Fix};
+use crate::lexer::Token;
+use crate::scope::{Scope, ScopeId, ScopeKind, SymbolId};
+use crate::source_span::SourceSpan;
+use super::{Assertion, Block, Expr, ExprKind, Hir, Reference, Stmt, Symbol, Test, Type, ASSERTION_FAILED};
+
+/// size of the integers without explicit type (i32)
+pub const DEFAULT_SIZE : Size = 4;
+/// size of the value returned by the program (i64)
+pub const RETURN_SIZE : Size = 8;
+
+/// functions that can only be called in a test
+const ASSERTIONS : [&str; 3] = ["assert", "assert_eq", "assert_output"];
+
+/// resolve the names and the types of an ast
+pub struct HirBuilder {
+    symbols : Vec<Symbol>,
+    scopes : Vec<Scope>,
+    ext_fn : Vec<IrExternalFn>,
+    current_scope : ScopeId,
+    // loops that contain a break
+    broken_loops : Vec<ScopeId>,
+    references : Vec<Reference>,
+    tests : Vec<Test>,
+    /// the test being lowered
+    test : Option<Test>,
+    /// errors of the statements, the next statements are still lowered
+    errors : Vec<CompilerError>
+}
+
+impl Default for HirBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HirBuilder {
+    pub fn new() -> Self {
+        Self {
+            symbols : Vec::new(),
+            scopes : vec![Scope::new(ScopeKind::Root, None)],
+            ext_fn : Vec::new(),
+            current_scope : 0,
+            broken_loops : Vec::new(),
+            references : Vec::new(),
+            tests : Vec::new(),
+            test : None,
+            errors : Vec::new()
+        }
+    }
+
+    /// continue a previous analysis (e.g. in a repl), the variables keep their ids
+    /// and the statements are in a new root scope that can shadow the previous ones
+    pub fn resume(previous : Hir) -> Self {
+        let parent = previous.scopes.iter()
+            .rposition(|s| s.kind == ScopeKind::Root)
+            .map(|i| i as ScopeId);
+        let mut builder = Self::new();
+        builder.symbols = previous.symbols;
+        builder.scopes = previous.scopes;
+        builder.scopes.push(Scope::new(ScopeKind::Root, parent));
+        builder.current_scope = (builder.scopes.len() - 1) as ScopeId;
+        builder
+    }
+
+    pub fn build(self, program : &ast::Program) -> Result<Hir, CompilerError> {
+        self.build_all(program).map_err(|mut errors| errors.remove(0))
+    }
+
+    /// build the hir or return the errors of every statement in the order of the source
+    pub fn build_all(mut self, program : &ast::Program) -> Result<Hir, Vec<CompilerError>> {
+        let mut body = vec![];
+        let mut locations = vec![];
+        for stmt in program.statements() {
+            if let Some(s) = self.lower_body_stmt(stmt) {
+                body.push(s);
+                locations.push(Self::location(stmt.span()));
+            }
+        }
+        if !self.errors.is_empty() {
+            return Err(self.errors)
+        }
+        Ok(Hir {
+            symbols : self.symbols,
+            scopes : self.scopes,
+            ext_fn : self.ext_fn,
+            body,
+            locations,
+            references : self.references,
+            tests : self.tests
+        })
+    }
+
+    /// line and collumn of the start of a span
+    fn location(span : &SourceSpan) -> (usize, usize) {
+        (span.start.line, span.start.collumn)
+    }
+
+    fn enter_scope(&mut self, kind : ScopeKind) -> ScopeId {
+        self.scopes.push(Scope::new(kind, Some(self.current_scope)));
+        self.current_scope = (self.scopes.len() - 1) as ScopeId;
+        self.current_scope
+    }
+
+    fn exit_scope(&mut self) {
+        self.current_scope = self.scopes[self.current_scope as usize].parent.unwrap_or_default();
+    }
+
+    /// the scopes from the current one to the root
+    fn scope_chain(&self) -> impl Iterator<Item = (ScopeId, &Scope)> {
+        let mut current = Some(self.current_scope);
+        std::iter::from_fn(move || {
+            let id = current?;
+            let scope = &self.scopes[id as usize];
+            current = scope.parent;
+            Some((id, scope))
+        })
+    }
+
+    fn get_var(&self, name : &str) -> Option<SymbolId> {
+        self.scope_chain().find_map(|(_, s)| s.get_var(name))
+    }
+
+    /// innermost loop scope
+    fn get_loop(&self) -> Option<ScopeId> {
+        self.scope_chain()
+            .find(|(_, s)| s.kind == ScopeKind::Loop)
+            .map(|(id, _)| id)
+    }
+
+    fn decl_var(&mut self, name : &Token, size : Size) -> SymbolId {
+        let id = self.symbols.len() as SymbolId;
+        self.symbols.push(Symbol {
+            name : name.span.data.to_string(),
+            size,
+            scope : self.current_scope,
+            line : name.span.start.line,
+            collumn : name.span.start.collumn,
+            offset : name.span.offset()
+        });
+        self.scopes[self.current_scope as usize].decl_var(name.span.data.to_string(), id);
+        self.add_reference(id, name);
+        id
+    }
+
+    fn add_reference(&mut self, symbol : SymbolId, name : &Token) {
+        self.references.push(Reference {
+            symbol,
+            offset : name.span.offset(),
+            len : name.span.size
+        });
+    }
+
+    fn decl_extern(&mut self, name : &str, func : &dyn jolang_shared::ffi::JolangExtern) -> FnId {
+        if let Some(id) = self.ext_fn.iter().position(|f| f.name == name) {
+            return id as FnId
+        }
+        self.ext_fn.push(IrExternalFn::new(name.to_string(), func.signature()));
+        (self.ext_fn.len() - 1) as FnId
+    }
+
+    /// size of the value of an expression without lowering it (None if it adapts to the context)
+    fn value_size(&self, expr : &ast::Expr) -> Option<Size> {
+        match expr {
+            ast::Expr::PrimaryExpr(p) => self.primary_size(p),
+            ast::Expr::UnaryExpr(u) => self.primary_size(&u.primary),
+            ast::Expr::BinExpr(b) => match b.op.kind {
+                BinOpKind::Equal
+                    | BinOpKind::NotEqual
+                    | BinOpKind::Greater
+                    | BinOpKind::GreaterEqual
+                    | BinOpKind::LesserEqual
+                    | BinOpKind::Lesser
+                    => Some(1),
+                _ => self.value_size(&b.left).max(self.value_size(&b.right))
+            },
+            ast::Expr::BlockExpr(b) => b.ret.as_ref().and_then(|r| self.value_size(r)),
+            ast::Expr::IfExpr(i) => self.value_size(&i.then),
+            ast::Expr::WhileExpr(_)
+                | ast::Expr::LoopExpr(_)
+                | ast::Expr::AssignExpr(_)
+                => None
+        }
+    }
+
+    fn primary_size(&self, expr : &PrimaryExpr) -> Option<Size> {
+        match expr {
+            PrimaryExpr::Call(c) => JOLANG_STD.iter()
+                .find(|x| x.0 == c.name.span.data)
+                .and_then(|x| type_size(&x.1.signature().ret)),
+            PrimaryExpr::Ident(name) => self.get_var(name.span.data)
+                .map(|id| self.symbols[id as usize].size),
+            PrimaryExpr::IntLit(_)
+                | PrimaryExpr::VoidLit(_)
+                | PrimaryExpr::StrLit(_)
+                => None,
+            PrimaryExpr::Paren(p) => self.value_size(&p.expr)
+        }
+    }
+
+    /// convert an integer expression to the given size
+    fn cast(expr : Expr, size : Size) -> Expr {
+        match expr.ty {
+            Type::Int(s) if s != size => Expr::new(ExprKind::Cast(Box::new(expr)), Type::Int(size)),
+            _ => expr
+        }
+    }
+
+    /// lower an expression that must produce a value
+    fn lower_int(&mut self, expr : &ast::Expr, size_hint : Option<Size>) -> Result<Expr, CompilerError> {
+        let value = self.lower_expr(expr, size_hint)?;
+        if value.ty == Type::Void {
+            return Err(CompilerError::from_span(
+                CompilerErrorKind::MismatchedTypes,
+                "expected a value but the expression does not return anything",
+                expr.span(),
+                None))
+        }
+        Ok(value)
+    }
+
+    /// lower an expression and convert it to the given size
+    fn lower_value(&mut self, expr : &ast::Expr, size : Size) -> Result<Expr, CompilerError> {
+        let value = self.lower_int(expr, Some(size))?;
+        Ok(Self::cast(value, size))
+    }
+
+    /// lower a condition and convert it to a byte
+    fn lower_condition(&mut self, expr : &ast::Expr) -> Result<Expr, CompilerError> {
+        let value = self.lower_int(expr, None)?;
+        Ok(match value.ty {
+            Type::Int(size) if size != 1 => Expr::new(
+                ExprKind::Binary(BinOpKind::NotEqual, Box::new(value), Box::new(Expr::new(ExprKind::Int(0), Type::Int(size)))),
+                Type::Int(1)),
+            _ => value
+        })
+    }
+
+    fn diverges(stmt : &Stmt) -> bool {
+        match stmt {
+            Stmt::Return(_)
+                | Stmt::Break(_)
+                | Stmt::Continue(_)
+                => true,
+            Stmt::Expr(e)
+                | Stmt::Decl(_, e)
+                => e.ty == Type::Never
+        }
+    }
+
+    /// lower a statement of a body, its error is kept so the next statements are checked too
+    fn lower_body_stmt(&mut self, stmt : &Statement) -> Option<Stmt> {
+        match self.lower_stmt(stmt) {
+            Ok(s) => s,
+            Err(e) => {
+                self.errors.push(e);
+                None
+            }
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt : &Statement) -> Result<Option<Stmt>, CompilerError> {
+        Ok(Some(match stmt {
+            Statement::Return(ret) => Stmt::Return(self.lower_value(&ret.value, RETURN_SIZE)?),
+            Statement::Break(_)
+                | Statement::Continue(_)
+                => {
+                let (kw, name) = match stmt {
+                    Statement::Break(b) => (&b.break_kw, "break"),
+                    Statement::Continue(c) => (&c.continue_kw, "continue"),
+                    _ => unreachable!()
+                };
+                let target = self.get_loop().ok_or_else(|| CompilerError::from_span(
+                    CompilerErrorKind::OutsideOfLoop,
+                    format!("can't {} outside a loop", name).as_str(),
+                    &kw.span,
+                    None))?;
+                if name == "break" {
+                    self.broken_loops.push(target);
+                    Stmt::Break(target)
+                }else {
+                    Stmt::Continue(target)
+                }
+            },
+            Statement::Noop(_) => return Ok(None),
+            Statement::VarDecl(decl) => {
+                let name = decl.name.span.data;
+                if self.scopes[self.current_scope as usize].get_var(name).is_some() {
+                    return Err(CompilerError::from_span(
+                        CompilerErrorKind::ReeclaretedVariable,
+                        format!("the variable {} is already declared in this scope", name).as_str(),
+                        &decl.name.span,
+                        None))
+                }
+                // the variable is declared even if its type or value is invalid so its uses are not reported too
+                let size = match &decl.type_name {
+                    Some(t) => Some(type_size(t.span.data).ok_or_else(|| {
+                        self.decl_var(&decl.name, DEFAULT_SIZE);
+                        CompilerError::from_span(
+                            CompilerErrorKind::UnknownType,
+                            format!("unknown type : {}", t.span.data).as_str(),
+                            &t.span,
+                            None)
+                    })?),
+                    None => None
+                };
+                let (value, size) = match &decl.value {
+                    Some(v) => {
+                        let hint = size.or(self.value_size(v)).unwrap_or(DEFAULT_SIZE);
+                        let value = self.lower_int(v, Some(hint)).inspect_err(|_| {
+                            self.decl_var(&decl.name, size.unwrap_or(hint));
+                        })?;
+                        let size = size.or(value.ty.size()).unwrap_or(hint);
+                        (Self::cast(value, size), size)
+                    },
+                    None => {
+                        let size = size.unwrap_or(DEFAULT_SIZE);
+                        (Expr::new(ExprKind::Int(0), Type::Int(size)), size)
+                    }
+                };
+                // the variable is declared after its value so `let a = a;` uses the outer a
+                let id = self.decl_var(&decl.name, size);
+                Stmt::Decl(id, value)
+            },
+            Statement::Expr(stmt) => Stmt::Expr(self.lower_expr(&stmt.expr, None)?),
+            Statement::Test(test) => {
+                self.lower_test(test)?;
+                return Ok(None)
+            }
+        }))
+    }
+
+    fn lower_test(&mut self, test : &ast::Test) -> Result<(), CompilerError> {
+        self.test = Some(Test {
+            name : test.name.value.clone(),
+            line : test.test_kw.span.start.line,
+            body : vec![],
+            assertions : vec![],
+            output : None
+        });
+        // the variables of the program are not initialized when a test runs so they are not visible
+        let previous = self.current_scope;
+        self.scopes.push(Scope::new(ScopeKind::Root, None));
+        self.current_scope = (self.scopes.len() - 1) as ScopeId;
+        let body = self.lower_expr(&ast::Expr::BlockExpr(test.body.clone()), None);
+        self.current_scope = previous;
+        let mut result = self.test.take().unwrap();
+        result.body = vec![Stmt::Expr(body?)];
+        self.tests.push(result);
+        Ok(())
+    }
+
+    /// the message argument of an assertion
+    fn assertion_message(arg : &ast::Expr) -> Result<String, CompilerError> {
+        match arg {
+            ast::Expr::PrimaryExpr(PrimaryExpr::StrLit(lit)) => Ok(lit.value.clone()),
+            _ => Err(CompilerError::from_span(
+                CompilerErrorKind::MismatchedTypes,
+                "the message of an assertion must be a string",
+                arg.span(),
+                None))
+        }
+    }
+
+    /// lower assert(cond, "message"), assert_eq(left, right, "message") and assert_output("text")
+    /// a failed assertion returns its code from the test
+    fn lower_assertion(&mut self, call : &ast::Call) -> Result<Expr, CompilerError> {
+        let name = call.name.span.data;
+        if self.test.is_none() {
+            return Err(CompilerError::from_span(
+                CompilerErrorKind::UnknownFunction,
+                format!("{} can only be used in a test", name).as_str(),
+                &call.name.span,
+                None))
+        }
+        let args = call.args();
+        let (required, optional) = match name {
+            "assert" => (1, 1),
+            "assert_eq" => (2, 1),
+            _ => (1, 0)
+        };
+        if args.len() < required || args.len() > required + optional {
+            let count = if optional > 0 {
+                format!("{} to {}", required, required + optional)
+            }else {
+                required.to_string()
+            };
+            return Err(CompilerError::from_span(
+                CompilerErrorKind::BadArgumentCount,
+                format!("{} takes {} arguments but {} were given", name, count, args.len()).as_str(),
+                &call.name.span,
+                None))
+        }
+        if name == "assert_output" {
+            let text = Self::assertion_message(args[0])?;
+            let test = self.test.as_mut().unwrap();
+            test.output.get_or_insert_with(String::new).push_str(&text);
+            return Ok(Expr::new(ExprKind::Void, Type::Void))
+        }
+        let failed = if name == "assert" {
+            let cond = self.lower_condition(args[0])?;
+            Expr::new(ExprKind::Binary(BinOpKind::Equal, Box::new(cond), Box::new(Expr::new(ExprKind::Int(0), Type::Int(1)))), Type::Int(1))
+        }else {
+            let size = self.value_size(args[0])
+                .max(self.value_size(args[1]))
+                .unwrap_or(DEFAULT_SIZE);
+            let left = self.lower_value(args[0], size)?;
+            let right = self.lower_value(args[1], size)?;
+            Expr::new(ExprKind::Binary(BinOpKind::NotEqual, Box::new(left), Box::new(right)), Type::Int(1))
+        };
+        let message = match args.get(required) {
+            Some(arg) => Self::assertion_message(arg)?,
+            None => {
+                let source = &call.name.span.source.buffer;
+                source[call.name.span.offset()..call.rparen.span.offset() + 1].to_string()
+            }
+        };
+        let test = self.test.as_mut().unwrap();
+        let code = ASSERTION_FAILED + test.assertions.len() as i64;
+        test.assertions.push(Assertion {
+            line : call.name.span.start.line,
+            message
+        });
+        let scope = self.enter_scope(ScopeKind::Block);
+        self.exit_scope();
+        let fail = Block {
+            scope,
+            body : vec![Stmt::Return(Expr::new(ExprKind::Int(code), Type::Int(RETURN_SIZE)))],
+            value : None,
+            locations : vec![Self::location(&call.name.span)]
+        };
+        Ok(Expr::new(ExprKind::If(Box::new(failed), Box::new(Expr::new(ExprKind::Block(fail), Type::Never)), None), Type::Void))
+    }
+
+    fn lower_expr(&mut self, expr : &ast::Expr, size_hint : Option<Size>) -> Result<Expr, CompilerError> {
+        Ok(match expr {
+            ast::Expr::WhileExpr(expr) => {
+                let cond = self.lower_condition(&expr.cond)?;
+                let scope = self.enter_scope(ScopeKind::Loop);
+                let body = self.lower_expr(&expr.body, None)?;
+                self.exit_scope();
+                Expr::new(ExprKind::While(scope, Box::new(cond), Box::new(body)), Type::Void)
+            },
+            ast::Expr::LoopExpr(expr) => {
+                let scope = self.enter_scope(ScopeKind::Loop);
+                let body = self.lower_expr(&expr.body, None)?;
+                self.exit_scope();
+                let ty = if self.broken_loops.contains(&scope) {
+                    Type::Void
+                }else {
+                    Type::Never
+                };
+                Expr::new(ExprKind::Loop(scope, Box::new(body)), ty)
+            },
+            ast::Expr::BlockExpr(block) => {
+                let scope = self.enter_scope(ScopeKind::Block);
+                self.scopes[scope as usize].range = Some((block.lcurly.span.offset(), block.rcurly.span.offset() + 1));
+                let mut body = vec![];
+                let mut locations = vec![];
+                for stmt in &block.body {
+                    if let Some(s) = self.lower_body_stmt(stmt) {
+                        body.push(s);
+                        locations.push(Self::location(stmt.span()));
+                    }
+                }
+                let value = match &block.ret {
+                    Some(ret) => {
+                        locations.push(Self::location(ret.span()));
+                        Some(Box::new(self.lower_expr(ret, size_hint)?))
+                    },
+                    None => None
+                };
+                self.exit_scope();
+                let ty = if body.iter().any(Self::diverges) {
+                    Type::Never
+                }else {
+                    value.as_ref().map_or(Type::Void, |v| v.ty)
+                };
+                Expr::new(ExprKind::Block(Block { scope, body, value, locations }), ty)
+            },
+            ast::Expr::IfExpr(expr) => {
+                let cond = self.lower_condition(&expr.cond)?;
+                let then = self.lower_expr(&expr.then, size_hint)?;
+                match &expr._else {
+                    Some(_else) => {
+                        let _else = self.lower_expr(_else, then.ty.size().or(size_hint))?;
+                        let ty = match (then.ty, _else.ty) {
+                            (Type::Int(a), Type::Int(b)) => Type::Int(a.max(b)),
+                            (Type::Int(a), Type::Never) => Type::Int(a),
+                            (Type::Never, Type::Int(b)) => Type::Int(b),
+                            (Type::Never, Type::Never) => Type::Never,
+                            _ => Type::Void
+                        };
+                        let (then, _else) = match ty {
+                            Type::Int(size) => (Self::cast(then, size), Self::cast(_else, size)),
+                            _ => (then, _else)
+                        };
+                        Expr::new(ExprKind::If(Box::new(cond), Box::new(then), Some(Box::new(_else))), ty)
+                    },
+                    None => Expr::new(ExprKind::If(Box::new(cond), Box::new(then), None), Type::Void)
+                }
+            },
+            ast::Expr::AssignExpr(assign) => {
+                let id = self.get_var(assign.name.span.data)
+                    .ok_or_else(|| undeclared_assign(&assign.name.span))?;
+                self.add_reference(id, &assign.name);
+                let value = self.lower_value(&assign.value, self.symbols[id as usize].size)?;
+                Expr::new(ExprKind::Assign(id, Box::new(value)), Type::Void)
+            },
+            ast::Expr::BinExpr(expr) => {
+                let size = self.value_size(&expr.left)
+                    .max(self.value_size(&expr.right))
+                    .or(size_hint)
+                    .unwrap_or(DEFAULT_SIZE);
+                let left = self.lower_value(&expr.left, size)?;
+                let right = self.lower_value(&expr.right, size)?;
+                let ty = match expr.op.kind {
+                    _ if left.ty == Type::Never || right.ty == Type::Never => Type::Never,
+                    BinOpKind::Equal
+                        | BinOpKind::NotEqual
+                        | BinOpKind::Greater
+                        | BinOpKind::GreaterEqual
+                        | BinOpKind::LesserEqual
+                        | BinOpKind::Lesser
+                        => Type::Int(1),
+                    _ => Type::Int(size)
+                };
+                Expr::new(ExprKind::Binary(expr.op.kind.clone(), Box::new(left), Box::new(right)), ty)
+            },
+            ast::Expr::UnaryExpr(expr) => {
+                let value = self.lower_primary(&expr.primary, size_hint)?;
+                match expr.op.kind {
+                    UnaryOpKind::Plus => value,
+                    UnaryOpKind::Minus => {
+                        if value.ty == Type::Void {
+                            return Err(CompilerError::from_span(
+                                CompilerErrorKind::MismatchedTypes,
+                                "expected a value but the expression does not return anything",
+                                &expr.op.token.span,
+                                None))
+                        }
+                        let ty = value.ty;
+                        Expr::new(ExprKind::Neg(Box::new(value)), ty)
+                    }
+                }
+            },
+            ast::Expr::PrimaryExpr(p) => self.lower_primary(p, size_hint)?
+        })
+    }
+
+    fn lower_primary(&mut self, expr : &PrimaryExpr, size_hint : Option<Size>) -> Result<Expr, CompilerError> {
+        Ok(match expr {
+            PrimaryExpr::Call(c) => self.lower_call(c)?,
+            PrimaryExpr::Ident(name) => {
+                let id = self.get_var(name.span.data)
+                    .ok_or_else(|| self.undeclared_var(&name.span))?;
+                self.add_reference(id, name);
+                Expr::new(ExprKind::Var(id), Type::Int(self.symbols[id as usize].size))
+            },
+            PrimaryExpr::IntLit(lit) => {
+                let value = i64::try_from(lit.value).map_err(|_| CompilerError::from_span(
+                    CompilerErrorKind::BadToken,
+                    "integer litteral is too large",
+                    &lit.token.span,
+                    None))?;
+                Expr::new(ExprKind::Int(value), Type::Int(size_hint.unwrap_or(DEFAULT_SIZE)))
+            },
+            PrimaryExpr::VoidLit(_) => Expr::new(ExprKind::Void, Type::Void),
+            PrimaryExpr::StrLit(lit) => return Err(CompilerError::from_span(
+                CompilerErrorKind::MismatchedTypes,
+                "a string can only be the message of an assertion",
+                &lit.token.span,
+                None)),
+            PrimaryExpr::Paren(p) => self.lower_expr(&p.expr, size_hint)?
+        })
+    }
+
+    fn lower_call(&mut self, call : &ast::Call) -> Result<Expr, CompilerError> {
+        let name = call.name.span.data;
+        if ASSERTIONS.contains(&name) {
+            return self.lower_assertion(call)
+        }
+        let func = JOLANG_STD.iter()
+            .find(|x| x.0 == name)
+            .map(|x| &x.1)
+            .ok_or_else(|| {
+                let error = CompilerError::from_span(
+                    CompilerErrorKind::UnknownFunction,
+                    format!("unknown function : {}", name).as_str(),
+                    &call.name.span,
+                    None);
+                match closest_name(name, JOLANG_STD.iter().map(|x| x.0)) {
+                    Some(similar) => error.with_fix(
+                        format!("a builtin function with a similar name exists : {}", similar).as_str(),
+                        Fix::replace(&call.name.span, similar),
+                        call.name.span.source),
+                    None => error
+                }
+            })?;
+        let sig = func.signature();
+        let args = call.args();
+        if args.len() != sig.args.len() {
+            return Err(CompilerError::from_span(
+                CompilerErrorKind::BadArgumentCount,
+                format!("the function {} takes {} arguments but {} were given", name, sig.args.len(), args.len()).as_str(),
+                &call.name.span,
+                None))
+        }
+        let id = self.decl_extern(name, func.as_ref());
+        let mut values = vec![];
+        for (arg, arg_type) in args.into_iter().zip(sig.args.iter()) {
+            values.push(self.lower_value(arg, type_size(arg_type).unwrap_or(RETURN_SIZE))?);
+        }
+        let ty = type_size(&sig.ret).map_or(Type::Void, Type::Int);
+        Ok(Expr::new(ExprKind::Call(id, values), ty))
+    }
+
+    /// read of an undeclared variable, the fix use a visible variable with a similar name
+    fn undeclared_var(&self, span : &SourceSpan) -> CompilerError {
+        let error = CompilerError::from_span(
+            CompilerErrorKind::UnderlaredVariable,
+            format!("unknown variable : {}", span.data).as_str(),
+            span,
+            None);
+        let names = self.scope_chain()
+            .flat_map(|(_, s)| s.get_vars().keys().map(|k| k.as_str()));
+        match closest_name(span.data, names) {
+            Some(similar) => error.with_fix(
+                format!("a variable with a similar name exists : {}", similar).as_str(),
+                Fix::replace(span, similar),
+                span.source),
+            None => error
+        }
+    }
+}
+
+/// assignment to an undeclared variable, the fix turns it into a declaration when it is a statement
+fn undeclared_assign(span : &SourceSpan) -> CompilerError {
+    let error = CompilerError::from_span(
+        CompilerErrorKind::UnderlaredVariable,
+        format!("unknown variable : {}", span.data).as_str(),
+        span,
+        Some(("declare the variable first", format!("let {} = ...;", span.data).as_str())));
+    let before = span.source.buffer[..span.offset()].trim_end();
+    if before.is_empty() || before.ends_with(';') || before.ends_with('{') || before.ends_with('}') {
+        error.with_fix("declare the variable first", Fix::insert(span.offset(), "let "), span.source)
+    }else {
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyze, source_buffer::SourceBuffer};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn hir(code : &str) -> Hir {
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : code.to_string()
+        };
+        analyze(&buf).unwrap()
+    }
+
+    #[test]
+    fn test_scopes() {
+        let hir = hir("let a = 1; { let a : i8 = 2; a = 3; } a = 4;");
+        assert_eq!(hir.symbols.len(), 2);
+        assert_eq!(hir.symbols[0].size, 4);
+        assert_eq!(hir.symbols[0].scope, 0);
+        assert_eq!(hir.symbols[1].size, 1);
+        assert_eq!(hir.scopes[hir.symbols[1].scope as usize].parent, Some(0));
+        // the assignment in the block uses the shadowing variable
+        match &hir.body[1] {
+            Stmt::Expr(Expr { kind : ExprKind::Block(block), .. }) => {
+                assert_eq!(block.body[1], Stmt::Expr(Expr::new(
+                    ExprKind::Assign(1, Box::new(Expr::new(ExprKind::Int(3), Type::Int(1)))),
+                    Type::Void)));
+                assert_eq!(hir.visible_symbols(block.scope), vec![1]);
+            },
+            s => panic!("unexpected statement {:?}", s)
+        }
+        assert_eq!(hir.body[2], Stmt::Expr(Expr::new(
+            ExprKind::Assign(0, Box::new(Expr::new(ExprKind::Int(4), Type::Int(4)))),
+            Type::Void)));
+    }
+
+    #[test]
+    fn test_types() {
+        let hir = hir("let a : i8 = 1; let b = a == 2; let c = input(); return a;");
+        assert_eq!(hir.symbols.iter().map(|s| s.size).collect::<Vec<_>>(), vec![1, 1, 8]);
+        assert_eq!(hir.body[3], Stmt::Return(Expr::new(
+            ExprKind::Cast(Box::new(Expr::new(ExprKind::Var(0), Type::Int(1)))),
+            Type::Int(8))));
+        let hir = self::hir("loop { return 1; } while (1) { break; }");
+        match &hir.body[..] {
+            [Stmt::Expr(l), Stmt::Expr(w)] => {
+                assert_eq!(l.ty, Type::Never);
+                assert_eq!(w.ty, Type::Void);
+                match &w.kind {
+                    ExprKind::While(scope, cond, _) => {
+                        assert_eq!(hir.scopes[*scope as usize].kind, ScopeKind::Loop);
+                        assert_eq!(cond.ty, Type::Int(1));
+                    },
+                    k => panic!("unexpected expression {:?}", k)
+                }
+            },
+            b => panic!("unexpected body {:?}", b)
+        }
+    }
+
+    #[test]
+    fn test_references() {
+        let code = "let a = 1;\n{ let b = a; b = 2; }\nprint(a);";
+        let hir = hir(code);
+        let offset = |pattern : &str| code.find(pattern).unwrap();
+        let refs = hir.references.iter()
+            .map(|r| (r.symbol, r.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(refs, vec![
+            (0, offset("a = 1")),
+            (0, offset("a; b")),
+            (1, offset("b = a")),
+            (1, offset("b = 2")),
+            (0, offset("a);"))]);
+        assert_eq!(hir.reference_at(offset("a);") + 1).map(|r| r.symbol), Some(0));
+        assert_eq!(hir.reference_at(offset("print")), None);
+        // b is only visible in the block after its declaration
+        assert_eq!(hir.symbols_at(offset("b = 2")), vec![1, 0]);
+        assert_eq!(hir.symbols_at(offset("let b")), vec![0]);
+        assert_eq!(hir.symbols_at(offset("print")), vec![0]);
+        assert!(hir.symbols_at(0).is_empty());
+    }
+
+    #[test]
+    fn test_resume() {
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : "let a = 1; let b = a;".to_string()
+        };
+        let first = analyze(&buf).unwrap();
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : "let a : i8 = b; a = 2;".to_string()
+        };
+        let program = crate::ast::AstBuilder::from(crate::lexer::Lexer::new(&buf)).parse_program().unwrap();
+        let hir = HirBuilder::resume(first).build(&program).unwrap();
+        assert_eq!(hir.symbols.len(), 3);
+        assert_eq!(hir.body[0], Stmt::Decl(2, Expr::new(
+            ExprKind::Cast(Box::new(Expr::new(ExprKind::Var(1), Type::Int(4)))),
+            Type::Int(1))));
+        // the new a shadows the previous one
+        assert_eq!(hir.body[1], Stmt::Expr(Expr::new(
+            ExprKind::Assign(2, Box::new(Expr::new(ExprKind::Int(2), Type::Int(1)))),
+            Type::Void)));
+        assert_eq!(hir.scopes[hir.symbols[2].scope as usize].parent, Some(0));
+    }
+}
@@ -0,0 +1,239 @@
+use jolang_shared::ir::instructions::{operand::Size, Instruction};
+use crate::ast::BinOpKind;
+use crate::compiler_error::{CompilerError, CompilerErrorKind};
+use crate::generator::{Generate, IrGenerator};
+use crate::scope::VarId;
+use super::builder::{DEFAULT_SIZE, RETURN_SIZE};
+use super::{Expr, ExprKind, Hir, Stmt};
+
+/// size of the variable ids given to varref
+pub const VAR_ID_SIZE : Size = 4;
+
+fn push_var_ref(generator : &mut IrGenerator, id : VarId) {
+    generator.add(Instruction::Iconst(VAR_ID_SIZE, id as i64));
+    generator.add(Instruction::Varref());
+}
+
+/// generate a branch of an if, the value is stored in result if there is one
+fn gen_branch(expr : &Expr, generator : &mut IrGenerator, result : Option<(VarId, Size)>) -> Result<(), CompilerError> {
+    let before = generator.stack_size();
+    if let Some((id, _)) = result {
+        push_var_ref(generator, id);
+    }
+    expr.generate(generator)?;
+    if generator.is_terminated() {
+        return Ok(())
+    }
+    if let Some((_, size)) = result {
+        generator.add(Instruction::Istore(size));
+    }
+    generator.truncate_stack(before);
+    Ok(())
+}
+
+/// generate statements, the instructions of each one get its location
+fn gen_body(body : &[Stmt], locations : &[(usize, usize)], generator : &mut IrGenerator) -> Result<(), CompilerError> {
+    for (i, s) in body.iter().enumerate() {
+        if let Some(&(line, collumn)) = locations.get(i) {
+            generator.set_location(line, collumn);
+        }
+        s.generate(generator)?;
+    }
+    Ok(())
+}
+
+impl Generate for Hir {
+    fn generate(&self, generator : &mut IrGenerator) -> Result<(), CompilerError> {
+        let blk = generator.append_block();
+        let exit_block = generator.append_block();
+        generator.goto_begin(exit_block);
+        generator.add(Instruction::Iconst(RETURN_SIZE, 0));
+        generator.add(Instruction::Iret(RETURN_SIZE));
+        generator.set_block_args(blk, Vec::new());
+        generator.goto_begin(blk);
+        gen_body(&self.body, &self.locations, generator)?;
+        if !generator.is_terminated() {
+            generator.truncate_stack(0);
+            generator.add(Instruction::Br(exit_block));
+        }
+        Ok(())
+    }
+}
+
+impl Generate for Stmt {
+    fn generate(&self, generator : &mut IrGenerator) -> Result<(), CompilerError> {
+        if generator.is_terminated() {
+            return Ok(())
+        }
+        match self {
+            Self::Expr(expr) => {
+                let before = generator.stack_size();
+                expr.generate(generator)?;
+                generator.truncate_stack(before);
+            },
+            Self::Decl(id, value) => {
+                push_var_ref(generator, *id);
+                value.generate(generator)?;
+                if let Some(size) = value.ty.size() {
+                    generator.add(Instruction::Istore(size));
+                }
+            },
+            Self::Return(value) => {
+                value.generate(generator)?;
+                generator.add(Instruction::Iret(RETURN_SIZE));
+            },
+            Self::Break(scope)
+                | Self::Continue(scope)
+                => {
+                let target = match (generator.get_loop(*scope), self) {
+                    (Some((_, exit)), Self::Break(_)) => exit,
+                    (Some((block, _)), _) => block,
+                    // the hir only has loops that contain the statement
+                    (None, _) => return Err(generator.error(
+                        CompilerErrorKind::OutsideOfLoop,
+                        "the loop of this statement is not being generated"))
+                };
+                let depth = generator.get_block_args(target).map_or(0, |a| a.len());
+                generator.truncate_stack(depth);
+                generator.add(Instruction::Br(target));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Generate for Expr {
+    fn generate(&self, generator : &mut IrGenerator) -> Result<(), CompilerError> {
+        if generator.is_terminated() {
+            return Ok(())
+        }
+        let size = self.ty.size().unwrap_or(DEFAULT_SIZE);
+        match &self.kind {
+            ExprKind::Int(value) => {
+                generator.add(Instruction::Iconst(size, *value));
+            },
+            ExprKind::Void => (),
+            ExprKind::Var(id) => {
+                push_var_ref(generator, *id);
+                generator.add(Instruction::Iload(size));
+            },
+            ExprKind::Assign(id, value) => {
+                push_var_ref(generator, *id);
+                value.generate(generator)?;
+                generator.add(Instruction::Istore(value.ty.size().unwrap_or(DEFAULT_SIZE)));
+            },
+            ExprKind::Cast(value) => {
+                value.generate(generator)?;
+                generator.cast(size);
+            },
+            ExprKind::Neg(value) => {
+                value.generate(generator)?;
+                generator.add(Instruction::Ineg(size));
+            },
+            ExprKind::Binary(op, left, right) => {
+                left.generate(generator)?;
+                right.generate(generator)?;
+                let size = left.ty.size().unwrap_or(DEFAULT_SIZE);
+                generator.add(match op {
+                    BinOpKind::Add => Instruction::Iadd(size),
+                    BinOpKind::Sub => Instruction::Isub(size),
+                    BinOpKind::Mul => Instruction::Imul(size),
+                    BinOpKind::Div => Instruction::Idiv(size),
+                    BinOpKind::Equal => Instruction::Ieq(size),
+                    BinOpKind::NotEqual => Instruction::Ine(size),
+                    BinOpKind::Greater => Instruction::Igt(size),
+                    BinOpKind::GreaterEqual => Instruction::Ige(size),
+                    BinOpKind::LesserEqual => Instruction::Ile(size),
+                    BinOpKind::Lesser => Instruction::Ilt(size),
+                    BinOpKind::LShift => Instruction::Ishl(size),
+                    BinOpKind::RShift => Instruction::Iashr(size)
+                });
+            },
+            ExprKind::Call(id, args) => {
+                for arg in args {
+                    arg.generate(generator)?;
+                }
+                generator.add(Instruction::Call(*id));
+            },
+            ExprKind::Block(block) => {
+                // the code after the block is part of the statement that contains it
+                let (line, collumn) = generator.get_location();
+                gen_body(&block.body, &block.locations, generator)?;
+                if let Some(value) = &block.value {
+                    if let Some(&(line, collumn)) = block.locations.get(block.body.len()) {
+                        generator.set_location(line, collumn);
+                    }
+                    value.generate(generator)?;
+                }
+                generator.set_location(line, collumn);
+            },
+            ExprKind::If(cond, then, _else) => {
+                let then_block = generator.append_block();
+                let else_block = generator.append_block();
+                let after_block = match _else {
+                    Some(_) => generator.append_block(),
+                    None => else_block
+                };
+                generator.set_block_args(after_block, generator.stack_types());
+                cond.generate(generator)?;
+                generator.add(Instruction::Briz(else_block, then_block));
+                // the value is passed to the after block with a variable
+                let result = self.ty.size().map(|size| (generator.add_var_slot(size), size));
+
+                generator.goto_begin(then_block);
+                gen_branch(then, generator, result)?;
+                if !generator.is_terminated() {
+                    generator.add(Instruction::Br(after_block));
+                }
+                if let Some(_else) = _else {
+                    generator.goto_begin(else_block);
+                    gen_branch(_else, generator, result)?;
+                    if !generator.is_terminated() {
+                        generator.add(Instruction::Br(after_block));
+                    }
+                }
+                generator.goto_end(after_block);
+                if let Some((id, size)) = result {
+                    push_var_ref(generator, id);
+                    generator.add(Instruction::Iload(size));
+                }
+            },
+            ExprKind::While(scope, cond, body) => {
+                let while_cond = generator.append_block();
+                let while_body = generator.append_block();
+                let after_block = generator.append_block();
+                generator.add(Instruction::Br(while_cond));
+                generator.goto_begin(while_cond);
+                cond.generate(generator)?;
+                generator.add(Instruction::Briz(after_block, while_body));
+                generator.goto_begin(while_body);
+                generator.push_loop(*scope, while_cond, after_block);
+                let before = generator.stack_size();
+                body.generate(generator)?;
+                generator.truncate_stack(before);
+                generator.pop_loop();
+                if !generator.is_terminated() {
+                    generator.add(Instruction::Br(while_cond));
+                }
+                generator.goto_end(after_block);
+            },
+            ExprKind::Loop(scope, body) => {
+                let loop_body = generator.append_block();
+                let after_block = generator.append_block();
+                generator.set_block_args(after_block, generator.stack_types());
+                generator.add(Instruction::Br(loop_body));
+                generator.goto_begin(loop_body);
+                generator.push_loop(*scope, loop_body, after_block);
+                let before = generator.stack_size();
+                body.generate(generator)?;
+                generator.truncate_stack(before);
+                generator.pop_loop();
+                if !generator.is_terminated() {
+                    generator.add(Instruction::Br(loop_body));
+                }
+                generator.goto_end(after_block);
+            }
+        }
+        Ok(())
+    }
+}
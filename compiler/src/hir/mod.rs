@@ -0,0 +1,192 @@
+//! high level intermediate representation : the ast with resolved names and types
+mod builder;
+pub mod generators;
+pub use builder::HirBuilder;
+use jolang_shared::ir::{instructions::operand::{FnId, Size}, IrExternalFn};
+use crate::{ast::BinOpKind, scope::{Scope, ScopeId, SymbolId}};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    /// the expression doesn't produce a value
+    Void,
+    /// integer of the given size in bytes
+    Int(Size),
+    /// the expression never finish (e.g. a block that returns)
+    Never
+}
+
+impl Type {
+    pub fn size(&self) -> Option<Size> {
+        match self {
+            Self::Int(size) => Some(*size),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub name : String,
+    pub size : Size,
+    /// scope where the variable is declared
+    pub scope : ScopeId,
+    /// position of the name in the declaration
+    pub line : usize,
+    pub collumn : usize,
+    /// byte offset of the name in the declaration
+    pub offset : usize
+}
+
+/// a use or the declaration of a variable in the source
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    pub symbol : SymbolId,
+    /// byte offset and length of the name
+    pub offset : usize,
+    pub len : usize
+}
+
+#[derive(Debug, Clone)]
+pub struct Hir {
+    /// the variables, the id of a symbol is its index
+    pub symbols : Vec<Symbol>,
+    /// the scopes, the first one is the root scope
+    pub scopes : Vec<Scope>,
+    pub ext_fn : Vec<IrExternalFn>,
+    pub body : Vec<Stmt>,
+    /// line and collumn of each statement of the body
+    pub locations : Vec<(usize, usize)>,
+    /// the uses and declarations of the variables
+    pub references : Vec<Reference>,
+    /// the test blocks, they are not part of the body
+    pub tests : Vec<Test>
+}
+
+/// code returned by a test when an assertion fails, the index of the assertion is added to it
+pub const ASSERTION_FAILED : i64 = i64::MIN;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Assertion {
+    pub line : usize,
+    /// the message of the assertion or its source
+    pub message : String
+}
+
+/// test "name" { ... }, each test is compiled in its own program
+#[derive(Debug, PartialEq, Clone)]
+pub struct Test {
+    pub name : String,
+    pub line : usize,
+    pub body : Vec<Stmt>,
+    pub assertions : Vec<Assertion>,
+    /// the text printed by the test, given with assert_output
+    pub output : Option<String>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stmt {
+    Expr(Expr),
+    /// declaration with the initial value (already converted to the size of the variable)
+    Decl(SymbolId, Expr),
+    Return(Expr),
+    /// break out of the loop with the given scope
+    Break(ScopeId),
+    Continue(ScopeId)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expr {
+    pub kind : ExprKind,
+    pub ty : Type
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub scope : ScopeId,
+    pub body : Vec<Stmt>,
+    pub value : Option<Box<Expr>>,
+    /// line and collumn of each statement of the body then of the value
+    pub locations : Vec<(usize, usize)>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExprKind {
+    Int(i64),
+    Void,
+    Var(SymbolId),
+    Assign(SymbolId, Box<Expr>),
+    /// convert an integer to the size of the expression type
+    Cast(Box<Expr>),
+    Neg(Box<Expr>),
+    /// both operands have the same size
+    Binary(BinOpKind, Box<Expr>, Box<Expr>),
+    /// arguments are already converted to the signature
+    Call(FnId, Vec<Expr>),
+    Block(Block),
+    /// condition (1 byte), then, else
+    If(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    /// loop scope, condition (1 byte), body
+    While(ScopeId, Box<Expr>, Box<Expr>),
+    Loop(ScopeId, Box<Expr>)
+}
+
+impl Expr {
+    pub fn new(kind : ExprKind, ty : Type) -> Self {
+        Self {
+            kind,
+            ty
+        }
+    }
+}
+
+impl Hir {
+    /// the program that only runs a test
+    pub fn test_program(&self, index : usize) -> Hir {
+        let mut hir = self.clone();
+        hir.body = self.tests[index].body.clone();
+        // the body of a test is a block with its own locations
+        hir.locations.clear();
+        hir.tests.clear();
+        hir
+    }
+
+    /// the variables visible in a scope, the innermost first
+    pub fn visible_symbols(&self, scope : ScopeId) -> Vec<SymbolId> {
+        let mut symbols = vec![];
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            let scope = &self.scopes[id as usize];
+            let mut ids : Vec<SymbolId> = scope.get_vars().values().copied().collect();
+            ids.sort();
+            for id in ids {
+                // skip the shadowed variables
+                let name = &self.symbols[id as usize].name;
+                if !symbols.iter().any(|s : &SymbolId| &self.symbols[*s as usize].name == name) {
+                    symbols.push(id);
+                }
+            }
+            current = scope.parent;
+        }
+        symbols
+    }
+
+    /// the innermost scope at a byte offset of the source
+    pub fn scope_at(&self, offset : usize) -> ScopeId {
+        self.scopes.iter()
+            .rposition(|s| s.range.is_some_and(|(start, end)| start <= offset && offset < end))
+            .unwrap_or(0) as ScopeId
+    }
+
+    /// the variable whose name contains a byte offset of the source
+    pub fn reference_at(&self, offset : usize) -> Option<&Reference> {
+        self.references.iter()
+            .find(|r| r.offset <= offset && offset <= r.offset + r.len)
+    }
+
+    /// the variables that can be used at a byte offset of the source
+    pub fn symbols_at(&self, offset : usize) -> Vec<SymbolId> {
+        self.visible_symbols(self.scope_at(offset)).into_iter()
+            .filter(|id| self.symbols[*id as usize].offset < offset)
+            .collect()
+    }
+}
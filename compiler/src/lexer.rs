@@ -47,7 +47,9 @@ pub enum TokenKind {
     Comma,
     Keyword(KeywordType),
     Ident,
-    Int
+    Int,
+    /// string litteral between double quotes
+    Str
 });
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -74,15 +76,103 @@ impl Token<'_> {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    /// spaces and tabs
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+    /// other text ignored by the lexer
+    Skipped
+}
+
+/// text between the tokens
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind : TriviaKind,
+    /// byte offset in the source
+    pub offset : usize,
+    pub text : &'a str
+}
+
+/// token with the whitespaces and the comments around it
+/// the trailing trivia are the ones on the same line as the token
+#[derive(Clone, Debug, PartialEq)]
+pub struct LosslessToken<'a> {
+    pub leading : Vec<Trivia<'a>>,
+    pub token : Token<'a>,
+    pub trailing : Vec<Trivia<'a>>
+}
+
+impl std::fmt::Display for Trivia<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl std::fmt::Display for LosslessToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for t in &self.leading {
+            write!(f, "{}", t)?;
+        }
+        write!(f, "{}", self.token.span.data)?;
+        for t in &self.trailing {
+            write!(f, "{}", t)?;
+        }
+        Ok(())
+    }
+}
+
+/// split the text between two tokens into trivia
+pub fn split_trivia(text : &str, offset : usize) -> Vec<Trivia<'_>> {
+    let mut trivia = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (kind, len) = if offset == 0 && rest.len() == text.len() && rest.starts_with("#!") {
+            // shebang line
+            (TriviaKind::Skipped, rest.find(['\r', '\n']).unwrap_or(rest.len()))
+        }else if rest.starts_with('\n') {
+            (TriviaKind::Newline, 1)
+        }else if rest.starts_with("\r\n") {
+            (TriviaKind::Newline, 2)
+        }else if rest.starts_with("//") {
+            (TriviaKind::LineComment, rest.find('\n').map_or(rest.len(), |x| x - rest[..x].ends_with('\r') as usize))
+        }else if rest.starts_with("/*") {
+            (TriviaKind::BlockComment, rest[2..].find("*/").map_or(rest.len(), |x| x + 4))
+        }else if rest.starts_with(|c : char| c.is_whitespace()) {
+            (TriviaKind::Whitespace, rest.find(|c : char| !c.is_whitespace() || c == '\n' || c == '\r').unwrap_or(rest.len()).max(1))
+        }else {
+            (TriviaKind::Skipped, rest.find(|c : char| c.is_whitespace() || c == '/').unwrap_or(rest.len()).max(1))
+        };
+        // never split a char
+        let len = (len..=rest.len()).find(|l| rest.is_char_boundary(*l)).unwrap_or(rest.len());
+        trivia.push(Trivia {
+            kind,
+            offset : offset + text.len() - rest.len(),
+            text : &rest[..len]
+        });
+        rest = &rest[len..];
+    }
+    trivia
+}
+
 pub struct Lexer<'a> {
     pub reader : SourceReader<'a>
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source : &'a SourceBuffer) -> Self{
-        Self {
+        let mut lexer = Self {
             reader : SourceReader::from(source)
+        };
+        // skip the shebang line of a script
+        if source.buffer.starts_with("#!") {
+            while lexer.reader.peek_char().is_some_and(|c| c != '\n') {
+                lexer.reader.next_char();
+            }
         }
+        lexer
     }
 
     /// ignore whitespaces
@@ -94,8 +184,8 @@ impl<'a> Lexer<'a> {
                     let error = Some(Err(CompilerError::new(
                             super::compiler_error::CompilerErrorKind::BadToken,
                             format!("unterminated block comment").as_str(),
-                            self.reader.source.path.to_str().unwrap(),
-                            self.reader.source.get_line(self.reader.get_cursor().line).unwrap(), 
+                            self.reader.source.path.to_str().unwrap_or_default(),
+                            self.reader.source.get_line(self.reader.get_cursor().line).unwrap_or_default(), 
                             self.reader.get_cursor().line as u32,
                             self.reader.get_cursor().collumn as u32,
                             None)));
@@ -133,13 +223,49 @@ impl<'a> Lexer<'a> {
     }
 }
 
+impl<'a> Lexer<'a> {
+    /// lex the whole source and keep the whitespaces and the comments
+    /// the trivia after the last token are returned separately
+    pub fn lossless(self) -> Result<(Vec<LosslessToken<'a>>, Vec<Trivia<'a>>), CompilerError> {
+        let buffer = self.reader.source.buffer.as_str();
+        let mut tokens : Vec<LosslessToken<'a>> = vec![];
+        let mut pos = 0;
+        for token in self {
+            let token = token?;
+            let start = token.span.offset();
+            let leading = Self::attach_trailing(tokens.last_mut(), split_trivia(&buffer[pos..start], pos));
+            pos = start + token.span.size;
+            tokens.push(LosslessToken {
+                leading,
+                token,
+                trailing : vec![]
+            });
+        }
+        let eof = Self::attach_trailing(tokens.last_mut(), split_trivia(&buffer[pos..], pos));
+        Ok((tokens, eof))
+    }
+
+    /// give the trivia before the first newline to the previous token and return the others
+    fn attach_trailing(previous : Option<&mut LosslessToken<'a>>, mut trivia : Vec<Trivia<'a>>) -> Vec<Trivia<'a>> {
+        if let Some(previous) = previous {
+            let end = trivia.iter()
+                .position(|t| t.kind == TriviaKind::Newline)
+                .unwrap_or(trivia.len());
+            previous.trailing = trivia.drain(..end).collect();
+        }
+        trivia
+    }
+}
+
 impl<'a> Iterator for Lexer<'a> {
     type Item = Result<Token<'a>, CompilerError>;
    
     fn next(&mut self) -> Option<Self::Item> {
-        let _ = self.skip_whitespaces_and_commants()?;
+        if let Err(e) = self.skip_whitespaces_and_commants()? {
+            return Some(Err(e))
+        }
         // test for integer litteral
-        if self.reader.peek_char().unwrap().is_ascii_digit() {
+        if self.reader.peek_char()?.is_ascii_digit() {
             let start = self.reader.current_cursor;
             let first_char = self.reader.peek_char();
             self.reader.next_char();
@@ -167,6 +293,39 @@ impl<'a> Iterator for Lexer<'a> {
             return Some(Ok(Token { kind : TokenKind::Int, span } ))
         }
 
+        // test for string litteral
+        if self.reader.peek_char()? == '"' {
+            let start = self.reader.current_cursor;
+            let mut size = 1;
+            self.reader.next_char();
+            loop {
+                match self.reader.peek_char() {
+                    Some('"') => break,
+                    Some('\n') | None => return Some(Err(CompilerError::new(
+                        super::compiler_error::CompilerErrorKind::BadToken,
+                        "unterminated string litteral",
+                        self.reader.source.path.to_str().unwrap_or_default(),
+                        self.reader.source.get_line(start.line).unwrap_or_default(),
+                        start.line as u32,
+                        start.collumn as u32,
+                        None))),
+                    Some(c) => {
+                        size += c.len_utf8();
+                        self.reader.next_char();
+                        // the escaped char can be a quote
+                        if let Some(escaped) = self.reader.peek_char().filter(|e| c == '\\' && *e != '\n') {
+                            size += escaped.len_utf8();
+                            self.reader.next_char();
+                        }
+                    }
+                }
+            }
+            self.reader.next_char();
+            size += 1;
+            let span : SourceSpan<'a> = unsafe { std::mem::transmute(SourceSpan::at(self.reader.source, start, size)) };
+            return Some(Ok(Token { kind : TokenKind::Str, span } ))
+        }
+
         if self.reader.get_cursor().data_ref.chars().nth(1).is_some() {
             let start = self.reader.current_cursor.clone();
             // test for the two chars tokens
@@ -248,8 +407,8 @@ impl<'a> Iterator for Lexer<'a> {
         return Some(Err(CompilerError::new(
                     super::compiler_error::CompilerErrorKind::BadToken,
                     format!("bad token : {}", token).as_str(),
-                    self.reader.source.path.to_str().unwrap(),
-                    self.reader.source.get_line(cursor.line).unwrap(), 
+                    self.reader.source.path.to_str().unwrap_or_default(),
+                    self.reader.source.get_line(cursor.line).unwrap_or_default(), 
                     cursor.line as u32,
                     cursor.collumn as u32,
                     None)))
@@ -262,6 +421,65 @@ mod tests {
     use core::panic;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_shebang() {
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : String::from("#!/usr/bin/env jolang\nprint(1);")
+        };
+        let tokens : Vec<_> = Lexer::new(&buf)
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[0].span.data, "print");
+        assert_eq!(tokens[0].span.start.line, 2);
+        let (tokens, _) = Lexer::new(&buf).lossless().unwrap();
+        assert_eq!(tokens[0].leading[0], Trivia {
+            kind : TriviaKind::Skipped,
+            offset : 0,
+            text : "#!/usr/bin/env jolang"
+        });
+        // only the first line can be a shebang
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : String::from("\n#!")
+        };
+        assert!(Lexer::new(&buf).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_string() {
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : String::from(r#"test "a \"b\" é" "";"#)
+        };
+        let tokens : Vec<_> = Lexer::new(&buf)
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(tokens.iter().map(|t| t.kind.clone()).collect::<Vec<_>>(), vec![TokenKind::Ident, TokenKind::Str, TokenKind::Str, TokenKind::Semicolon]);
+        assert_eq!(tokens[1].span.data, r#""a \"b\" é""#);
+        assert_eq!(tokens[2].span.data, r#""""#);
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : String::from("\"unterminated\n\"")
+        };
+        assert!(Lexer::new(&buf).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        // a newline is counted on the line it ends, also at the start of the source
+        let buf = SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : String::from("\nlet a;\n\n  print(a);")
+        };
+        let tokens : Vec<_> = Lexer::new(&buf)
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!((tokens[0].span.data, tokens[0].span.start.line, tokens[0].span.start.collumn), ("let", 2, 1));
+        assert_eq!((tokens[3].span.data, tokens[3].span.start.line, tokens[3].span.start.collumn), ("print", 4, 3));
+    }
+
     #[test]
     fn test_single_char() {
         let buf = SourceBuffer {
@@ -1,33 +1,159 @@
 use ast::AstBuilder;
+use compiler_error::CompilerError;
 use generator::{Generate, IrGenerator};
+use hir::{Hir, HirBuilder};
+use jolang_shared::ir::IrObject;
 use lexer::Lexer;
 use source_buffer::SourceBuffer;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::path::PathBuf;
-use std::fs::{File, OpenOptions};
+use std::fs::OpenOptions;
 use jolang_shared::ir::writer::write;
+use jolang_shared::ir::verifier::verify;
+use jolang_shared::ir::passes::PassManager;
+use jolang_shared::VERSION_STR;
 pub mod source_buffer;
+pub mod cache;
 pub mod lexer;
 pub mod compiler_error;
+pub mod error_codes;
+pub mod fix;
+pub mod formatter;
+pub mod lsp;
 pub mod source_span;
 pub mod ast;
+pub mod cst;
 pub mod source_reader;
 pub mod generator;
+pub mod hir;
 pub mod scope;
 
-pub fn build(source_path : PathBuf, _output_path : PathBuf) -> Result<()> {
+/// parse a source buffer and resolve its names and types
+pub fn analyze(source : &SourceBuffer) -> Result<Hir, CompilerError> {
+    let program = AstBuilder::from(Lexer::new(source)).parse_program()?;
+    HirBuilder::new().build(&program)
+}
+
+/// analyze a source and return every error of its statements, the parsing stops at the first syntax error
+pub fn analyze_all(source : &SourceBuffer) -> Result<Hir, Vec<CompilerError>> {
+    let program = AstBuilder::from(Lexer::new(source)).parse_program().map_err(|e| vec![e])?;
+    HirBuilder::new().build_all(&program)
+}
+
+/// compile a source buffer into an ir object without optimizing it
+pub fn compile(source : &SourceBuffer) -> Result<IrObject, CompilerError> {
+    compile_with(source, &PassManager::new())
+}
+
+/// compile a source buffer and run the optimization passes on its ir
+pub fn compile_with(source : &SourceBuffer, passes : &PassManager) -> Result<IrObject, CompilerError> {
+    let hir = analyze(source)?;
+    let mut generator = IrGenerator::from(&hir);
+    hir.generate(&mut generator).map_err(|e| e.with_source(source))?;
+    let mut object = generator.into_ir();
+    passes.run(&mut object);
+    object.metadata.push(("compiler".to_string(), format!("jolangc {}", VERSION_STR)));
+    object.metadata.push(("source_hash".to_string(), blake3::hash(source.buffer.as_bytes()).to_hex().to_string()));
+    object.metadata.push(("build_flags".to_string(), passes.flags().to_string()));
+    Ok(object)
+}
+
+pub fn build(source_path : PathBuf, _output_path : PathBuf, passes : &PassManager) -> Result<()> {
     let source = SourceBuffer::open(source_path)?;
-    match AstBuilder::from(Lexer::new(&source)).parse_program() {
-        Ok(p) => {
-            let mut generator = IrGenerator::new();
-            p.generate(&mut generator, None);
-            let mut obj_file = OpenOptions::new()
-                .create(true)
-                .write(true)
-                .open(_output_path)?;
-            write(generator.into_ir(), &mut obj_file)?;
-            Ok(())
-        },
-        Err(e) => return Err(e.into())
+    let object = compile_with(&source, passes)?;
+    // the generator and the passes are expected to produce valid ir, the runtimes verify it anyway
+    if cfg!(debug_assertions) {
+        verify(&object).context("the compiler generated invalid ir")?;
+    }
+    let mut obj_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(_output_path)?;
+    write(object, &mut obj_file)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jolang_shared::ir::verifier::errors;
+
+    fn source(code : &str) -> SourceBuffer {
+        SourceBuffer {
+            path : PathBuf::from("test.jol"),
+            buffer : code.to_string()
+        }
+    }
+
+    #[test]
+    fn test_semantic_errors() {
+        use compiler_error::CompilerErrorKind;
+        let cases = [
+            ("a = 5;", CompilerErrorKind::UnderlaredVariable),
+            ("print(a);", CompilerErrorKind::UnderlaredVariable),
+            ("let a = 1; let a = 2;", CompilerErrorKind::ReeclaretedVariable),
+            ("prnt(5);", CompilerErrorKind::UnknownFunction),
+            ("let a : i256 = 0;", CompilerErrorKind::UnknownType),
+            ("break;", CompilerErrorKind::OutsideOfLoop),
+            ("if (1) { continue; }", CompilerErrorKind::OutsideOfLoop),
+            ("let a = print(1);", CompilerErrorKind::MismatchedTypes),
+            ("pow(1);", CompilerErrorKind::BadArgumentCount),
+        ];
+        for (code, kind) in cases {
+            match compile(&source(code)) {
+                Ok(_) => panic!("{} should not compile", code),
+                Err(e) => assert_eq!(e.kind(), &kind, "{}", code)
+            }
+        }
+        assert!(compile(&source("let a = 1; { let a = 2; } loop { break; } return a;")).is_ok());
+    }
+
+    #[test]
+    fn test_generation_error() {
+        use compiler_error::CompilerErrorKind;
+        let code = source("let a = 1;\nprint(a);\nbreak;");
+        let mut hir = analyze(&source("let a = 1;\nprint(a);")).unwrap();
+        // a hir built by hand can reference a loop that doesn't contain the statement
+        hir.body.push(hir::Stmt::Break(0));
+        hir.locations.push((3, 1));
+        let e = hir.generate(&mut IrGenerator::from(&hir)).unwrap_err().with_source(&code);
+        assert_eq!(e.kind(), &CompilerErrorKind::OutsideOfLoop);
+        assert_eq!((e.line_number(), e.col_number(), e.line()), (3, 1, "break;"));
+    }
+
+    #[test]
+    fn test_no_panic() {
+        let vocabulary = [
+            "let", "if", "else", "while", "loop", "return", "break", "continue",
+            "a", "b", "print", "input", "pow", "randint", "0", "1", "0x1f", "0b", "99999999999999999999999999",
+            "{", "}", "(", ")", ";", ":", "=", "==", "!=", "+", "-", "*", "/", "<", ">", "<=", ">=", "<<", ">>",
+            ",", "i8", "i64", "i128", "//", "/*", "*/", "\n", " ", "$", "é"
+        ];
+        let mut seed : u64 = 0x2545F4914F6CDD1D;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..5000 {
+            let len = next() % 24;
+            let code : String = (0..len)
+                .map(|_| vocabulary[(next() % vocabulary.len() as u64) as usize])
+                .collect::<Vec<_>>()
+                .join(if next() % 2 == 0 { " " } else { "" });
+            let buf = source(&code);
+            if let Ok(object) = compile(&buf) {
+                assert_eq!(errors(&object), vec![], "{}", code);
+            }
+            if let Ok(cst) = cst::Cst::parse(&buf) {
+                assert_eq!(cst.to_string(), code);
+            }
+        }
+        let example = include_str!("../../exemple/fibonacci.jol");
+        for (i, _) in example.char_indices() {
+            let _ = compile(&source(&example[..i]));
+        }
     }
 }
@@ -0,0 +1,603 @@
+//! language server (used by `jolang lsp`), json-rpc messages over a stream
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use anyhow::{anyhow, Result};
+use jolang_shared::ffi::jolang_std::JOLANG_STD;
+use jolang_shared::ir::instructions::operand::Size;
+use serde_json::{json, Value};
+use crate::compiler_error::CompilerError;
+use crate::hir::Hir;
+use crate::source_buffer::SourceBuffer;
+use crate::analyze;
+
+const PARSE_ERROR : i64 = -32700;
+const METHOD_NOT_FOUND : i64 = -32601;
+const INVALID_PARAMS : i64 = -32602;
+
+// lsp enums
+const SEVERITY_ERROR : u32 = 1;
+const COMPLETION_FUNCTION : u32 = 3;
+const COMPLETION_VARIABLE : u32 = 6;
+const SYMBOL_VARIABLE : u32 = 13;
+const SYNC_FULL : u32 = 1;
+
+/// read the content of a message with its Content-Length header, None at the end of the stream
+fn read_content(reader : &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None)
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("message without Content-Length"))?;
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+/// read a message with its Content-Length header, None at the end of the stream
+pub fn read_message(reader : &mut impl BufRead) -> Result<Option<Value>> {
+    match read_content(reader)? {
+        Some(content) => Ok(Some(serde_json::from_slice(&content)?)),
+        None => Ok(None)
+    }
+}
+
+pub fn write_message(writer : &mut impl Write, message : &Value) -> Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// a line and a utf-16 character as in the protocol
+fn position(text : &str, offset : usize) -> Value {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line" : before.matches('\n').count(),
+        "character" : before[line_start..].encode_utf16().count()
+    })
+}
+
+/// byte offset of a protocol position, clamped to the line
+fn offset(text : &str, position : &Value) -> Option<usize> {
+    let line = position.get("line")?.as_u64()? as usize;
+    let character = position.get("character")?.as_u64()? as usize;
+    let mut line_start = 0;
+    for _ in 0..line {
+        line_start += text[line_start..].find('\n')? + 1;
+    }
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + i)
+        }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+fn range(text : &str, start : usize, end : usize) -> Value {
+    json!({
+        "start" : position(text, start),
+        "end" : position(text, end)
+    })
+}
+
+fn type_name(size : Size) -> String {
+    format!("i{}", size * 8)
+}
+
+fn builtin_detail(name : &str) -> Option<String> {
+    let sig = JOLANG_STD.iter().find(|x| x.0 == name)?.1.signature();
+    Some(format!("fn {}({}) -> {}", name, sig.args.join(", "), sig.ret))
+}
+
+/// the identifier around a byte offset and its start
+fn word_at(text : &str, offset : usize) -> (usize, &str) {
+    let is_ident = |c : char| c.is_ascii_alphanumeric() || c == '_';
+    let start = text[..offset].rfind(|c| !is_ident(c)).map_or(0, |i| i + 1);
+    let end = text[offset..].find(|c| !is_ident(c)).map_or(text.len(), |i| offset + i);
+    (start, &text[start..end])
+}
+
+struct Document {
+    source : SourceBuffer,
+    /// the last successful analysis and the text it was made on
+    hir : Option<Hir>,
+    analyzed_text : String,
+    /// error of the current text, the analysis is out of date if there is one
+    error : Option<CompilerError>
+}
+
+impl Document {
+    fn new(uri : &str, text : String) -> Self {
+        let path = uri.strip_prefix("file://").unwrap_or(uri);
+        let mut document = Self {
+            source : SourceBuffer::from_string(path, text),
+            hir : None,
+            analyzed_text : String::new(),
+            error : None
+        };
+        document.update_hir();
+        document
+    }
+
+    fn update_hir(&mut self) {
+        match analyze(&self.source) {
+            Ok(hir) => {
+                self.hir = Some(hir);
+                self.analyzed_text = self.source.buffer.clone();
+                self.error = None;
+            },
+            Err(e) => self.error = Some(e)
+        }
+    }
+
+    fn set_text(&mut self, text : String) {
+        self.source.buffer = text;
+        self.update_hir();
+    }
+
+    /// the analysis if it matches the text
+    fn current_hir(&self) -> Option<&Hir> {
+        self.hir.as_ref().filter(|_| self.error.is_none())
+    }
+
+    fn diagnostics(&self) -> Vec<Value> {
+        let text = &self.source.buffer;
+        let error = match &self.error {
+            Some(e) => e,
+            None => return vec![]
+        };
+        // the error is on a line and a collumn (in characters) both starting at 1
+        let line_start = text.split_inclusive('\n')
+            .take(error.line_number().saturating_sub(1) as usize)
+            .map(|l| l.len())
+            .sum::<usize>();
+        let line = error.line();
+        let column = line.char_indices()
+            .nth(error.col_number().saturating_sub(1) as usize)
+            .map_or(line.len(), |(i, _)| i);
+        let start = (line_start + column).min(text.len());
+        let (word_start, word) = word_at(text, start);
+        let end = if word.is_empty() || word_start != start {
+            text[start..].chars().next().map_or(start, |c| start + c.len_utf8())
+        }else {
+            start + word.len()
+        };
+        vec![json!({
+            "range" : range(text, start, end),
+            "severity" : SEVERITY_ERROR,
+            "code" : error.kind().code(),
+            "source" : "jolang",
+            "message" : error.message()
+        })]
+    }
+}
+
+/// the state of the server, messages are given to `handle` in the order they are received
+pub struct Server {
+    documents : HashMap<String, Document>,
+    shutdown : bool,
+    exit : bool
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self {
+            documents : HashMap::new(),
+            shutdown : false,
+            exit : false
+        }
+    }
+
+    /// the client asked the server to stop
+    pub fn exited(&self) -> bool {
+        self.exit
+    }
+
+    /// the exit code is an error if the client didn't shutdown the server first
+    pub fn exit_code(&self) -> i32 {
+        if self.shutdown { 0 } else { 1 }
+    }
+
+    /// handle a message, returns the messages to send to the client
+    pub fn handle(&mut self, message : &Value) -> Vec<Value> {
+        let method = message.get("method").and_then(|m| m.as_str()).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notification(method, &params)
+        };
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities" : {
+                    "textDocumentSync" : SYNC_FULL,
+                    "definitionProvider" : true,
+                    "hoverProvider" : true,
+                    "completionProvider" : {},
+                    "documentSymbolProvider" : true
+                },
+                "serverInfo" : {
+                    "name" : "jolang",
+                    "version" : jolang_shared::VERSION_STR
+                }
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/completion" => self.completion(&params),
+            "textDocument/documentSymbol" => self.document_symbols(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method : {}", method)))
+        };
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc" : "2.0", "id" : id, "result" : result }),
+            Err((code, message)) => json!({
+                "jsonrpc" : "2.0",
+                "id" : id,
+                "error" : { "code" : code, "message" : message }
+            })
+        }]
+    }
+
+    fn notification(&mut self, method : &str, params : &Value) -> Vec<Value> {
+        let uri = params.pointer("/textDocument/uri")
+            .and_then(|u| u.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.pointer("/textDocument/text")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default();
+                self.documents.insert(uri.clone(), Document::new(&uri, text.to_string()));
+            },
+            "textDocument/didChange" => {
+                // full synchronisation : the last change is the whole text
+                let text = params.get("contentChanges")
+                    .and_then(|c| c.as_array())
+                    .and_then(|c| c.last())
+                    .and_then(|c| c.get("text"))
+                    .and_then(|t| t.as_str());
+                match (self.documents.get_mut(&uri), text) {
+                    (Some(document), Some(text)) => document.set_text(text.to_string()),
+                    (None, Some(text)) => {
+                        self.documents.insert(uri.clone(), Document::new(&uri, text.to_string()));
+                    },
+                    _ => return vec![]
+                }
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, vec![])]
+            },
+            "exit" => {
+                self.exit = true;
+                return vec![]
+            },
+            _ => return vec![]
+        }
+        match self.documents.get(&uri) {
+            Some(document) => vec![publish_diagnostics(&uri, document.diagnostics())],
+            None => vec![]
+        }
+    }
+
+    /// the document and the byte offset of a text document position
+    fn document_position<'a>(&'a self, params : &'a Value) -> Result<(&'a str, &'a Document, usize), (i64, String)> {
+        let uri = params.pointer("/textDocument/uri")
+            .and_then(|u| u.as_str())
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let document = self.documents.get(uri)
+            .ok_or((INVALID_PARAMS, format!("unknown document : {}", uri)))?;
+        let offset = params.get("position")
+            .and_then(|p| offset(&document.source.buffer, p))
+            .ok_or((INVALID_PARAMS, "invalid position".to_string()))?;
+        Ok((uri, document, offset))
+    }
+
+    fn definition(&self, params : &Value) -> Result<Value, (i64, String)> {
+        let (uri, document, offset) = self.document_position(params)?;
+        let hir = match document.current_hir() {
+            Some(hir) => hir,
+            None => return Ok(Value::Null)
+        };
+        let text = &document.source.buffer;
+        Ok(match hir.reference_at(offset) {
+            Some(r) => {
+                let symbol = &hir.symbols[r.symbol as usize];
+                json!({
+                    "uri" : uri,
+                    "range" : range(text, symbol.offset, symbol.offset + symbol.name.len())
+                })
+            },
+            None => Value::Null
+        })
+    }
+
+    fn hover(&self, params : &Value) -> Result<Value, (i64, String)> {
+        let (_, document, offset) = self.document_position(params)?;
+        let text = &document.source.buffer;
+        let (value, start, end) = match document.current_hir().and_then(|hir| Some((hir, hir.reference_at(offset)?))) {
+            Some((hir, r)) => {
+                let symbol = &hir.symbols[r.symbol as usize];
+                (format!("let {} : {}", symbol.name, type_name(symbol.size)), r.offset, r.offset + r.len)
+            },
+            None => {
+                let (start, word) = word_at(text, offset);
+                match builtin_detail(word) {
+                    Some(detail) => (detail, start, start + word.len()),
+                    None => return Ok(Value::Null)
+                }
+            }
+        };
+        Ok(json!({
+            "contents" : {
+                "kind" : "plaintext",
+                "value" : value
+            },
+            "range" : range(text, start, end)
+        }))
+    }
+
+    fn completion(&self, params : &Value) -> Result<Value, (i64, String)> {
+        let (_, document, _) = self.document_position(params)?;
+        let mut items = vec![];
+        // an older analysis is still good enough for the names, the position is on its text
+        let offset = params.get("position").and_then(|p| offset(&document.analyzed_text, p));
+        if let (Some(hir), Some(offset)) = (&document.hir, offset) {
+            for id in hir.symbols_at(offset) {
+                let symbol = &hir.symbols[id as usize];
+                items.push(json!({
+                    "label" : symbol.name,
+                    "kind" : COMPLETION_VARIABLE,
+                    "detail" : type_name(symbol.size)
+                }));
+            }
+        }
+        for (name, _) in JOLANG_STD.iter() {
+            items.push(json!({
+                "label" : name,
+                "kind" : COMPLETION_FUNCTION,
+                "detail" : builtin_detail(name)
+            }));
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn document_symbols(&self, params : &Value) -> Result<Value, (i64, String)> {
+        let uri = params.pointer("/textDocument/uri")
+            .and_then(|u| u.as_str())
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let document = self.documents.get(uri)
+            .ok_or((INVALID_PARAMS, format!("unknown document : {}", uri)))?;
+        let hir = match document.current_hir() {
+            Some(hir) => hir,
+            None => return Ok(json!([]))
+        };
+        let text = &document.source.buffer;
+        Ok(Value::Array(hir.symbols.iter()
+            .map(|s| {
+                let name = range(text, s.offset, s.offset + s.name.len());
+                json!({
+                    "name" : s.name,
+                    "detail" : type_name(s.size),
+                    "kind" : SYMBOL_VARIABLE,
+                    "range" : name,
+                    "selectionRange" : name
+                })
+            })
+            .collect()))
+    }
+}
+
+fn publish_diagnostics(uri : &str, diagnostics : Vec<Value>) -> Value {
+    json!({
+        "jsonrpc" : "2.0",
+        "method" : "textDocument/publishDiagnostics",
+        "params" : {
+            "uri" : uri,
+            "diagnostics" : diagnostics
+        }
+    })
+}
+
+/// serve the messages of a stream until the exit notification, returns the exit code
+pub fn run(reader : &mut impl BufRead, writer : &mut impl Write) -> Result<i32> {
+    let mut server = Server::new();
+    while let Some(content) = read_content(reader)? {
+        // the length is known so the next messages can still be read
+        let message = match serde_json::from_slice(&content) {
+            Ok(message) => message,
+            Err(e) => {
+                write_message(writer, &json!({
+                    "jsonrpc" : "2.0",
+                    "id" : Value::Null,
+                    "error" : { "code" : PARSE_ERROR, "message" : format!("parse error : {}", e) }
+                }))?;
+                continue
+            }
+        };
+        for response in server.handle(&message) {
+            write_message(writer, &response)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(server.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    const URI : &str = "file:///test.jol";
+
+    /// scripted client, sends the messages to the server and returns the responses
+    struct Client {
+        server : Server,
+        next_id : u64
+    }
+
+    impl Client {
+        fn new() -> Self {
+            let mut client = Self {
+                server : Server::new(),
+                next_id : 0
+            };
+            let init = client.request("initialize", json!({ "capabilities" : {} }));
+            assert_eq!(init["result"]["capabilities"]["hoverProvider"], true);
+            client.notify("initialized", json!({}));
+            client
+        }
+
+        fn request(&mut self, method : &str, params : Value) -> Value {
+            self.next_id += 1;
+            let responses = self.server.handle(&json!({
+                "jsonrpc" : "2.0",
+                "id" : self.next_id,
+                "method" : method,
+                "params" : params
+            }));
+            assert_eq!(responses.len(), 1);
+            assert_eq!(responses[0]["id"], self.next_id);
+            responses[0].clone()
+        }
+
+        fn notify(&mut self, method : &str, params : Value) -> Vec<Value> {
+            self.server.handle(&json!({
+                "jsonrpc" : "2.0",
+                "method" : method,
+                "params" : params
+            }))
+        }
+
+        /// open or change the document, returns the published diagnostics
+        fn set_text(&mut self, text : &str, open : bool) -> Value {
+            let messages = if open {
+                self.notify("textDocument/didOpen", json!({
+                    "textDocument" : { "uri" : URI, "languageId" : "jolang", "version" : 1, "text" : text }
+                }))
+            }else {
+                self.notify("textDocument/didChange", json!({
+                    "textDocument" : { "uri" : URI, "version" : 2 },
+                    "contentChanges" : [{ "text" : text }]
+                }))
+            };
+            assert_eq!(messages[0]["method"], "textDocument/publishDiagnostics");
+            messages[0]["params"]["diagnostics"].clone()
+        }
+
+        fn at(&mut self, method : &str, line : u32, character : u32) -> Value {
+            self.request(method, json!({
+                "textDocument" : { "uri" : URI },
+                "position" : { "line" : line, "character" : character }
+            }))["result"].clone()
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut client = Client::new();
+        assert_eq!(client.set_text("let a = 1;\nprint(a);", true), json!([]));
+        let diagnostics = client.set_text("let a = 1;\nprint(b);", false);
+        assert_eq!(diagnostics[0]["code"], "E0004");
+        assert_eq!(diagnostics[0]["range"], json!({
+            "start" : { "line" : 1, "character" : 6 },
+            "end" : { "line" : 1, "character" : 7 }
+        }));
+        let diagnostics = client.set_text("let a = 1\nprint(a);", false);
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+        let closed = client.notify("textDocument/didClose", json!({ "textDocument" : { "uri" : URI } }));
+        assert_eq!(closed[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut client = Client::new();
+        client.set_text("let a : i8 = 1;\n{\n    let b = a;\n    print(b);\n}\n", true);
+        assert_eq!(client.at("textDocument/definition", 2, 12), json!({
+            "uri" : URI,
+            "range" : {
+                "start" : { "line" : 0, "character" : 4 },
+                "end" : { "line" : 0, "character" : 5 }
+            }
+        }));
+        assert_eq!(client.at("textDocument/definition", 3, 2), Value::Null);
+        assert_eq!(client.at("textDocument/hover", 3, 11)["contents"]["value"], "let b : i8");
+        assert_eq!(client.at("textDocument/hover", 3, 6)["contents"]["value"], "fn print(i64) -> void");
+        let symbols = client.request("textDocument/documentSymbol", json!({ "textDocument" : { "uri" : URI } }));
+        let names : Vec<_> = symbols["result"].as_array().unwrap().iter()
+            .map(|s| (s["name"].clone(), s["range"]["start"]["line"].clone()))
+            .collect();
+        assert_eq!(names, vec![(json!("a"), json!(0)), (json!("b"), json!(2))]);
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut client = Client::new();
+        client.set_text("let a = 1;\n{\n    let b = 2;\n    \n}\n", true);
+        let labels = |result : Value| result.as_array().unwrap().iter()
+            .map(|i| i["label"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(labels(client.at("textDocument/completion", 3, 4)), vec!["b", "a", "print", "input", "pow", "randint"]);
+        assert_eq!(labels(client.at("textDocument/completion", 5, 0)), vec!["a", "print", "input", "pow", "randint"]);
+        // the last analysis is used while the source doesn't compile
+        client.set_text("let a = 1;\n{\n    let b = 2;\n    pr\n}\n", false);
+        assert_eq!(labels(client.at("textDocument/completion", 3, 6))[..2], ["b", "a"]);
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut input = vec![];
+        for message in [
+            json!({ "jsonrpc" : "2.0", "id" : 1, "method" : "initialize", "params" : {} }),
+            json!({ "jsonrpc" : "2.0", "id" : 2, "method" : "unknown", "params" : {} }),
+            json!({ "jsonrpc" : "2.0", "id" : 3, "method" : "shutdown" }),
+            json!({ "jsonrpc" : "2.0", "method" : "exit" }),
+        ] {
+            if message["id"] == 3 {
+                // a malformed message doesn't stop the server
+                write!(input, "Content-Length: 11\r\n\r\n{{\"id\" : 3,}}").unwrap();
+            }
+            write_message(&mut input, &message).unwrap();
+        }
+        let mut output = vec![];
+        assert_eq!(run(&mut Cursor::new(input), &mut output).unwrap(), 0);
+        let mut output = Cursor::new(output);
+        let mut responses = vec![];
+        while let Some(message) = read_message(&mut output).unwrap() {
+            responses.push(message);
+        }
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[2]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[2]["id"], Value::Null);
+        assert_eq!(responses[3]["result"], Value::Null);
+    }
+}
@@ -1,7 +1,13 @@
 use std::collections::HashMap;
-use jolang_shared::ir::instructions::operand::{BlkId, Imm64};
 
-#[derive(PartialEq, Debug)]
+/// id of a local variable slot
+pub type VarId = u32;
+/// id of a variable in the hir, the variable is stored in the slot with the same id
+pub type SymbolId = VarId;
+/// index of a scope in the hir
+pub type ScopeId = u32;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum ScopeKind {
     // the first Scope contains globals
     Root,
@@ -9,52 +15,35 @@ pub enum ScopeKind {
     Loop
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Scope {
-    // name : (offset, size)
-    variables : HashMap<String, (u64, u64)>,
+    // name : symbol
+    variables : HashMap<String, SymbolId>,
     pub kind : ScopeKind,
-    pub block : BlkId,
-    pub exit : BlkId
+    pub parent : Option<ScopeId>,
+    /// byte range of the source covered by a block scope
+    pub range : Option<(usize, usize)>
 }
 
 impl Scope {
-    pub fn new(kind : ScopeKind, block : BlkId, exit : BlkId) -> Self {
-        Self { 
-            variables: HashMap::new(), 
+    pub fn new(kind : ScopeKind, parent : Option<ScopeId>) -> Self {
+        Self {
+            variables: HashMap::new(),
             kind,
-            block,
-            exit
+            parent,
+            range : None
         }
     }
 
-    pub fn decl_var(&mut self, name : String, offset : u64, size : u64) {
-        self.variables.insert(name, (offset, size));
-    }
-
-    pub fn get_var_offset(&self, name : &String) -> Option<u64> {
-        self.variables.get(name).copied().map(|x| x.0)
-    }
-
-    pub fn get_var_size(&self, name : &String) -> Option<u64> {
-        self.variables.get(name).copied().map(|x| x.1)
+    pub fn decl_var(&mut self, name : String, id : SymbolId) {
+        self.variables.insert(name, id);
     }
 
-    pub fn update_var(&mut self, name : &String, offset : u64) {
-        self.variables.get_mut(name).unwrap().0 = offset;
+    pub fn get_var(&self, name : &str) -> Option<SymbolId> {
+        self.variables.get(name).copied()
     }
 
-    pub fn var_sizes(&self) -> Vec<u64> {
-        self.get_vars().values()
-            .map(|v| v.1)
-            .collect::<Vec<_>>()
-    }
-
-    pub fn get_vars(&self) -> &HashMap<String, (u64, u64)> {
+    pub fn get_vars(&self) -> &HashMap<String, SymbolId> {
         &self.variables
     }
-
-    pub fn get_vars_mut(&mut self) -> &mut HashMap<String, (u64, u64)> {
-        &mut self.variables
-    }
 }
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 use std::fs::read_to_string;
+use std::io::{stdin, Read};
 use anyhow::Error;
 
 pub struct SourceBuffer {
@@ -24,6 +25,21 @@ impl SourceBuffer {
         }
     }
 
+    /// source that isn't in a file, the name is used in the errors
+    pub fn from_string(name : &str, code : String) -> Self {
+        SourceBuffer {
+            path : PathBuf::from(name),
+            buffer : code
+        }
+    }
+
+    /// read the whole standard input
+    pub fn from_stdin() -> Result<Self, Error> {
+        let mut buffer = String::new();
+        stdin().read_to_string(&mut buffer)?;
+        Ok(Self::from_string("<stdin>", buffer))
+    }
+
     pub fn get_line(&self, line : usize) -> Option<&str> {
         self.buffer.lines().nth(line-1)
     }
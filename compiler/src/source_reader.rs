@@ -17,6 +17,19 @@ impl Debug for SourceCursor<'_> {
     }
 }
 
+impl<'a> SourceCursor<'a> {
+    /// cursor on the first char of a source
+    pub fn begin(source : &'a SourceBuffer) -> Self {
+        // a newline is at the start of the next line, like in next_char
+        let newline = source.buffer.starts_with('\n');
+        SourceCursor {
+            data_ref : source.buffer.as_str(),
+            line : 1 + newline as usize,
+            collumn : !newline as usize
+        }
+    }
+}
+
 impl<'a> PartialEq for SourceCursor<'a>{
     fn eq(&self, other: &Self) -> bool {
         return self.line==other.line
@@ -33,11 +46,7 @@ impl<'a> SourceReader<'a> {
     pub fn new<'b>(source : &'b SourceBuffer) -> SourceReader<'b> {
         SourceReader {
             source,
-            current_cursor : SourceCursor{
-                data_ref : source.buffer.as_str(),
-                line : 1,
-                collumn : 1
-            }
+            current_cursor : SourceCursor::begin(source)
         }
     }
 
@@ -79,11 +88,7 @@ impl<'a> SourceReader<'a> {
     }
 
     pub fn goto_begin(&mut self) {
-        self.goto(SourceCursor{
-            data_ref : self.source.buffer.as_str(),
-            line : 1,
-            collumn : 1
-        })
+        self.goto(SourceCursor::begin(self.source))
     }
 
     pub fn read_span(&self, size : usize) -> Option<SourceSpan>{
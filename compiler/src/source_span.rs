@@ -26,4 +26,9 @@ impl SourceSpan<'_> {
             source
         }
     }
+
+    /// byte offset of the span in the source buffer
+    pub fn offset(&self) -> usize {
+        self.source.buffer.len() - self.start.data_ref.len()
+    }
 }
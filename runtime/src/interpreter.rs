@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Result};
+use jolang_shared::{ffi::jolang_std::JOLANG_STD, ir::{instructions::{operand::{bits, sext, zext, Size}, Instruction}, verifier::verify, IrExternalFn, IrObject}};
+use crate::Runtime;
+
+/// value on the stack of the interpreter
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Int(i64),
+    /// reference to a local variable
+    Ref(u32)
+}
+
+/// call a builtin function with its native pointer
+fn call_extern(func : &IrExternalFn, args : &[i64]) -> Result<i64> {
+    let std_fn = JOLANG_STD.iter()
+        .find(|x| x.0 == func.name)
+        .map(|x| &x.1)
+        .ok_or_else(|| anyhow!("unknown function : {}", func.name))?;
+    if std_fn.signature() != func.sig {
+        return Err(anyhow!("singnature of the function : \"{}\" is not the same between the runtime and the object", func.name))
+    }
+    let returns = func.sig.ret != "void";
+    unsafe {
+        let ptr = std_fn.get_pointer();
+        Ok(match (args, returns) {
+            ([], true) => std::mem::transmute::<u64, extern "C" fn() -> i64>(ptr)(),
+            ([a], false) => {
+                std::mem::transmute::<u64, extern "C" fn(i64)>(ptr)(*a);
+                0
+            },
+            ([a, b], true) => std::mem::transmute::<u64, extern "C" fn(i64, i64) -> i64>(ptr)(*a, *b),
+            _ => return Err(anyhow!("unsupported signature for the function : {}", func.name))
+        })
+    }
+}
+
+/// runtime that executes the ir without compiling it, available on every platform
+pub struct Interpreter {
+    /// values of the local variables, they are kept from one run to the next
+    locals : Vec<i64>,
+    stack : Vec<Value>
+}
+
+impl Interpreter {
+    pub fn locals(&self) -> &[i64] {
+        &self.locals
+    }
+
+    /// forget the local variables of the previous runs
+    pub fn reset(&mut self) {
+        self.locals.clear();
+    }
+
+    fn pop(&mut self) -> Result<Value> {
+        self.stack.pop().ok_or_else(|| anyhow!("pop on an empty stack"))
+    }
+
+    fn pop_int(&mut self) -> Result<i64> {
+        match self.pop()? {
+            Value::Int(v) => Ok(v),
+            Value::Ref(_) => Err(anyhow!("expected an integer but found a reference"))
+        }
+    }
+
+    fn pop_ref(&mut self) -> Result<usize> {
+        match self.pop()? {
+            Value::Ref(id) => Ok(id as usize),
+            Value::Int(_) => Err(anyhow!("expected a reference but found an integer"))
+        }
+    }
+
+    /// pop two integers and push the result of the operation
+    fn binary(&mut self, size : Size, op : impl Fn(i64, i64) -> Result<i64>) -> Result<()> {
+        let right = self.pop_int()?;
+        let left = self.pop_int()?;
+        self.stack.push(Value::Int(sext(op(left, right)?, size)));
+        Ok(())
+    }
+
+    /// pop two integers and push 1 if the comparison is true
+    fn compare(&mut self, size : Size, unsigned : bool, op : impl Fn(i128, i128) -> bool) -> Result<()> {
+        let right = self.pop_int()?;
+        let left = self.pop_int()?;
+        let (left, right) = if unsigned {
+            (zext(left, size) as i128, zext(right, size) as i128)
+        }else {
+            (sext(left, size) as i128, sext(right, size) as i128)
+        };
+        self.stack.push(Value::Int(op(left, right) as i64));
+        Ok(())
+    }
+
+    fn execute(&mut self, object : &IrObject) -> Result<i64> {
+        let blocks : Vec<Vec<Instruction>> = object.blocks.iter()
+            .map(|b| b.instructions.iter().copied().collect())
+            .collect();
+        let mut current = 0;
+        loop {
+            let block = blocks.get(current)
+                .ok_or_else(|| anyhow!("branch to the unknown block : {}", current))?;
+            let mut next = None;
+            for instruction in block {
+                match *instruction {
+                    Instruction::Nop() => (),
+                    Instruction::Pop(_) => {
+                        self.pop()?;
+                    },
+                    Instruction::Dup(_) => {
+                        let value = self.pop()?;
+                        self.stack.push(value);
+                        self.stack.push(value);
+                    },
+                    Instruction::Swap(_) => {
+                        let a = self.pop()?;
+                        let b = self.pop()?;
+                        self.stack.push(a);
+                        self.stack.push(b);
+                    },
+                    Instruction::Br(id) => next = Some(id),
+                    Instruction::Briz(zero, not_zero) => {
+                        next = Some(if self.pop_int()? == 0 { zero } else { not_zero });
+                    },
+                    Instruction::Call(id) => {
+                        let func = object.ext_fn.get(id as usize)
+                            .ok_or_else(|| anyhow!("call of the unknown function : {}", id))?;
+                        let mut args = vec![0; func.sig.args.len()];
+                        for arg in args.iter_mut().rev() {
+                            *arg = self.pop_int()?;
+                        }
+                        let ret = call_extern(func, &args)?;
+                        if func.sig.ret != "void" {
+                            self.stack.push(Value::Int(ret));
+                        }
+                    },
+                    Instruction::Varref() => {
+                        let id = self.pop_int()?;
+                        if id < 0 || id as usize >= self.locals.len() {
+                            return Err(anyhow!("reference to the unknown variable : {}", id))
+                        }
+                        self.stack.push(Value::Ref(id as u32));
+                    },
+                    Instruction::Iconst(size, value) => self.stack.push(Value::Int(sext(value, size))),
+                    Instruction::Iload(size) => {
+                        let id = self.pop_ref()?;
+                        self.stack.push(Value::Int(sext(self.locals[id], size)));
+                    },
+                    Instruction::Istore(size) => {
+                        let value = self.pop_int()?;
+                        let id = self.pop_ref()?;
+                        self.locals[id] = sext(value, size);
+                    },
+                    Instruction::Iret(size) => return Ok(sext(self.pop_int()?, size)),
+                    Instruction::Inot(size) => {
+                        let value = self.pop_int()?;
+                        self.stack.push(Value::Int(sext(!value, size)));
+                    },
+                    Instruction::Ineg(size) => {
+                        let value = self.pop_int()?;
+                        self.stack.push(Value::Int(sext(value.wrapping_neg(), size)));
+                    },
+                    Instruction::Ior(size) => self.binary(size, |a, b| Ok(a | b))?,
+                    Instruction::Iand(size) => self.binary(size, |a, b| Ok(a & b))?,
+                    Instruction::Ixor(size) => self.binary(size, |a, b| Ok(a ^ b))?,
+                    Instruction::Ilshr(size) => self.binary(size, |a, b| Ok((zext(a, size) >> (b as u32 % bits(size))) as i64))?,
+                    Instruction::Iashr(size) => self.binary(size, |a, b| Ok(sext(a, size) >> (b as u32 % bits(size))))?,
+                    Instruction::Ishl(size) => self.binary(size, |a, b| Ok(a.wrapping_shl(b as u32 % bits(size))))?,
+                    Instruction::Iadd(size) => self.binary(size, |a, b| Ok(a.wrapping_add(b)))?,
+                    Instruction::Isub(size) => self.binary(size, |a, b| Ok(a.wrapping_sub(b)))?,
+                    Instruction::Imul(size) => self.binary(size, |a, b| Ok(a.wrapping_mul(b)))?,
+                    Instruction::Idiv(size)
+                        | Instruction::Irem(size)
+                        => {
+                        let rem = matches!(instruction, Instruction::Irem(_));
+                        self.binary(size, |a, b| match (sext(a, size), sext(b, size)) {
+                            (_, 0) => Err(anyhow!("division by zero")),
+                            (a, b) if rem => Ok(a.wrapping_rem(b)),
+                            (a, b) => Ok(a.wrapping_div(b))
+                        })?
+                    },
+                    Instruction::Udiv(size)
+                        | Instruction::Urem(size)
+                        => {
+                        let rem = matches!(instruction, Instruction::Urem(_));
+                        self.binary(size, |a, b| match (zext(a, size), zext(b, size)) {
+                            (_, 0) => Err(anyhow!("division by zero")),
+                            (a, b) if rem => Ok((a % b) as i64),
+                            (a, b) => Ok((a / b) as i64)
+                        })?
+                    },
+                    Instruction::Ieq(size) => self.compare(size, false, |a, b| a == b)?,
+                    Instruction::Ine(size) => self.compare(size, false, |a, b| a != b)?,
+                    Instruction::Ige(size) => self.compare(size, false, |a, b| a >= b)?,
+                    Instruction::Igt(size) => self.compare(size, false, |a, b| a > b)?,
+                    Instruction::Ilt(size) => self.compare(size, false, |a, b| a < b)?,
+                    Instruction::Ile(size) => self.compare(size, false, |a, b| a <= b)?,
+                    Instruction::Uge(size) => self.compare(size, true, |a, b| a >= b)?,
+                    Instruction::Ugt(size) => self.compare(size, true, |a, b| a > b)?,
+                    Instruction::Ule(size) => self.compare(size, true, |a, b| a <= b)?,
+                    Instruction::Ult(size) => self.compare(size, true, |a, b| a < b)?,
+                    Instruction::Iconv(from, to) => {
+                        let value = self.pop_int()?;
+                        self.stack.push(Value::Int(sext(sext(value, from), to)));
+                    },
+                    Instruction::Uconv(from, to) => {
+                        let value = self.pop_int()?;
+                        self.stack.push(Value::Int(sext(zext(value, from) as i64, to)));
+                    }
+                }
+                if next.is_some() {
+                    break;
+                }
+            }
+            current = next.ok_or_else(|| anyhow!("the block {} has no terminator", current))? as usize;
+        }
+    }
+}
+
+impl Runtime for Interpreter {
+    fn new() -> Self {
+        Self {
+            locals : Vec::new(),
+            stack : Vec::new()
+        }
+    }
+
+    /// the variables of the previous runs keep their values
+    fn run(&mut self, object : IrObject) -> Result<i64> {
+        verify(&object)?;
+        self.locals.resize(object.local_vars.len().max(self.locals.len()), 0);
+        self.stack.clear();
+        self.execute(&object)
+    }
+}
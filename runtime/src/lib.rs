@@ -1,15 +1,56 @@
-use std::{fs::OpenOptions, path::PathBuf};
-use anyhow::Result;
+use std::{fs::OpenOptions, path::PathBuf, time::{Duration, Instant}};
+use anyhow::{anyhow, Result};
 use jolang_shared::ir::{reader::read, IrObject};
 use platforms::Platform;
 #[cfg(feature = "llvm")]
 mod llvm;
+pub mod interpreter;
 mod platforms;
 
+/// names of the runtimes that can be chosen with runtime_by_name
+#[cfg(feature = "llvm")]
+pub const RUNTIMES : [&str; 2] = ["llvm", "interpreter"];
+#[cfg(not(feature = "llvm"))]
+pub const RUNTIMES : [&str; 1] = ["interpreter"];
+
+/// durations of the phases of a run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunTimes {
+    /// preparation of the object (e.g. jit compilation), none for the runtimes that don't prepare it
+    pub prepare : Option<Duration>,
+    pub execute : Duration
+}
+
 pub trait Runtime {
     fn new() -> Self
         where Self : Sized;
     fn run(&mut self, object : IrObject) -> Result<i64>;
+
+    /// run an object and measure its preparation separately from its execution
+    /// runtimes without a preparation measure the whole run as the execution
+    fn run_timed(&mut self, object : IrObject) -> Result<(i64, RunTimes)> {
+        let start = Instant::now();
+        let value = self.run(object)?;
+        Ok((value, RunTimes {
+            prepare : None,
+            execute : start.elapsed()
+        }))
+    }
+}
+
+/// create a runtime from its name, see RUNTIMES
+pub fn runtime_by_name(name : &str) -> Result<Box<dyn Runtime>> {
+    match name {
+        #[cfg(feature = "llvm")]
+        "llvm" => Ok(Box::new(llvm::LLVMRuntime::new())),
+        "interpreter" => Ok(Box::new(interpreter::Interpreter::new())),
+        _ => Err(anyhow!("unknown runtime : {}, the runtimes are : {}", name, RUNTIMES.join(", ")))
+    }
+}
+
+/// the default runtime of the platform
+pub fn default_runtime() -> Box<dyn Runtime> {
+    platforms::CurrentPlatform::new().default_runtime()
 }
 
 pub fn run(file : PathBuf) -> Result<i64> {
@@ -18,8 +59,11 @@ pub fn run(file : PathBuf) -> Result<i64> {
         .write(false)
         .create(false)
         .open(file)?;
-    let object = read(&mut file)?;
-    let platform = platforms::CurrentPlatform::new();
-    let mut runtime : Box<dyn Runtime> = platform.default_runtime();
+    run_object(read(&mut file)?)
+}
+
+/// run an object with the default runtime of the platform
+pub fn run_object(object : IrObject) -> Result<i64> {
+    let mut runtime = default_runtime();
     Ok(runtime.run(object)?)
 }
@@ -1,45 +1,109 @@
 use anyhow::{anyhow, Result};
-use inkwell::{builder::Builder, context::Context, execution_engine::JitFunction, module::Module, types::{BasicMetadataTypeEnum, BasicType, IntType}, values::{BasicMetadataValueEnum, FunctionValue, PhiValue}, AddressSpace, OptimizationLevel, basic_block::BasicBlock};
-use crate::Runtime;
-use jolang_shared::{ffi::jolang_std::JOLANG_STD, ir::{block::Block, instructions::Instruction}};
-use std::{cell::RefCell, collections::LinkedList};
+use inkwell::{builder::Builder, context::Context, execution_engine::JitFunction, module::Module, types::{BasicMetadataTypeEnum, BasicTypeEnum, IntType}, values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PhiValue, PointerValue}, AddressSpace, IntPredicate, OptimizationLevel, basic_block::BasicBlock};
+use crate::{Runtime, RunTimes};
+use jolang_shared::{ffi::jolang_std::JOLANG_STD, ir::{instructions::{operand::Size, Instruction}, verifier::{analyze, verify, REF_SIZE}, IrExternalFn, IrObject}};
+use std::{cell::Cell, time::Instant};
 
 pub struct LLVMRuntime {
     ctx : Context,
 }
 
+thread_local! {
+    /// set by the compiled code when it divides by zero, the run then fails like in the interpreter
+    static DIVISION_BY_ZERO : Cell<bool> = const { Cell::new(false) };
+}
+
+extern "C" fn division_by_zero() {
+    DIVISION_BY_ZERO.with(|d| d.set(true));
+}
+
+fn pop<'b>(stack : &mut Vec<BasicValueEnum<'b>>) -> Result<BasicValueEnum<'b>> {
+    stack.pop().ok_or_else(|| anyhow!("tried to get a value from an empty stack"))
+}
+
+fn pop_int<'b>(stack : &mut Vec<BasicValueEnum<'b>>) -> Result<IntValue<'b>> {
+    match pop(stack)? {
+        BasicValueEnum::IntValue(value) => Ok(value),
+        _ => Err(anyhow!("expected an integer but found a reference"))
+    }
+}
+
+fn pop_ref<'b>(stack : &mut Vec<BasicValueEnum<'b>>) -> Result<PointerValue<'b>> {
+    match pop(stack)? {
+        BasicValueEnum::PointerValue(value) => Ok(value),
+        _ => Err(anyhow!("expected a reference but found an integer"))
+    }
+}
+
+fn predicate(instruction : &Instruction) -> Option<IntPredicate> {
+    Some(match instruction {
+        Instruction::Ieq(_) => IntPredicate::EQ,
+        Instruction::Ine(_) => IntPredicate::NE,
+        Instruction::Ige(_) => IntPredicate::SGE,
+        Instruction::Igt(_) => IntPredicate::SGT,
+        Instruction::Uge(_) => IntPredicate::UGE,
+        Instruction::Ugt(_) => IntPredicate::UGT,
+        Instruction::Ilt(_) => IntPredicate::SLT,
+        Instruction::Ile(_) => IntPredicate::SLE,
+        Instruction::Ule(_) => IntPredicate::ULE,
+        Instruction::Ult(_) => IntPredicate::ULT,
+        _ => return None
+    })
+}
+
 impl LLVMRuntime {
-    fn get_int_type<'b>(&'b self, size : u64) -> Result<IntType<'b>> {
-        if size <= 128 {
-            Ok(self.ctx.custom_width_int_type(size as u32))   
-        }else{
-            return Err(anyhow!("unsupported integer type : i{}", size))
+    fn get_int_type(&self, size : Size) -> Result<IntType<'_>> {
+        match size {
+            1 | 2 | 4 | 8 => Ok(self.ctx.custom_width_int_type(size * 8)),
+            _ => Err(anyhow!("unsupported integer size : {}", size))
+        }
+    }
+
+    /// the type of a value on the stack, the references are pointers to the local variables
+    fn get_type(&self, size : Size) -> Result<BasicTypeEnum<'_>> {
+        match size {
+            REF_SIZE => Ok(self.ctx.i64_type().ptr_type(AddressSpace::default()).into()),
+            size => Ok(self.get_int_type(size)?.into())
         }
     }
-    
-    fn load_externs<'b>(&'b self, table : &Vec<(String, u8, bool)>, module : &Module<'b>, builder : &Builder) -> Result<()>{
-        for (name, argc, returns) in table {
-            let std_sig = JOLANG_STD.iter()
-                .filter(|x| x.0 == name)
-                .next().map(|x| &x.1)
-                .map_or_else(|| Err(anyhow!("unknown function : {}", name)), |x| Ok(x))?;
-            let context : &Context = &self.ctx;
-            if std_sig.returns() != *returns || std_sig.arg_count() != *argc {
-                return Err(anyhow!("singnature of the function : \"{}\" is not the same between the runtime and the object", name));
+
+    /// resize an integer, the values are sign extended unless unsigned is set
+    fn convert<'b>(&'b self, builder : &Builder<'b>, value : IntValue<'b>, size : Size, unsigned : bool) -> Result<IntValue<'b>> {
+        let t = self.get_int_type(size)?;
+        let from = value.get_type().get_bit_width();
+        Ok(if from == t.get_bit_width() {
+            value
+        }else if from > t.get_bit_width() {
+            builder.build_int_truncate(value, t, "conv")?
+        }else if unsigned {
+            builder.build_int_z_extend(value, t, "conv")?
+        }else {
+            builder.build_int_s_extend(value, t, "conv")?
+        })
+    }
+
+    fn load_externs<'b>(&'b self, table : &[IrExternalFn], module : &Module<'b>, builder : &Builder<'b>) -> Result<()>{
+        for f in table {
+            let std_fn = JOLANG_STD.iter()
+                .find(|x| x.0 == f.name)
+                .map(|x| &x.1)
+                .ok_or_else(|| anyhow!("unknown function : {}", f.name))?;
+            if std_fn.signature() != f.sig {
+                return Err(anyhow!("singnature of the function : \"{}\" is not the same between the runtime and the object", f.name));
             }
-            let i64_type = context.i64_type();
-            let args = vec![BasicMetadataTypeEnum::from(i64_type); *argc as usize];
-            let sig = if *returns { 
+            let i64_type = self.ctx.i64_type();
+            let args = vec![BasicMetadataTypeEnum::from(i64_type); f.sig.args.len()];
+            let sig = if f.sig.ret != "void" {
                 i64_type.fn_type(&args[..], false)
-            } else { 
-                context.void_type().fn_type(&args[..], false)
+            } else {
+                self.ctx.void_type().fn_type(&args[..], false)
             };
-            let fn_value = module.add_function(&name, sig, None);
-            let block = context.append_basic_block(fn_value, "call");
+            let fn_value = module.add_function(&f.name, sig, None);
+            let block = self.ctx.append_basic_block(fn_value, "call");
             builder.position_at_end(block);
             let fn_ptr = unsafe {
                 i64_type
-                    .const_int(std_sig.get_pointer() as *const usize as u64, false)
+                    .const_int(std_fn.get_pointer(), false)
                     .const_to_pointer(sig.ptr_type(AddressSpace::default()))
             };
             let ret = builder.build_indirect_call(sig, fn_ptr, &fn_value.get_params().iter().map(|x| BasicMetadataValueEnum::from(*x)).collect::<Vec<_>>()[..], "res")?;
@@ -49,315 +113,222 @@ impl LLVMRuntime {
                 builder.build_return(None)?;
             }
         }
-        return Ok(())
+        Ok(())
     }
 
-    pub fn gen_function(&self, blocks : &Vec<Block>, fn_value : FunctionValue, module : &Module, builder : &Builder) -> Result<()>{
+    /// the arguments of the blocks are phis with the types of the stack found by the verifier,
+    /// the blocks that are never reached are not generated
+    pub fn gen_function<'b>(&'b self, object : &IrObject, fn_value : FunctionValue<'b>, module : &Module<'b>, builder : &Builder<'b>) -> Result<()>{
         let i64_type = self.ctx.i64_type();
+        let analysis = analyze(object);
+
+        // the local variables are zeroed before the first block because it can be the target of a branch
+        let entry = self.ctx.append_basic_block(fn_value, "entry");
+        builder.position_at_end(entry);
+        let var_count = i64_type.const_int(object.local_vars.len() as u64, false);
+        let locals = builder.build_array_alloca(i64_type, var_count, "locals")?;
+        builder.build_memset(locals, 8, self.ctx.i8_type().const_zero(), i64_type.const_int(8 * object.local_vars.len() as u64, false))?;
 
-        let mut llvm_blocks : Vec<(Vec<PhiValue>, BasicBlock)> = Vec::new();
-        // init blocks and args as phi
-        for (i, blk) in blocks.iter().enumerate(){
+        let mut llvm_blocks : Vec<Option<(Vec<PhiValue>, BasicBlock)>> = Vec::new();
+        for (i, stack) in analysis.entries.iter().enumerate() {
+            let Some(stack) = stack else {
+                llvm_blocks.push(None);
+                continue
+            };
             let llvm_blk = self.ctx.append_basic_block(fn_value, format!("B{}", i).as_str());
             builder.position_at_end(llvm_blk);
             let mut args = Vec::new();
-            for arg in blk.args.iter() {
-                let arg = builder.build_phi(self.get_int_type(*arg)?, "")?;
-                args.push(arg);
+            for size in stack {
+                args.push(builder.build_phi(self.get_type(*size)?, "")?);
             }
-            llvm_blocks.push((args, llvm_blk));
+            llvm_blocks.push(Some((args, llvm_blk)));
         }
+        let Some(Some((_, first))) = llvm_blocks.first() else {
+            return Err(anyhow!("the object has no entry block"))
+        };
+        builder.position_at_end(entry);
+        builder.build_unconditional_branch(*first)?;
 
-        // add instructions
-        for (id, (blk,(args,llvm_blk))) in blocks.iter()
-        .zip(llvm_blocks.iter())
-        .enumerate(){
-            builder.position_at_end(*llvm_blk);
-            let mut stack = LinkedList::new();
-            for a in args {
-                stack.push_back(a.as_basic_value());
+        // the divisions by zero branch to this block, it stops the function
+        let zero_division = self.ctx.append_basic_block(fn_value, "division_by_zero");
+        builder.position_at_end(zero_division);
+        let report_sig = self.ctx.void_type().fn_type(&[], false);
+        let report = i64_type
+            .const_int(division_by_zero as extern "C" fn() as usize as u64, false)
+            .const_to_pointer(report_sig.ptr_type(AddressSpace::default()));
+        builder.build_indirect_call(report_sig, report, &[], "")?;
+        builder.build_return(Some(&i64_type.const_zero()))?;
+
+        let get_block = |id : u32| llvm_blocks.get(id as usize)
+            .and_then(|b| b.as_ref())
+            .ok_or_else(|| anyhow!("tried to branch to a non existant block : B{}", id));
+        // the stack is passed to the phis of the target
+        let branch = |target : &(Vec<PhiValue<'b>>, BasicBlock<'b>), stack : &[BasicValueEnum<'b>], from : BasicBlock<'b>| {
+            for (arg, value) in target.0.iter().zip(stack) {
+                arg.add_incoming(&[(value, from)]);
             }
-            for i in &blk.instructions {
-                match i  {
-                    Instruction::Ret() => {
-                        builder.build_return(None)?;
+        };
+
+        for (blk, llvm_blk) in object.blocks.iter().zip(llvm_blocks.iter()) {
+            let Some((args, llvm_blk)) = llvm_blk else {
+                continue
+            };
+            builder.position_at_end(*llvm_blk);
+            let mut stack : Vec<BasicValueEnum> = args.iter().map(|a| a.as_basic_value()).collect();
+            for i in blk.instructions.iter() {
+                match *i {
+                    Instruction::Nop() => (),
+                    Instruction::Pop(_) => {
+                        pop(&mut stack)?;
+                    },
+                    Instruction::Dup(_) => {
+                        let value = pop(&mut stack)?;
+                        stack.extend([value, value]);
+                    },
+                    Instruction::Swap(_) => {
+                        let val1 = pop(&mut stack)?;
+                        let val2 = pop(&mut stack)?;
+                        stack.extend([val1, val2]);
+                    },
+                    Instruction::Br(other_id) => {
+                        let other_blk = get_block(other_id)?;
+                        branch(other_blk, &stack, builder.get_insert_block().unwrap_or(*llvm_blk));
+                        builder.build_unconditional_branch(other_blk.1)?;
+                        break
                     },
-                    Instruction::Reti() => {
-                        if let Some(value) = stack.pop_back() {
-                            builder.build_return(Some(&value))?;
-                        }else {
-                            return Err(anyhow!("tried to get a value from an empty stack\nwhile building reti in B{}", id))
+                    Instruction::Briz(id1, id2) => {
+                        let cond = pop_int(&mut stack)?;
+                        let (blk1, blk2) = (get_block(id1)?, get_block(id2)?);
+                        let cond = builder.build_int_compare(IntPredicate::EQ, cond, cond.get_type().const_zero(), "cond")?;
+                        let from = builder.get_insert_block().unwrap_or(*llvm_blk);
+                        branch(blk1, &stack, from);
+                        branch(blk2, &stack, from);
+                        builder.build_conditional_branch(cond, blk1.1, blk2.1)?;
+                        break
+                    },
+                    Instruction::Call(f) => {
+                        let function = object.ext_fn.get(f as usize)
+                            .and_then(|f| module.get_function(&f.name))
+                            .ok_or_else(|| anyhow!("call of unregistered function : {}", f))?;
+                        let mut args = vec![BasicMetadataValueEnum::from(i64_type.const_zero()); function.count_params() as usize];
+                        for arg in args.iter_mut().rev() {
+                            *arg = self.convert(builder, pop_int(&mut stack)?, 8, false)?.into();
                         }
+                        let value = builder.build_call(function, &args[..], "value")?;
+                        if let Some(val) = value.try_as_basic_value().left() {
+                            stack.push(val);
+                        }
+                    },
+                    Instruction::Varref() => {
+                        let index = self.convert(builder, pop_int(&mut stack)?, 8, false)?;
+                        let var = unsafe { builder.build_gep(i64_type, locals, &[index], "var")? };
+                        stack.push(var.into());
                     },
                     Instruction::Iconst(size, value) => {
-                        let val = self.get_int_type(*size)?.const_int_arbitrary_precision(&[u64::from_le_bytes(value.to_le_bytes()[..8].try_into()?), u64::from_le_bytes(value.to_le_bytes()[8..].try_into()?)]);
-                        stack.push_back(
-                            val.into()
-                        )
+                        stack.push(self.get_int_type(size)?.const_int(value as u64, true).into());
                     },
-                    Instruction::Icast(size) => {
-                        if let Some(value) = stack.pop_back() {
-                            if value.into_int_value().get_type().get_bit_width() as u64 == *size {
-                                stack.push_back(value);
-                            }else if value.into_int_value().get_type().get_bit_width() as u64 > *size {
-                                let t = self.get_int_type(*size)?;
-                                let res = builder.build_int_truncate(value.into_int_value(), t, "res")?;
-                                stack.push_back(res.into());
-                            }else {
-                                let t = self.get_int_type(*size)?;
-                                let res = builder.build_int_s_extend(value.into_int_value(), t, "res")?;
-                                stack.push_back(res.into());
-                            }
-                        }else {
-                            return Err(anyhow!("tried to get a value from an empty stack\nwhile building icast in B{}", id))
-                        }
+                    Instruction::Iload(size) => {
+                        let var = pop_ref(&mut stack)?;
+                        let value = builder.build_load(i64_type, var, "load")?.into_int_value();
+                        stack.push(self.convert(builder, value, size, false)?.into());
                     },
-                    Instruction::Br(other_id) => {
-                        if let Some(other_blk) = llvm_blocks.get(*other_id as usize) {
-                            builder.build_unconditional_branch(other_blk.1)?;
-                            for arg in other_blk.0.iter().rev().zip(stack.iter().rev()) {
-                                arg.0.add_incoming(&[(arg.1, *llvm_blk)]);
-                            }
-                        }else {
-                            return Err(anyhow!("tried to branch to a non existant block : {} in B{}", *other_id, id))
-                        }
+                    Instruction::Istore(_) => {
+                        let value = self.convert(builder, pop_int(&mut stack)?, 8, false)?;
+                        builder.build_store(pop_ref(&mut stack)?, value)?;
                     },
-                    Instruction::Dup() => {
-                        if let Some(value) = stack.back() {
-                            stack.push_back(*value)
-                        }else {
-                            return Err(anyhow!("tried to get a value from an empty stack\nwhile building dup in B{}", id))
-                        }
+                    Instruction::Iret(_) => {
+                        let value = self.convert(builder, pop_int(&mut stack)?, 8, false)?;
+                        builder.build_return(Some(&value))?;
+                        break
                     },
-                    Instruction::Dupx(offset) => {
-                        if let Some(value) = stack.iter().nth(*offset as usize) {
-                            stack.push_back(*value)
-                        }else {
-                            return Err(anyhow!("tried to get a nonexistant value\nwhile building dupx in B{}", id))
-                        }
+                    Instruction::Inot(_) => {
+                        let value = pop_int(&mut stack)?;
+                        stack.push(builder.build_not(value, "res")?.into());
                     },
-                    Instruction::Swap() => {
-                        if let Some(val1) = stack.pop_back(){
-                            if let Some(val2) = stack.pop_back(){
-                                stack.push_back(val1);
-                                stack.push_back(val2);
-                            }else {
-                                return Err(anyhow!("tried to get a value from an empty stack\nwhile building swap in B{}", id))
-                            }
-                        }else {
-                            return Err(anyhow!("tried to get a value from an empty stack\nwhile building swap in B{}", id))
-                            }
+                    Instruction::Ineg(_) => {
+                        let value = pop_int(&mut stack)?;
+                        stack.push(builder.build_int_neg(value, "res")?.into());
                     },
-                    Instruction::Call(id) => {
-                        if let Some(function) = module.get_functions()
-                            .nth(*id as usize){
-                            let mut args = Vec::new();
-                            for _ in 0..function.count_params() {
-                                if let Some(arg) = stack.pop_back(){
-                                    args.push(arg.into());
-                                }else {
-                                    return Err(anyhow!("tried to call a function but the stack is too small"))
-                                }
-                            }
-                            let value = builder.build_call(function, &args[..], "value")?;
-                            if let Some(val) = value.try_as_basic_value().left() {
-                                stack.push_back(val);
-                            }
-                        }else {
-                            return Err(anyhow!("call of unregistered function : {}", id))
-                        }
+                    Instruction::Ior(_)
+                        | Instruction::Iand(_)
+                        | Instruction::Ixor(_)
+                        | Instruction::Ilshr(_)
+                        | Instruction::Iashr(_)
+                        | Instruction::Ishl(_)
+                        | Instruction::Iadd(_)
+                        | Instruction::Isub(_)
+                        | Instruction::Imul(_)
+                        => {
+                        let right = pop_int(&mut stack)?;
+                        let left = pop_int(&mut stack)?;
+                        // the shifts use the amount modulo the width like the interpreter
+                        let amount = || builder.build_and(right, right.get_type().const_int(right.get_type().get_bit_width() as u64 - 1, false), "amount");
+                        let result = match *i {
+                            Instruction::Ior(_) => builder.build_or(left, right, "res")?,
+                            Instruction::Iand(_) => builder.build_and(left, right, "res")?,
+                            Instruction::Ixor(_) => builder.build_xor(left, right, "res")?,
+                            Instruction::Ilshr(_) => builder.build_right_shift(left, amount()?, false, "res")?,
+                            Instruction::Iashr(_) => builder.build_right_shift(left, amount()?, true, "res")?,
+                            Instruction::Ishl(_) => builder.build_left_shift(left, amount()?, "res")?,
+                            Instruction::Iadd(_) => builder.build_int_add(left, right, "res")?,
+                            Instruction::Isub(_) => builder.build_int_sub(left, right, "res")?,
+                            Instruction::Imul(_) => builder.build_int_mul(left, right, "res")?,
+                            _ => unreachable!()
+                        };
+                        stack.push(result.into());
                     },
-                    Instruction::Neg() => {
-                        if let Some(value) = stack.pop_back() {
-                            let result = builder.build_int_sub(
-                                value.into_int_value().get_type().const_zero(),
-                                value.into_int_value() ,
-                                "result")?;
-                            stack.push_back(result.into())
-                        }else {
-                            return Err(anyhow!("tried to get a value from an empty stack\nwhile building neg in B{}", id))
-                        }
+                    Instruction::Idiv(_)
+                        | Instruction::Udiv(_)
+                        | Instruction::Irem(_)
+                        | Instruction::Urem(_)
+                        => {
+                        let right = pop_int(&mut stack)?;
+                        let left = pop_int(&mut stack)?;
+                        let t = right.get_type();
+                        let current = builder.get_insert_block().unwrap_or(*llvm_blk);
+                        let divide = self.ctx.insert_basic_block_after(current, "divide");
+                        let is_zero = builder.build_int_compare(IntPredicate::EQ, right, t.const_zero(), "is_zero")?;
+                        builder.build_conditional_branch(is_zero, zero_division, divide)?;
+                        builder.position_at_end(divide);
+                        // the signed division of the minimum by -1 overflows, the interpreter wraps it
+                        let is_minus_one = builder.build_int_compare(IntPredicate::EQ, right, t.const_all_ones(), "is_minus_one")?;
+                        let divisor = builder.build_select(is_minus_one, t.const_int(1, false), right, "divisor")?.into_int_value();
+                        let result = match *i {
+                            Instruction::Idiv(_) => {
+                                let quotient = builder.build_int_signed_div(left, divisor, "quotient")?;
+                                let negated = builder.build_int_neg(left, "negated")?;
+                                builder.build_select(is_minus_one, negated, quotient, "res")?.into_int_value()
+                            },
+                            Instruction::Irem(_) => builder.build_int_signed_rem(left, divisor, "res")?,
+                            Instruction::Udiv(_) => builder.build_int_unsigned_div(left, right, "res")?,
+                            Instruction::Urem(_) => builder.build_int_unsigned_rem(left, right, "res")?,
+                            _ => unreachable!()
+                        };
+                        stack.push(result.into());
                     },
-                    Instruction::Add()
-                        | Instruction::Sub()
-                        | Instruction::Mul()
-                        | Instruction::Div()
-                        | Instruction::Eq()
-                        | Instruction::Ne()
-                        | Instruction::Gt()
-                        | Instruction::Ge()
-                        | Instruction::Le()
-                        | Instruction::Lt()
-                        | Instruction::Lsh()
-                        | Instruction::Rsh()
+                    Instruction::Ieq(_)
+                        | Instruction::Ine(_)
+                        | Instruction::Ige(_)
+                        | Instruction::Igt(_)
+                        | Instruction::Uge(_)
+                        | Instruction::Ugt(_)
+                        | Instruction::Ilt(_)
+                        | Instruction::Ile(_)
+                        | Instruction::Ule(_)
+                        | Instruction::Ult(_)
                         => {
-                        if let Some(val1) = stack.pop_back(){
-                            if let Some(val2) = stack.pop_back(){
-                                if val1.into_int_value().get_type().get_bit_width() != val2.into_int_value().get_type().get_bit_width() {
-                                    return Err(anyhow!("mismached types i{}, i{}\n while building {} in B{}",
-                                            val1.into_int_value().get_type().get_bit_width(),
-                                            val2.into_int_value().get_type().get_bit_width(),
-                                            match i {
-                                                Instruction::Add() => "add",
-                                                Instruction::Sub() => "sub",
-                                                Instruction::Mul() => "mul",
-                                                Instruction::Div() => "div",
-                                                Instruction::Eq() => "eq",
-                                                Instruction::Ne() => "ne",
-                                                Instruction::Gt() => "gt",
-                                                Instruction::Ge() => "ge",
-                                                Instruction::Le() => "le",
-                                                Instruction::Lt() => "lt",
-                                                Instruction::Lsh() => "lsh",
-                                                Instruction::Rsh() => "rsh",
-                                                _ => unreachable!()
-                                            },
-                                            id))
-                                }
-                                let result_type = val1.into_int_value().get_type();
-                                let result = match i {
-                                    Instruction::Add() => {
-                                        builder.build_int_add(
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                        "res")?
-                                    },
-                                    Instruction::Sub() => {
-                                        builder.build_int_sub(
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                        "res")?
-                                    },
-                                    Instruction::Mul() => {
-                                        builder.build_int_mul(
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                        "res")?
-                                    },
-                                    Instruction::Div() => {
-                                        builder.build_int_signed_div(
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                        "res")?
-                                    },
-                                    Instruction::Eq() => {
-                                        let cmp = builder.build_int_compare(
-                                            inkwell::IntPredicate::EQ,
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            "cmp")?;
-                                        builder.build_int_z_extend(
-                                            cmp,
-                                            result_type,
-                                            "res")?
-                                    },
-                                    Instruction::Ne() => {
-                                        let cmp = builder.build_int_compare(
-                                            inkwell::IntPredicate::NE,
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            "cmp")?;
-                                        builder.build_int_z_extend(
-                                            cmp,
-                                            result_type,
-                                            "res")?
-                                    },
-                                    Instruction::Gt() => {
-                                        let cmp = builder.build_int_compare(
-                                            inkwell::IntPredicate::SGT,
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            "cmp")?;
-                                        builder.build_int_z_extend(
-                                            cmp,
-                                            result_type,
-                                            "res")?
-                                    },
-                                    Instruction::Ge() => {
-                                        let cmp = builder.build_int_compare(
-                                            inkwell::IntPredicate::SGE,
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            "cmp")?;
-                                        builder.build_int_z_extend(
-                                            cmp,
-                                            result_type,
-                                            "res")?
-                                    },
-                                    Instruction::Le() => {
-                                        let cmp = builder.build_int_compare(
-                                            inkwell::IntPredicate::SLE,
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            "cmp")?;
-                                        builder.build_int_z_extend(
-                                            cmp,
-                                            result_type,
-                                            "res")?
-                                    },
-                                    Instruction::Lt() => {
-                                        let cmp = builder.build_int_compare(
-                                            inkwell::IntPredicate::SLT,
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            "cmp")?;
-                                        builder.build_int_z_extend(
-                                            cmp,
-                                            result_type,
-                                            "res")?
-                                    },
-                                    Instruction::Lsh() => {
-                                        builder.build_left_shift(
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                        "res")?
-                                    },
-                                    Instruction::Rsh() => {
-                                        builder.build_right_shift(
-                                            val2.into_int_value(),
-                                            val1.into_int_value(),
-                                            false,
-                                        "res")?
-                                    },
-                                    _ => unreachable!()
-                                };
-                                stack.push_back(result.into());
-                            }else {
-                                return Err(anyhow!("tried to get a value from an empty stack\nwhile building swap in B{}", id))
-                            }
-                        }else {
-                            return Err(anyhow!("tried to get a value from an empty stack\nwhile building swap in B{}", id))
-                        }
+                        let right = pop_int(&mut stack)?;
+                        let left = pop_int(&mut stack)?;
+                        let cmp = builder.build_int_compare(predicate(i).unwrap_or(IntPredicate::EQ), left, right, "cmp")?;
+                        stack.push(self.convert(builder, cmp, 1, true)?.into());
                     },
-                    Instruction::Briz(id1, id2) => {
-                        if let Some(blk1) = llvm_blocks.get(*id1 as usize) {
-                            if let Some(blk2) = llvm_blocks.get(*id2 as usize){
-                                if let Some(cond) = stack.pop_back() {
-                                    let cond = builder.build_int_compare(
-                                        inkwell::IntPredicate::EQ,
-                                        cond.into_int_value(),
-                                        cond.into_int_value().get_type().const_zero().into(),
-                                        "cond")?;
-                                    builder.build_conditional_branch(
-                                        cond,
-                                        blk1.1,
-                                        blk2.1
-                                    )?;
-                                    for arg in blk1.0.iter().rev().zip(stack.iter().rev()) {
-                                        arg.0.add_incoming(&[(arg.1, *llvm_blk)]);
-                                    }
-                                    for arg in blk2.0.iter().rev().zip(stack.iter().rev()) {
-                                        arg.0.add_incoming(&[(arg.1, *llvm_blk)]);
-                                    }
-                                }else {
-                                    return Err(anyhow!("tried to get a value from an empty stack\nwhile building briz in B{}", id))
-                                }
-                            }else {
-                                return Err(anyhow!("tried to branch to a non existant block : {} in B{}", *id2, id))
-                            }
-                        }else {
-                            return Err(anyhow!("tried to branch to a non existant block : {}, in B{}", *id1, id))
-                        }
+                    Instruction::Iconv(_, to) => {
+                        let value = pop_int(&mut stack)?;
+                        stack.push(self.convert(builder, value, to, false)?.into());
+                    },
+                    Instruction::Uconv(_, to) => {
+                        let value = pop_int(&mut stack)?;
+                        stack.push(self.convert(builder, value, to, true)?.into());
                     }
                 }
             }
@@ -373,23 +344,87 @@ impl Runtime for LLVMRuntime {
         }
     }
 
-    fn run(&mut self, object : jolang_shared::ir::IrObject) -> anyhow::Result<i64> {
+    fn run(&mut self, object : IrObject) -> Result<i64> {
+        Ok(self.run_timed(object)?.0)
+    }
+
+    /// the jit compilation is the preparation
+    fn run_timed(&mut self, object : IrObject) -> Result<(i64, RunTimes)> {
+        verify(&object)?;
+        let start = Instant::now();
         let module = self.ctx.create_module("jolang_main");
         let builder = self.ctx.create_builder();
         self.load_externs(&object.ext_fn, &module, &builder)?;
         let main_sig = self.ctx.i64_type().fn_type(&[], false);
         let main_value = module.add_function("main", main_sig, None);
-        self.gen_function(&object.blocks, main_value, &module, &builder)?;
-
-        module.print_to_stderr();
+        self.gen_function(&object, main_value, &module, &builder)?;
+        module.verify().map_err(|e| anyhow!("invalid llvm module : {}", e))?;
 
-        let execution_engine = module.create_jit_execution_engine(OptimizationLevel::Default).unwrap();
+        let execution_engine = module.create_jit_execution_engine(OptimizationLevel::Default)
+            .map_err(|e| anyhow!("can't create the execution engine : {}", e))?;
 
         unsafe {
             type MainFn = unsafe extern "C" fn() -> i64;
-            let main_fn: JitFunction<MainFn> = execution_engine.get_function("main").unwrap();
-            Ok(main_fn.call())
+            let main_fn: JitFunction<MainFn> = execution_engine.get_function("main")?;
+            let prepare = start.elapsed();
+            let start = Instant::now();
+            DIVISION_BY_ZERO.with(|d| d.set(false));
+            let value = main_fn.call();
+            if DIVISION_BY_ZERO.with(|d| d.get()) {
+                return Err(anyhow!("division by zero"))
+            }
+            Ok((value, RunTimes {
+                prepare : Some(prepare),
+                execute : start.elapsed()
+            }))
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use jolang_shared::{ffi::jolang_std::capture_output, ir::assembler::assemble};
+
+    fn object(body : &str) -> IrObject {
+        assemble(&format!("extern fn print (i64) -> void;\nextern fn pow (i64, i64) -> i64;\nfn main () -> i64 {{\n\tvar 0 8\n\tvar 1 1\n{}\n}}", body)).unwrap()
+    }
+
+    #[test]
+    fn test_same_as_interpreter() {
+        let bodies = [
+            // countdown with the counter passed to the blocks on the stack
+            "B0 :\n\ticonst 8 3\n\tbr 1\nB1 :\n\tdup 8\n\tbriz 3 2\nB2 :\n\tdup 8\n\tcall 0\n\ticonst 8 1\n\tisub 8\n\tbr 1\nB3 :\n\ticonst 8 7\n\tswap 8\n\tpop 8\n\tiret 8",
+            // the variables are zeroed and keep the size of their stores
+            "B0 :\n\ticonst 4 1\n\tvarref\n\ticonst 8 300\n\ticonv 8 1\n\tistore 1\n\ticonst 4 1\n\tvarref\n\tiload 1\n\ticonv 1 8\n\ticonst 4 0\n\tvarref\n\tiload 8\n\tiadd 8\n\tiret 8",
+            "B0 :\n\ticonst 1 -1\n\tuconv 1 8\n\ticonst 1 -1\n\ticonv 1 8\n\tiadd 8\n\ticonst 8 2\n\ticonst 8 10\n\tcall 1\n\timul 8\n\tiret 8",
+            "B0 :\n\ticonst 8 -7\n\ticonst 8 2\n\tidiv 8\n\ticonst 8 -7\n\ticonst 8 2\n\tirem 8\n\tixor 8\n\ticonst 8 -16\n\ticonst 8 66\n\tiashr 8\n\tior 8\n\tiret 8",
+            // the division of the minimum by -1 wraps
+            "B0 :\n\ticonst 1 -128\n\ticonst 1 -1\n\tidiv 1\n\ticonv 1 8\n\ticonst 1 -128\n\ticonst 1 -1\n\tirem 1\n\ticonv 1 8\n\tiadd 8\n\tiret 8",
+            "B0 :\n\ticonst 4 -1\n\ticonst 4 1\n\tult 4\n\ticonst 4 -1\n\ticonst 4 1\n\tilt 4\n\ticonst 1 1\n\tishl 1\n\tior 1\n\tineg 1\n\tinot 1\n\ticonv 1 8\n\tiret 8",
+        ];
+        for body in bodies {
+            let expected = capture_output(|| Interpreter::new().run(object(body)).unwrap());
+            assert_eq!(capture_output(|| LLVMRuntime::new().run(object(body)).unwrap()), expected, "{}", body);
+        }
+        let ((value, times), output) = capture_output(|| LLVMRuntime::new().run_timed(object(bodies[0])).unwrap());
+        assert_eq!((value, output.as_str()), (7, "3\n2\n1\n"));
+        assert!(times.prepare.is_some());
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        for division in ["idiv", "udiv", "irem", "urem"] {
+            let body = format!("B0 :\n\ticonst 8 1\n\ticonst 8 0\n\t{} 8\n\tiret 8", division);
+            assert_eq!(Interpreter::new().run(object(&body)).unwrap_err().to_string(), "division by zero");
+            assert_eq!(LLVMRuntime::new().run(object(&body)).unwrap_err().to_string(), "division by zero");
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let error = LLVMRuntime::new().run(object("B0 :\n\tiadd 8\n\tiret 8")).unwrap_err();
+        assert_eq!(error.to_string(), "invalid ir : B0 instruction 0 (iadd 8) : the stack is empty");
+    }
+}
@@ -2,6 +2,8 @@ use super::Platform;
 use crate::Runtime;
 #[cfg(feature = "llvm")]
 use crate::llvm::LLVMRuntime;
+#[cfg(not(feature = "llvm"))]
+use crate::interpreter::Interpreter;
 
 pub struct LinuxPlatform  {}
 
@@ -16,8 +18,8 @@ impl Platform for LinuxPlatform {
         Box::new(LLVMRuntime::new())
     }
 
-    #[cfg(not(all(feature = "llvm")))]
+    #[cfg(not(feature = "llvm"))]
     fn default_runtime(&self) -> Box<dyn crate::Runtime> {
-        panic!("no compatible runtimes found for platform linux\ntry to activate a compatible runtime feature") 
+        Box::new(Interpreter::new())
     }
 }
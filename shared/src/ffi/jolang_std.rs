@@ -1,28 +1,102 @@
 //! standard (builtin functions)
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::io::Write;
 use rand::Rng;
 use lazy_static::lazy_static;
 use super::JolangExtern;
 
+// the programs call the builtins on the thread that runs them so each thread has its own redirections
+thread_local! {
+    /// text printed while the output is captured
+    static CAPTURED : RefCell<Option<String>> = const { RefCell::new(None) };
+    /// lines read by input instead of the standard input
+    static PROVIDED : RefCell<Option<VecDeque<String>>> = const { RefCell::new(None) };
+}
+
+// the builtins must not panic, a panic can't unwind out of an extern "C" function
+
 extern "C" fn print(value : i64) {
-    println!("{}", value);
+    let captured = CAPTURED.with(|c| match c.try_borrow_mut().as_deref_mut() {
+        Ok(Some(output)) => {
+            output.push_str(&format!("{}\n", value));
+            true
+        },
+        _ => false
+    });
+    if !captured {
+        let _ = writeln!(std::io::stdout(), "{}", value);
+    }
+}
+
+/// run f and return what print would have written to the standard output on this thread
+pub fn capture_output<T>(f : impl FnOnce() -> T) -> (T, String) {
+    let previous = CAPTURED.with(|c| c.replace(Some(String::new())));
+    let result = f();
+    let output = CAPTURED.with(|c| c.replace(previous)).unwrap_or_default();
+    (result, output)
+}
+
+/// run f with input reading the given lines on this thread, without prompt, and 0 once there are no more lines
+pub fn provide_input<T>(lines : Vec<String>, f : impl FnOnce() -> T) -> T {
+    let previous = PROVIDED.with(|p| p.replace(Some(lines.into())));
+    let result = f();
+    PROVIDED.with(|p| p.replace(previous));
+    result
 }
 
 extern "C" fn input() -> i64 {
-    print!("input: ");
-    std::io::stdout().flush().expect("failed to print to stdout");
-    let mut line = String::new();
-    std::io::stdin()
-        .read_line(&mut line)
-        .expect("failed to read input from stdout");
-    return line.trim().parse().expect("Failed to parse integer");
+    let provided = PROVIDED.with(|p| match p.try_borrow_mut().as_deref_mut() {
+        Ok(Some(lines)) => Some(lines.pop_front().and_then(|l| l.trim().parse().ok()).unwrap_or(0)),
+        _ => None
+    });
+    if let Some(value) = provided {
+        return value
+    }
+    loop {
+        let _ = write!(std::io::stdout(), "input: ");
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            // nothing more can be read
+            Ok(0) | Err(_) => return 0,
+            Ok(_) => match line.trim().parse() {
+                Ok(value) => return value,
+                Err(_) => {
+                    let _ = writeln!(std::io::stdout(), "not an integer : {}", line.trim());
+                }
+            }
+        }
+    }
 }
 
+/// the power wraps on overflow like the arithmetic of the programs,
+/// a negative exponent gives the integer part of the fraction (0 unless the value is 1 or -1)
 extern "C" fn pow(value : i64, exponent : i64) -> i64 {
-    value.pow(exponent as u32)
+    if exponent < 0 {
+        return match value {
+            1 => 1,
+            -1 if exponent % 2 == 0 => 1,
+            -1 => -1,
+            _ => 0
+        }
+    }
+    let (mut result, mut base, mut exponent) = (1i64, value, exponent as u64);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
 }
 
+/// a random integer from min to max excluded, min when the range is empty
 extern "C" fn randint(min : i64, max : i64) -> i64 {
+    if min >= max {
+        return min
+    }
     let mut rng = rand::thread_rng();
 
     rng.gen_range(min..max)
@@ -36,3 +110,48 @@ lazy_static! {
         ("randint", Box::new(randint as extern "C" fn(i64, i64) -> i64))
     ];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_redirections_per_thread() {
+        let outputs : Vec<String> = thread::scope(|scope| {
+            let threads : Vec<_> = (0..4).map(|i| scope.spawn(move || {
+                let lines = vec![(i * 10).to_string()];
+                provide_input(lines, || capture_output(|| {
+                    for _ in 0..100 {
+                        print(input() + i);
+                    }
+                })).1
+            })).collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        for (i, output) in outputs.iter().enumerate() {
+            let expected = format!("{}\n", i * 11) + &format!("{}\n", i).repeat(99);
+            assert_eq!(output, &expected);
+        }
+        // the redirections are nested
+        let (inner, outer) = capture_output(|| {
+            print(1);
+            let inner = capture_output(|| print(2)).1;
+            print(3);
+            inner
+        });
+        assert_eq!((inner.as_str(), outer.as_str()), ("2\n", "1\n3\n"));
+    }
+
+    #[test]
+    fn test_edge_inputs() {
+        assert_eq!(pow(3, 4), 81);
+        assert_eq!(pow(2, 100), 0);
+        assert_eq!(pow(3, 41), 3i64.wrapping_pow(41));
+        assert_eq!(pow(-2, 63), i64::MIN);
+        assert_eq!([pow(-1, i64::MAX), pow(3, 1 << 40)], [-1, 3i64.wrapping_pow(1 << 20).wrapping_pow(1 << 20)]);
+        assert_eq!([pow(2, -1), pow(1, -5), pow(-1, -3), pow(-1, -2), pow(0, -1)], [0, 1, -1, 1, 0]);
+        assert_eq!([randint(1, 1), randint(5, 2), randint(1, 2)], [1, 5, 1]);
+        assert!((0..100).map(|_| randint(-3, 3)).all(|v| (-3..3).contains(&v)));
+    }
+}
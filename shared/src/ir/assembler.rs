@@ -0,0 +1,221 @@
+//! assembler of the textual ir written by the printer, the text of an assembled object is printed back unchanged
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Result};
+use super::instructions::{operand::Size, Instruction};
+use super::{IrExternalFn, IrObject, Signature};
+
+const MAIN : &str = "fn main () -> i64 {";
+
+fn number<T : FromStr>(text : &str) -> Result<T> {
+    text.parse().map_err(|_| anyhow!("invalid operand : {}", text))
+}
+
+/// the instructions with a single size operand
+fn sized(mnemonic : &str) -> Option<fn(Size) -> Instruction> {
+    Some(match mnemonic {
+        "pop" => Instruction::Pop,
+        "dup" => Instruction::Dup,
+        "swap" => Instruction::Swap,
+        "iload" => Instruction::Iload,
+        "istore" => Instruction::Istore,
+        "iret" => Instruction::Iret,
+        "inot" => Instruction::Inot,
+        "ior" => Instruction::Ior,
+        "iand" => Instruction::Iand,
+        "ixor" => Instruction::Ixor,
+        "ilshr" => Instruction::Ilshr,
+        "iashr" => Instruction::Iashr,
+        "ishl" => Instruction::Ishl,
+        "ineg" => Instruction::Ineg,
+        "iadd" => Instruction::Iadd,
+        "isub" => Instruction::Isub,
+        "imul" => Instruction::Imul,
+        "idiv" => Instruction::Idiv,
+        "udiv" => Instruction::Udiv,
+        "irem" => Instruction::Irem,
+        "urem" => Instruction::Urem,
+        "ieq" => Instruction::Ieq,
+        "ine" => Instruction::Ine,
+        "ige" => Instruction::Ige,
+        "igt" => Instruction::Igt,
+        "uge" => Instruction::Uge,
+        "ugt" => Instruction::Ugt,
+        "ilt" => Instruction::Ilt,
+        "ile" => Instruction::Ile,
+        "ule" => Instruction::Ule,
+        "ult" => Instruction::Ult,
+        _ => return None
+    })
+}
+
+fn operand_count(mnemonic : &str) -> Option<usize> {
+    match mnemonic {
+        "nop" | "varref" => Some(0),
+        "br" | "call" => Some(1),
+        "briz" | "iconst" | "iconv" | "uconv" => Some(2),
+        m => sized(m).map(|_| 1)
+    }
+}
+
+pub fn parse_instruction(line : &str) -> Result<Instruction> {
+    let mut words = line.split_whitespace();
+    let mnemonic = words.next().ok_or_else(|| anyhow!("expected an instruction"))?;
+    let operands : Vec<&str> = words.collect();
+    Ok(match (mnemonic, operands.as_slice()) {
+        ("nop", []) => Instruction::Nop(),
+        ("varref", []) => Instruction::Varref(),
+        ("br", [id]) => Instruction::Br(number(id)?),
+        ("briz", [id1, id2]) => Instruction::Briz(number(id1)?, number(id2)?),
+        ("call", [id]) => Instruction::Call(number(id)?),
+        ("iconst", [size, val]) => Instruction::Iconst(number(size)?, number(val)?),
+        ("iconv", [size1, size2]) => Instruction::Iconv(number(size1)?, number(size2)?),
+        ("uconv", [size1, size2]) => Instruction::Uconv(number(size1)?, number(size2)?),
+        (m, [size]) if sized(m).is_some() => sized(m).unwrap()(number(size)?),
+        (m, operands) => match operand_count(m) {
+            Some(count) => bail!("{} expects {} operands but found {}", m, count, operands.len()),
+            None => bail!("unknown instruction : {}", m)
+        }
+    })
+}
+
+/// `extern fn name (arg, arg) -> ret;`
+fn parse_extern(line : &str) -> Result<IrExternalFn> {
+    let invalid = || anyhow!("expected `extern fn name (args) -> ret;`");
+    let decl = line.strip_prefix("extern fn ")
+        .and_then(|l| l.strip_suffix(';'))
+        .ok_or_else(invalid)?;
+    let (name, rest) = decl.split_once('(').ok_or_else(invalid)?;
+    let (args, ret) = rest.split_once(')').ok_or_else(invalid)?;
+    let ret = ret.trim().strip_prefix("->").ok_or_else(invalid)?.trim();
+    let name = name.trim();
+    if name.is_empty() || ret.is_empty() {
+        return Err(invalid())
+    }
+    let args = args.split(',')
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .collect();
+    Ok(IrExternalFn::new(name.to_string(), Signature::new(ret.to_string(), args)))
+}
+
+enum Section {
+    Externs,
+    Main,
+    End
+}
+
+fn parse_line(object : &mut IrObject, section : &mut Section, line : &str) -> Result<()> {
+    match section {
+        Section::Externs if line == MAIN => *section = Section::Main,
+        Section::Externs => object.ext_fn.push(parse_extern(line)?),
+        Section::Main if line == "}" => *section = Section::End,
+        Section::Main if line.starts_with("var ") => {
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["var", id, size] => {
+                    if number::<usize>(id)? != object.local_vars.len() {
+                        bail!("expected the variable {}", object.local_vars.len())
+                    }
+                    if !object.blocks.is_empty() {
+                        bail!("variables must be declared before the blocks")
+                    }
+                    object.local_vars.push(number(size)?);
+                },
+                _ => bail!("expected `var id size`")
+            }
+        },
+        Section::Main if line.starts_with('B') && line.ends_with(':') => {
+            let id = line[1..line.len() - 1].trim();
+            if number::<usize>(id)? != object.blocks.len() {
+                bail!("expected the block B{}", object.blocks.len())
+            }
+            object.append_block();
+        },
+        Section::Main => {
+            let instruction = parse_instruction(line)?;
+            match object.blocks.last_mut() {
+                Some(block) => { block.instructions.insert_last(instruction); },
+                None => bail!("instruction outside of a block")
+            }
+        },
+        Section::End => bail!("unexpected text after the end of main")
+    }
+    Ok(())
+}
+
+/// check the operands that reference the blocks and the externs
+pub(crate) fn check_references(object : &IrObject) -> Result<()> {
+    let blocks = object.blocks.len() as u32;
+    let externs = object.ext_fn.len() as u32;
+    for (i, block) in object.blocks.iter().enumerate() {
+        for instruction in block.instructions.iter() {
+            match *instruction {
+                Instruction::Br(id) if id >= blocks => bail!("B{} : branch to the unknown block B{}", i, id),
+                Instruction::Briz(id1, id2) if id1 >= blocks || id2 >= blocks
+                    => bail!("B{} : branch to the unknown block B{}", i, id1.max(id2)),
+                Instruction::Call(id) if id >= externs => bail!("B{} : call of the unknown extern {}", i, id),
+                _ => ()
+            }
+        }
+    }
+    Ok(())
+}
+
+/// parse the text of an object, empty lines and `//` comments (also at the end of a line) are ignored
+pub fn assemble(text : &str) -> Result<IrObject> {
+    let mut object = IrObject::new();
+    let mut section = Section::Externs;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        parse_line(&mut object, &mut section, line)
+            .map_err(|e| anyhow!("line {} : {}", n + 1, e))?;
+    }
+    match section {
+        Section::Externs => bail!("expected `{}`", MAIN),
+        Section::Main => bail!("expected `}}` at the end of main"),
+        Section::End => ()
+    }
+    check_references(&object)?;
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT : &str = "extern fn print (i64) -> void;\nextern fn input () -> i64;\nfn main () -> i64 {\n\tvar 0 8\nB0 : \n\ticonst 4 0\n\tvarref\n\tcall 1\n\ticonv 8 1\n\tistore 1\n\ticonst 8 -3\n\tcall 0\n\tbriz 1 1\nB1 : \n\ticonst 8 0\n\tiret 8\n}";
+
+    #[test]
+    fn test_round_trip() {
+        let object = assemble(TEXT).unwrap();
+        assert_eq!(object.ext_fn[0], IrExternalFn::new("print".to_string(), Signature::new("void".to_string(), vec!["i64".to_string()])));
+        assert_eq!(object.local_vars, vec![8]);
+        assert_eq!(object.blocks[0].instructions.get_first(), Some(&Instruction::Iconst(4, 0)));
+        assert_eq!(format!("{:?}", object), TEXT);
+        // comments and indentation are not kept
+        let commented = TEXT.replace("B1 : \n", "// exit\n  B1: // 2 instructions\n").replace("call 1\n", "call 1 // input\n");
+        assert_eq!(format!("{:?}", assemble(&commented).unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("", "expected `fn main () -> i64 {`"),
+            ("fn main () -> i64 {\nB0 :\n", "expected `}` at the end of main"),
+            ("extern fn print i64;\n", "line 1 : expected `extern fn name (args) -> ret;`"),
+            ("fn main () -> i64 {\nB0 :\n\tiadd\n}", "line 3 : iadd expects 1 operands but found 0"),
+            ("fn main () -> i64 {\nB0 :\n\tiadd8 8\n}", "line 3 : unknown instruction : iadd8"),
+            ("fn main () -> i64 {\nB0 :\n\ticonst 8 x\n}", "line 3 : invalid operand : x"),
+            ("fn main () -> i64 {\nB1 :\n}", "line 2 : expected the block B0"),
+            ("fn main () -> i64 {\n\tnop\n}", "line 2 : instruction outside of a block"),
+            ("fn main () -> i64 {\nB0 :\n\tbr 2\n}", "B0 : branch to the unknown block B2"),
+            ("fn main () -> i64 {\nB0 :\n\tcall 0\n}", "B0 : call of the unknown extern 0"),
+            ("fn main () -> i64 {\n}\nnop", "line 3 : unexpected text after the end of main"),
+        ];
+        for (text, message) in cases {
+            assert_eq!(assemble(text).unwrap_err().to_string(), message, "{}", text);
+        }
+    }
+}
@@ -2,7 +2,9 @@ use index_list::IndexList;
 use super::instructions::Instruction;
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
+    #[cfg_attr(feature = "serde", serde(with = "instruction_list"))]
     pub instructions : IndexList<Instruction>,
 }
 
@@ -13,3 +15,19 @@ impl Block {
         }
     }
 }
+
+/// the instructions are serialized as a sequence
+#[cfg(feature = "serde")]
+mod instruction_list {
+    use index_list::IndexList;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use super::Instruction;
+
+    pub fn serialize<S : Serializer>(list : &IndexList<Instruction>, serializer : S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(list.iter())
+    }
+
+    pub fn deserialize<'de, D : Deserializer<'de>>(deserializer : D) -> Result<IndexList<Instruction>, D::Error> {
+        Ok(Vec::<Instruction>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
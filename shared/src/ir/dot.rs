@@ -0,0 +1,64 @@
+//! export of the control-flow graph to the graphviz dot format
+use super::instructions::Instruction;
+use super::verifier::{analyze, type_name};
+use super::IrObject;
+
+/// escape a string for a quoted dot label
+fn escape(text : &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// a node for each block with its arguments and its instructions, an edge for each branch.
+/// the first target of briz is taken when the value is zero, the second one is not taken
+pub fn to_dot(object : &IrObject) -> String {
+    let stacks = analyze(object).entries;
+    let mut dot = String::from("digraph cfg {\n\tnode [shape=box, fontname=\"monospace\"];\n");
+    for (id, block) in object.blocks.iter().enumerate() {
+        let args = match &stacks[id] {
+            Some(stack) => format!("({})", stack.iter().map(|s| type_name(*s)).collect::<Vec<_>>().join(", ")),
+            None => "unreachable".to_string()
+        };
+        let mut label = format!("B{} {}\\l", id, args);
+        for instruction in block.instructions.iter() {
+            label += &format!("  {}\\l", escape(&instruction.to_string()));
+        }
+        let style = if stacks[id].is_none() { ", style=dashed" } else { "" };
+        dot += &format!("\tB{} [label=\"{}\"{}];\n", id, label, style);
+    }
+    for (id, block) in object.blocks.iter().enumerate() {
+        match block.instructions.get_last() {
+            Some(Instruction::Br(target)) => dot += &format!("\tB{} -> B{};\n", id, target),
+            Some(Instruction::Briz(zero, not_zero)) => {
+                dot += &format!("\tB{} -> B{} [label=\"taken\"];\n", id, zero);
+                dot += &format!("\tB{} -> B{} [label=\"not taken\"];\n", id, not_zero);
+            },
+            _ => ()
+        }
+    }
+    dot += "}\n";
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::assembler::assemble;
+
+    #[test]
+    fn test_to_dot() {
+        let object = assemble("fn main () -> i64 {\nB0 :\n\ticonst 8 1\n\ticonst 8 0\n\tbriz 1 2\nB1 :\n\tbr 2\nB2 :\n\tiret 8\nB3 :\n\tbr 2\n}").unwrap();
+        assert_eq!(to_dot(&object), concat!(
+            "digraph cfg {\n",
+            "\tnode [shape=box, fontname=\"monospace\"];\n",
+            "\tB0 [label=\"B0 ()\\l  iconst 8 1\\l  iconst 8 0\\l  briz 1 2\\l\"];\n",
+            "\tB1 [label=\"B1 (i64)\\l  br 2\\l\"];\n",
+            "\tB2 [label=\"B2 (i64)\\l  iret 8\\l\"];\n",
+            "\tB3 [label=\"B3 unreachable\\l  br 2\\l\", style=dashed];\n",
+            "\tB0 -> B1 [label=\"taken\"];\n",
+            "\tB0 -> B2 [label=\"not taken\"];\n",
+            "\tB1 -> B2;\n",
+            "\tB3 -> B2;\n",
+            "}\n"));
+        assert_eq!(escape("a \"b\" \\"), "a \\\"b\\\" \\\\");
+    }
+}
@@ -50,9 +50,39 @@ pub mod operand {
     pub type BlkId = u32;
     pub type FnId = u32;
     pub type Size = u32;
+
+    /// keep the least significant bytes of an integer and sign extend it
+    pub fn sext(value : Imm, size : Size) -> Imm {
+        match size {
+            1 => value as i8 as Imm,
+            2 => value as i16 as Imm,
+            4 => value as i32 as Imm,
+            _ => value
+        }
+    }
+
+    /// keep the least significant bytes of an integer as an unsigned value
+    pub fn zext(value : Imm, size : Size) -> u64 {
+        match size {
+            1 => value as u8 as u64,
+            2 => value as u16 as u64,
+            4 => value as u32 as u64,
+            _ => value as u64
+        }
+    }
+
+    /// the number of bits of an integer of the size
+    pub fn bits(size : Size) -> u32 {
+        match size {
+            1 | 2 | 4 => size * 8,
+            _ => 64
+        }
+    }
 }
 
+/// serialized with the mnemonic as key and the operands as value, `{"iconst":[8,1]}`
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum Instruction {
     Nop(),
     Pop(operand::Size),
@@ -140,3 +170,115 @@ impl Instruction {
         }
     }
 }
+
+/// size of an encoded instruction : 3 padding bytes, the opcode and 12 bytes of operands
+pub const INSTRUCTION_SIZE : usize = 16;
+
+fn operand(bytes : &[u8; INSTRUCTION_SIZE], index : usize) -> u32 {
+    u32::from_le_bytes(bytes[4 + 4 * index..8 + 4 * index].try_into().unwrap())
+}
+
+impl Instruction {
+    pub fn to_bytes(&self) -> [u8; INSTRUCTION_SIZE] {
+        let mut bytes = [0; INSTRUCTION_SIZE];
+        bytes[3] = self.opcode().into();
+        let mut operands = |values : &[u32]| {
+            for (i, v) in values.iter().enumerate() {
+                bytes[4 + 4 * i..8 + 4 * i].copy_from_slice(&v.to_le_bytes());
+            }
+        };
+        match *self {
+            Self::Nop() | Self::Varref() => (),
+            Self::Iconst(size, value) => {
+                operands(&[size]);
+                bytes[8..16].copy_from_slice(&value.to_le_bytes());
+            },
+            Self::Briz(op1, op2)
+                | Self::Iconv(op1, op2)
+                | Self::Uconv(op1, op2)
+                => operands(&[op1, op2]),
+            Self::Pop(op)
+                | Self::Dup(op)
+                | Self::Swap(op)
+                | Self::Br(op)
+                | Self::Call(op)
+                | Self::Iload(op)
+                | Self::Istore(op)
+                | Self::Iret(op)
+                | Self::Inot(op)
+                | Self::Ior(op)
+                | Self::Iand(op)
+                | Self::Ixor(op)
+                | Self::Ilshr(op)
+                | Self::Iashr(op)
+                | Self::Ishl(op)
+                | Self::Ineg(op)
+                | Self::Iadd(op)
+                | Self::Isub(op)
+                | Self::Imul(op)
+                | Self::Idiv(op)
+                | Self::Udiv(op)
+                | Self::Irem(op)
+                | Self::Urem(op)
+                | Self::Ieq(op)
+                | Self::Ine(op)
+                | Self::Ige(op)
+                | Self::Igt(op)
+                | Self::Uge(op)
+                | Self::Ugt(op)
+                | Self::Ilt(op)
+                | Self::Ile(op)
+                | Self::Ule(op)
+                | Self::Ult(op)
+                => operands(&[op])
+        }
+        bytes
+    }
+
+    /// decode an instruction, the padding and the unused operand bytes are ignored
+    pub fn from_bytes(bytes : &[u8; INSTRUCTION_SIZE]) -> Option<Self> {
+        let op = operand(bytes, 0);
+        Some(match Opcodes::from(bytes[3]) {
+            Opcodes::Nop => Self::Nop(),
+            Opcodes::Pop => Self::Pop(op),
+            Opcodes::Dup => Self::Dup(op),
+            Opcodes::Swap => Self::Swap(op),
+            Opcodes::Br => Self::Br(op),
+            Opcodes::Briz => Self::Briz(op, operand(bytes, 1)),
+            Opcodes::Call => Self::Call(op),
+            Opcodes::Varref => Self::Varref(),
+            Opcodes::Iconst => Self::Iconst(op, i64::from_le_bytes(bytes[8..16].try_into().unwrap())),
+            Opcodes::Iload => Self::Iload(op),
+            Opcodes::Istore => Self::Istore(op),
+            Opcodes::Iret => Self::Iret(op),
+            Opcodes::Inot => Self::Inot(op),
+            Opcodes::Ior => Self::Ior(op),
+            Opcodes::Iand => Self::Iand(op),
+            Opcodes::Ixor => Self::Ixor(op),
+            Opcodes::Ilshr => Self::Ilshr(op),
+            Opcodes::Iashr => Self::Iashr(op),
+            Opcodes::Ishl => Self::Ishl(op),
+            Opcodes::Ineg => Self::Ineg(op),
+            Opcodes::Iadd => Self::Iadd(op),
+            Opcodes::Isub => Self::Isub(op),
+            Opcodes::Imul => Self::Imul(op),
+            Opcodes::Idiv => Self::Idiv(op),
+            Opcodes::Udiv => Self::Udiv(op),
+            Opcodes::Irem => Self::Irem(op),
+            Opcodes::Urem => Self::Urem(op),
+            Opcodes::Ieq => Self::Ieq(op),
+            Opcodes::Ine => Self::Ine(op),
+            Opcodes::Ige => Self::Ige(op),
+            Opcodes::Igt => Self::Igt(op),
+            Opcodes::Uge => Self::Uge(op),
+            Opcodes::Ugt => Self::Ugt(op),
+            Opcodes::Ilt => Self::Ilt(op),
+            Opcodes::Ile => Self::Ile(op),
+            Opcodes::Ule => Self::Ule(op),
+            Opcodes::Ult => Self::Ult(op),
+            Opcodes::Iconv => Self::Iconv(op, operand(bytes, 1)),
+            Opcodes::Uconv => Self::Uconv(op, operand(bytes, 1)),
+            _ => return None
+        })
+    }
+}
@@ -0,0 +1,352 @@
+//! upgrade of the objects written before the current spec (up to jolang 0.3.0-beta) :
+//! 8-bytes table entries, 32-bytes instructions, externs as an `(argc, returns)` pair and blocks with arguments.
+//! the sizes of the old instructions are in bits and implied by the values on the stack,
+//! they are found by following the widths of the stack of each block
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Context, Result};
+use super::instructions::{operand::Size, Instruction};
+use super::{reader::Table, IrExternalFn, IrObject, Signature};
+
+/// the header and the 2 tables
+const LEGACY_HEADER_SIZE : usize = 7 + 4 * 8;
+pub const LEGACY_INSTRUCTION_SIZE : usize = 32;
+
+/// opcodes of the 32-bytes instructions
+mod opcodes {
+    pub const RET : u8 = 0x00;
+    pub const RETI : u8 = 0x01;
+    pub const ICONST : u8 = 0x02;
+    pub const BR : u8 = 0x03;
+    pub const DUP : u8 = 0x04;
+    pub const DUPX : u8 = 0x05;
+    pub const ICAST : u8 = 0x06;
+    pub const SWAP : u8 = 0x07;
+    pub const CALL : u8 = 0x08;
+    pub const NEG : u8 = 0x09;
+    pub const ADD : u8 = 0x0A;
+    pub const SUB : u8 = 0x0B;
+    pub const MUL : u8 = 0x0C;
+    pub const DIV : u8 = 0x0D;
+    pub const EQ : u8 = 0x0E;
+    pub const NE : u8 = 0x0F;
+    pub const GT : u8 = 0x10;
+    pub const GE : u8 = 0x11;
+    pub const LE : u8 = 0x12;
+    pub const LT : u8 = 0x13;
+    pub const LSH : u8 = 0x14;
+    pub const RSH : u8 = 0x15;
+    pub const BRIZ : u8 = 0x16;
+}
+
+/// the size in bytes of an integer of the old width in bits
+fn size_of(bits : u64) -> Result<Size> {
+    match bits {
+        8 | 16 | 32 | 64 => Ok(bits as Size / 8),
+        _ => bail!("unsupported integer type : i{}", bits)
+    }
+}
+
+struct LegacyBlock<'a> {
+    args : Vec<u64>,
+    instructions : &'a [u8]
+}
+
+struct Upgrade {
+    ir : IrObject,
+    /// local variables used to reach the values below the top of the stack, by stack position and size
+    temps : HashMap<(usize, Size), u32>,
+    /// the widths of the stack of the current block in bytes
+    stack : Vec<Size>
+}
+
+impl Upgrade {
+    fn pop(&mut self) -> Result<Size> {
+        self.stack.pop().ok_or_else(|| anyhow!("the stack is empty"))
+    }
+
+    fn top(&self) -> Result<Size> {
+        self.stack.last().copied().ok_or_else(|| anyhow!("the stack is empty"))
+    }
+
+    fn temp(&mut self, pos : usize, size : Size) -> u32 {
+        match self.temps.get(&(pos, size)) {
+            Some(id) => *id,
+            None => {
+                let id = self.ir.add_var(size as u8);
+                self.temps.insert((pos, size), id);
+                id
+            }
+        }
+    }
+
+    /// copy the value at the position from the bottom of the stack by storing the values above it in variables
+    fn dupx(&mut self, pos : usize, out : &mut Vec<Instruction>) -> Result<()> {
+        let depth = self.stack.len();
+        if pos >= depth {
+            bail!("dupx {} with only {} values on the stack", pos, depth)
+        }
+        let size = self.stack[pos];
+        if pos == depth - 1 {
+            out.push(Instruction::Dup(size));
+            self.stack.push(size);
+            return Ok(())
+        }
+        let above : Vec<(u32, Size)> = (pos + 1..depth)
+            .map(|j| (self.temp(j, self.stack[j]), self.stack[j]))
+            .collect();
+        let copy = self.temp(depth, size);
+        let store = |out : &mut Vec<Instruction>, id : u32, size : Size| {
+            out.extend([Instruction::Iconst(4, id as i64), Instruction::Varref(), Instruction::Swap(size), Instruction::Istore(size)]);
+        };
+        let load = |out : &mut Vec<Instruction>, id : u32, size : Size| {
+            out.extend([Instruction::Iconst(4, id as i64), Instruction::Varref(), Instruction::Iload(size)]);
+        };
+        for (id, size) in above.iter().rev() {
+            store(out, *id, *size);
+        }
+        out.push(Instruction::Dup(size));
+        store(out, copy, size);
+        for (id, size) in above.iter() {
+            load(out, *id, *size);
+        }
+        load(out, copy, size);
+        self.stack.push(size);
+        Ok(())
+    }
+
+    fn instruction(&mut self, bytes : &[u8], out : &mut Vec<Instruction>) -> Result<()> {
+        let op1 = u64::from_le_bytes(bytes[8..16].try_into()?);
+        let op2 = u64::from_le_bytes(bytes[16..24].try_into()?);
+        let id = |op : u64| u32::try_from(op).map_err(|_| anyhow!("invalid id : {}", op));
+        match bytes[7] {
+            opcodes::RET => out.extend([Instruction::Iconst(8, 0), Instruction::Iret(8)]),
+            opcodes::RETI => {
+                let size = self.pop()?;
+                out.push(Instruction::Iret(size));
+            },
+            opcodes::ICONST => {
+                let size = size_of(op1)?;
+                // the value is 16 bytes long but the current ir only has 8-bytes immediates
+                out.push(Instruction::Iconst(size, op2 as i64));
+                self.stack.push(size);
+            },
+            opcodes::BR => out.push(Instruction::Br(id(op1)?)),
+            opcodes::BRIZ => {
+                self.pop()?;
+                out.push(Instruction::Briz(id(op1)?, id(op2)?));
+            },
+            opcodes::DUP => {
+                let size = self.top()?;
+                out.push(Instruction::Dup(size));
+                self.stack.push(size);
+            },
+            opcodes::DUPX => self.dupx(op1 as usize, out)?,
+            opcodes::SWAP => {
+                let size1 = self.pop()?;
+                let size2 = self.pop()?;
+                out.push(Instruction::Swap(size1));
+                self.stack.extend([size1, size2]);
+            },
+            opcodes::ICAST => {
+                let from = self.pop()?;
+                let to = size_of(op1)?;
+                if from != to {
+                    out.push(Instruction::Iconv(from, to));
+                }
+                self.stack.push(to);
+            },
+            opcodes::CALL => {
+                let func = self.ir.ext_fn.get(op1 as usize)
+                    .ok_or_else(|| anyhow!("call of the unknown function : {}", op1))?;
+                let returns = func.sig.ret != "void";
+                for _ in 0..func.sig.args.len() {
+                    self.pop()?;
+                }
+                out.push(Instruction::Call(id(op1)?));
+                if returns {
+                    self.stack.push(8);
+                }
+            },
+            opcodes::NEG => out.push(Instruction::Ineg(self.top()?)),
+            opcode @ opcodes::ADD..=opcodes::RSH => {
+                let size = self.pop()?;
+                self.pop()?;
+                let (instruction, comparison) = match opcode {
+                    opcodes::ADD => (Instruction::Iadd(size), false),
+                    opcodes::SUB => (Instruction::Isub(size), false),
+                    opcodes::MUL => (Instruction::Imul(size), false),
+                    opcodes::DIV => (Instruction::Idiv(size), false),
+                    opcodes::EQ => (Instruction::Ieq(size), true),
+                    opcodes::NE => (Instruction::Ine(size), true),
+                    opcodes::GT => (Instruction::Igt(size), true),
+                    opcodes::GE => (Instruction::Ige(size), true),
+                    opcodes::LE => (Instruction::Ile(size), true),
+                    opcodes::LT => (Instruction::Ilt(size), true),
+                    opcodes::LSH => (Instruction::Ishl(size), false),
+                    _ => (Instruction::Ilshr(size), false)
+                };
+                out.push(instruction);
+                // the old comparisons gave a result of the size of their operands
+                if comparison && size != 1 {
+                    out.push(Instruction::Uconv(1, size));
+                }
+                self.stack.push(size);
+            },
+            opcode => bail!("bad opcode {}", opcode)
+        }
+        Ok(())
+    }
+}
+
+/// read an object of the legacy layout and upgrade it to the current ir
+pub fn read_legacy(object : &[u8]) -> Result<IrObject> {
+    let header = Table::new(object, "header", 0, LEGACY_HEADER_SIZE)?;
+    let ext_fn_count = header.u64(7)?;
+    let ext_fn_pos = header.u64(15)?;
+    let block_count = header.u64(23)?;
+    let block_pos = header.u64(31)?;
+    let mut ir = IrObject::new();
+    let externs = Table::with_entries(object, "external functions table", ext_fn_pos, ext_fn_count, 6)?;
+    let mut pos = 0;
+    for _ in 0..ext_fn_count {
+        let (name, next) = externs.string(pos)?;
+        let info = externs.slice(next, 2)?;
+        let ret = if info[1] != 0 { "i64" } else { "void" };
+        let sig = Signature::new(ret.to_string(), vec!["i64".to_string(); info[0] as usize]);
+        ir.ext_fn.push(IrExternalFn::new(name, sig));
+        pos = next + 2;
+    }
+    let table = Table::with_entries(object, "blocks table", block_pos, block_count, 24)?;
+    let whole = Table { name : "object".to_string(), data : object, offset : 0 };
+    let mut blocks = vec![];
+    let mut pos = 0;
+    for i in 0..block_count {
+        let size = table.u64(pos)?;
+        let argc = table.u64(pos + 8)?;
+        let args = table.slice(pos + 16, argc.saturating_mul(8))?
+            .chunks_exact(8)
+            .map(|a| u64::from_le_bytes(a.try_into().unwrap()))
+            .collect();
+        pos += 16 + 8 * argc;
+        let start = table.u64(pos)?;
+        pos += 8;
+        let instructions = start.checked_add(size.saturating_mul(LEGACY_INSTRUCTION_SIZE))
+            .and_then(|end| whole.data.get(start..end))
+            .ok_or_else(|| anyhow!("the {} instructions of the block {} at offset {} are outside of the object ({} bytes)", size, i, start, object.len()))?;
+        blocks.push(LegacyBlock { args, instructions });
+    }
+    let mut upgrade = Upgrade {
+        ir,
+        temps : HashMap::new(),
+        stack : vec![]
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        upgrade.stack = block.args.iter().map(|a| size_of(*a)).collect::<Result<_>>()
+            .with_context(|| format!("in the arguments of the block {}", i))?;
+        let mut instructions = vec![];
+        for (j, bytes) in block.instructions.chunks_exact(LEGACY_INSTRUCTION_SIZE).enumerate() {
+            upgrade.instruction(bytes, &mut instructions)
+                .with_context(|| format!("cannot upgrade the instruction {} of B{}", j, i))?;
+        }
+        let id = upgrade.ir.append_block();
+        for instruction in instructions {
+            upgrade.ir.get_block_mut(id).instructions.insert_last(instruction);
+        }
+    }
+    Ok(upgrade.ir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::reader::{read, read_layout, Layout};
+    use std::io::Cursor;
+
+    fn instruction(opcode : u8, op1 : u64, op2 : u64) -> [u8; LEGACY_INSTRUCTION_SIZE] {
+        let mut bytes = [0; LEGACY_INSTRUCTION_SIZE];
+        bytes[7] = opcode;
+        bytes[8..16].copy_from_slice(&op1.to_le_bytes());
+        bytes[16..24].copy_from_slice(&op2.to_le_bytes());
+        bytes
+    }
+
+    /// an object as the old writer wrote it
+    fn legacy_object(version : [u8; 3], externs : &[(&str, u8, bool)], blocks : &[(Vec<u64>, Vec<[u8; LEGACY_INSTRUCTION_SIZE]>)]) -> Vec<u8> {
+        let mut object = b"\0JOO".to_vec();
+        object.extend(version);
+        let ext_size : usize = externs.iter().map(|e| 6 + e.0.len()).sum();
+        let block_pos = LEGACY_HEADER_SIZE + ext_size;
+        for value in [externs.len(), LEGACY_HEADER_SIZE, blocks.len(), block_pos] {
+            object.extend((value as u64).to_le_bytes());
+        }
+        for (name, argc, returns) in externs {
+            object.extend((name.len() as u32).to_le_bytes());
+            object.extend(name.as_bytes());
+            object.extend([*argc, *returns as u8]);
+        }
+        let mut instr_pos = block_pos + blocks.iter().map(|b| 24 + 8 * b.0.len()).sum::<usize>();
+        for (args, instructions) in blocks {
+            object.extend((instructions.len() as u64).to_le_bytes());
+            object.extend((args.len() as u64).to_le_bytes());
+            for arg in args {
+                object.extend(arg.to_le_bytes());
+            }
+            object.extend((instr_pos as u64).to_le_bytes());
+            instr_pos += LEGACY_INSTRUCTION_SIZE * instructions.len();
+        }
+        for (_, instructions) in blocks {
+            for i in instructions {
+                object.extend(i);
+            }
+        }
+        object
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let object = legacy_object([0, 2, 1], &[("print", 1, false), ("input", 0, true)], &[
+            (vec![], vec![
+                instruction(opcodes::ICONST, 64, 7),
+                instruction(opcodes::ICONST, 32, 2),
+                instruction(opcodes::ICAST, 64, 0),
+                instruction(opcodes::ADD, 0, 0),
+                instruction(opcodes::CALL, 0, 0),
+                instruction(opcodes::CALL, 1, 0),
+                instruction(opcodes::ICONST, 64, 2),
+                instruction(opcodes::DUPX, 0, 0),
+                instruction(opcodes::LT, 0, 0),
+                instruction(opcodes::BRIZ, 1, 1),
+            ]),
+            (vec![64], vec![
+                instruction(opcodes::DUP, 0, 0),
+                instruction(opcodes::RETI, 0, 0),
+            ]),
+        ]);
+        assert_eq!(read_layout(&mut Cursor::new(object.clone())).unwrap(), (Layout::V1, [0, 2, 1]));
+        let ir = read(&mut Cursor::new(object)).unwrap();
+        assert_eq!(format!("{:?}", ir), "extern fn print (i64) -> void;\nextern fn input () -> i64;\nfn main () -> i64 {\n\tvar 0 8\n\tvar 1 8\nB0 : \n\
+            \ticonst 8 7\n\ticonst 4 2\n\ticonv 4 8\n\tiadd 8\n\tcall 0\n\tcall 1\n\ticonst 8 2\n\
+            \ticonst 4 0\n\tvarref\n\tswap 8\n\tistore 8\n\tdup 8\n\ticonst 4 1\n\tvarref\n\tswap 8\n\tistore 8\n\
+            \ticonst 4 0\n\tvarref\n\tiload 8\n\ticonst 4 1\n\tvarref\n\tiload 8\n\
+            \tilt 8\n\tuconv 1 8\n\tbriz 1 1\nB1 : \n\tdup 8\n\tiret 8\n}");
+    }
+
+    #[test]
+    fn test_upgrade_errors() {
+        let read_blocks = |instructions : Vec<[u8; LEGACY_INSTRUCTION_SIZE]>| {
+            let object = legacy_object([0, 3, 0], &[], &[(vec![], instructions)]);
+            format!("{:#}", read(&mut Cursor::new(object)).map(|_| ()).unwrap_err())
+        };
+        assert_eq!(read_blocks(vec![instruction(opcodes::ADD, 0, 0)]),
+            "cannot upgrade the object: cannot upgrade the instruction 0 of B0: the stack is empty");
+        assert_eq!(read_blocks(vec![instruction(opcodes::ICONST, 128, 0)]),
+            "cannot upgrade the object: cannot upgrade the instruction 0 of B0: unsupported integer type : i128");
+        assert_eq!(read_blocks(vec![instruction(opcodes::ICONST, 8, 0), instruction(opcodes::DUPX, 1, 0)]),
+            "cannot upgrade the object: cannot upgrade the instruction 1 of B0: dupx 1 with only 1 values on the stack");
+        assert_eq!(read_blocks(vec![instruction(0x40, 0, 0)]),
+            "cannot upgrade the object: cannot upgrade the instruction 0 of B0: bad opcode 64");
+        let mut truncated = legacy_object([0, 3, 0], &[], &[(vec![], vec![instruction(opcodes::RET, 0, 0)])]);
+        truncated.pop();
+        assert!(format!("{:#}", read(&mut Cursor::new(truncated)).map(|_| ()).unwrap_err()).contains("the 1 instructions of the block 0 at offset"));
+    }
+}
@@ -5,21 +5,45 @@ pub mod instructions;
 use std::cell::{Ref, RefCell, RefMut};
 use crate::ffi::JolangExtern;
 pub mod printer;
+pub mod assembler;
 pub mod block;
 pub mod writer;
 pub mod reader;
+pub mod legacy;
 pub mod signature;
+pub mod section;
+pub mod verifier;
+pub mod passes;
+pub mod dot;
+#[cfg(feature = "serde")]
+pub mod serialize;
 use block::Block;
+use section::{RawSection, SourceLocation};
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IrExternalFn {
     pub name : String,
     pub sig : Signature
 }
 
+/// the optional sections can be left out of the serialized objects
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IrObject {
     pub ext_fn : Vec<IrExternalFn>,
     pub blocks : Vec<Block>,
-    pub local_vars : Vec<u8>
+    pub local_vars : Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_map : Vec<SourceLocation>,
+    /// string data, reserved : nothing references it yet and the compiler leaves it empty
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub strings : Vec<u8>,
+    /// information about the build : compiler version, source hash, build flags...
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata : Vec<(String, String)>,
+    /// user-defined sections and sections of newer versions
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sections : Vec<RawSection>
 }
 
 impl IrExternalFn {
@@ -36,7 +60,11 @@ impl IrObject {
         Self{
             ext_fn : Vec::new(),
             blocks : Vec::new(),
-            local_vars : Vec::new()
+            local_vars : Vec::new(),
+            source_map : Vec::new(),
+            strings : Vec::new(),
+            metadata : Vec::new(),
+            sections : Vec::new()
         }
     }
 
@@ -58,6 +86,12 @@ impl IrObject {
         (self.ext_fn.len() -1) as FnId
     }
 
+    pub fn get_metadata(&self, key : &str) -> Option<&str> {
+        self.metadata.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
     pub fn add_var(&mut self, size : u8) -> u32 {
         let tmp = self.local_vars.len();
         self.local_vars.push(size);
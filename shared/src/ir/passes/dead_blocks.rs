@@ -0,0 +1,24 @@
+//! removal of the blocks that can't be reached from the entry block
+use super::{retain_blocks, successors, IrObject};
+
+pub fn run(object : &mut IrObject) -> bool {
+    if object.blocks.is_empty() {
+        return false
+    }
+    let mut reached = vec![false; object.blocks.len()];
+    reached[0] = true;
+    let mut queue = vec![0];
+    while let Some(id) = queue.pop() {
+        for target in successors(object, id) {
+            if let Some(r) = reached.get_mut(target as usize).filter(|r| !**r) {
+                *r = true;
+                queue.push(target as usize);
+            }
+        }
+    }
+    if reached.iter().all(|r| *r) {
+        return false
+    }
+    retain_blocks(object, &reached);
+    true
+}
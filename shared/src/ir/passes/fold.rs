@@ -0,0 +1,94 @@
+//! constant folding, the results are the ones of the interpreter.
+//! the operations that fail or that some runtimes leave undefined (division by zero, overflowing division and shift counts) are kept
+use crate::ir::instructions::{operand::{bits, sext, zext, Imm, Size}, Instruction};
+use super::{instructions, set_instructions, IrObject};
+
+/// the constant pushed by an operation on two constants of the size
+fn binary(operation : Instruction, size : Size, a : Imm, b : Imm) -> Option<(Size, Imm)> {
+    let shift = (0..bits(size) as Imm).contains(&b).then_some(b as u32);
+    // the smallest value divided by -1 overflows
+    let overflow = b == -1 && a == Imm::MIN >> (64 - bits(size));
+    let unsigned = (zext(a, size), zext(b, size));
+    let value = match operation {
+        Instruction::Ior(s) if s == size => a | b,
+        Instruction::Iand(s) if s == size => a & b,
+        Instruction::Ixor(s) if s == size => a ^ b,
+        Instruction::Ilshr(s) if s == size => (unsigned.0 >> shift?) as Imm,
+        Instruction::Iashr(s) if s == size => a >> shift?,
+        Instruction::Ishl(s) if s == size => a << shift?,
+        Instruction::Iadd(s) if s == size => a.wrapping_add(b),
+        Instruction::Isub(s) if s == size => a.wrapping_sub(b),
+        Instruction::Imul(s) if s == size => a.wrapping_mul(b),
+        Instruction::Idiv(s) if s == size => a.checked_div(b).filter(|_| !overflow)?,
+        Instruction::Irem(s) if s == size => a.checked_rem(b).filter(|_| !overflow)?,
+        Instruction::Udiv(s) if s == size => unsigned.0.checked_div(unsigned.1)? as Imm,
+        Instruction::Urem(s) if s == size => unsigned.0.checked_rem(unsigned.1)? as Imm,
+        Instruction::Ieq(s) if s == size => return Some((1, (a == b) as Imm)),
+        Instruction::Ine(s) if s == size => return Some((1, (a != b) as Imm)),
+        Instruction::Ige(s) if s == size => return Some((1, (a >= b) as Imm)),
+        Instruction::Igt(s) if s == size => return Some((1, (a > b) as Imm)),
+        Instruction::Ile(s) if s == size => return Some((1, (a <= b) as Imm)),
+        Instruction::Ilt(s) if s == size => return Some((1, (a < b) as Imm)),
+        Instruction::Uge(s) if s == size => return Some((1, (unsigned.0 >= unsigned.1) as Imm)),
+        Instruction::Ugt(s) if s == size => return Some((1, (unsigned.0 > unsigned.1) as Imm)),
+        Instruction::Ule(s) if s == size => return Some((1, (unsigned.0 <= unsigned.1) as Imm)),
+        Instruction::Ult(s) if s == size => return Some((1, (unsigned.0 < unsigned.1) as Imm)),
+        _ => return None
+    };
+    Some((size, sext(value, size)))
+}
+
+/// the instruction that replaces an operation on a constant
+fn unary(operation : Instruction, size : Size, a : Imm) -> Option<Instruction> {
+    Some(match operation {
+        Instruction::Inot(s) if s == size => Instruction::Iconst(size, sext(!a, size)),
+        Instruction::Ineg(s) if s == size => Instruction::Iconst(size, sext(a.wrapping_neg(), size)),
+        Instruction::Iconv(from, to) if from == size => Instruction::Iconst(to, sext(a, to)),
+        Instruction::Uconv(from, to) if from == size => Instruction::Iconst(to, sext(zext(a, from) as Imm, to)),
+        Instruction::Briz(zero, not_zero) => Instruction::Br(if a == 0 { zero } else { not_zero }),
+        _ => return None
+    })
+}
+
+/// fold the end of the instructions, returns true if it changed
+fn fold_last(out : &mut Vec<Instruction>) -> bool {
+    let n = out.len();
+    if n >= 3 {
+        if let [Instruction::Iconst(size, a), Instruction::Iconst(size2, b), operation] = out[n - 3..] {
+            if let Some((size, value)) = binary(operation, size, sext(a, size), sext(b, size)).filter(|_| size == size2) {
+                out.truncate(n - 3);
+                out.push(Instruction::Iconst(size, value));
+                return true
+            }
+        }
+    }
+    if n >= 2 {
+        if let [Instruction::Iconst(size, a), operation] = out[n - 2..] {
+            if let Some(folded) = unary(operation, size, sext(a, size)) {
+                out.truncate(n - 2);
+                out.push(folded);
+                return true
+            }
+        }
+    }
+    false
+}
+
+pub fn run(object : &mut IrObject) -> bool {
+    let mut changed = false;
+    for id in 0..object.blocks.len() {
+        let mut out = vec![];
+        let mut folded = false;
+        for instruction in instructions(object, id) {
+            out.push(instruction);
+            while fold_last(&mut out) {
+                folded = true;
+            }
+        }
+        if folded {
+            set_instructions(object, id, out);
+            changed = true;
+        }
+    }
+    changed
+}
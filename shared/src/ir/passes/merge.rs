@@ -0,0 +1,42 @@
+//! merge of the blocks with their only predecessor when it ends with a br to them
+use crate::ir::instructions::Instruction;
+use super::{instructions, retain_blocks, set_instructions, successors, IrObject};
+
+pub fn run(object : &mut IrObject) -> bool {
+    let count = object.blocks.len();
+    let mut predecessors = vec![0; count];
+    for id in 0..count {
+        for target in successors(object, id) {
+            if let Some(p) = predecessors.get_mut(target as usize) {
+                *p += 1;
+            }
+        }
+    }
+    let mut kept = vec![true; count];
+    for id in 0..count {
+        if !kept[id] {
+            continue
+        }
+        loop {
+            let mut merged = instructions(object, id);
+            let target = match merged.last() {
+                Some(Instruction::Br(target)) => *target as usize,
+                _ => break
+            };
+            // the entry block is also reached from the start of the program
+            if target == 0 || target == id || target >= count || predecessors[target] != 1 {
+                break
+            }
+            merged.pop();
+            merged.extend(instructions(object, target));
+            set_instructions(object, id, merged);
+            set_instructions(object, target, vec![]);
+            kept[target] = false;
+        }
+    }
+    if kept.iter().all(|k| *k) {
+        return false
+    }
+    retain_blocks(object, &kept);
+    true
+}
@@ -0,0 +1,242 @@
+//! optimization passes over the ir, they run between the generator and the writer so every runtime benefits from them.
+//! each pass keeps the stack passed from a block to the next unchanged
+mod fold;
+mod stack_ops;
+mod dead_blocks;
+mod merge;
+mod threading;
+use anyhow::{anyhow, bail, Result};
+use super::instructions::{operand::BlkId, Instruction};
+use super::IrObject;
+
+pub struct Pass {
+    pub name : &'static str,
+    pub description : &'static str,
+    /// returns true if the object changed
+    pub run : fn(&mut IrObject) -> bool
+}
+
+pub const PASSES : [Pass; 5] = [
+    Pass { name : "const-fold", description : "compute the arithmetic on constants and the branches on constant conditions", run : fold::run },
+    Pass { name : "stack-ops", description : "remove the dup, pop and swap sequences that leave the stack unchanged", run : stack_ops::run },
+    Pass { name : "jump-threading", description : "branch directly to the target of the blocks that only branch", run : threading::run },
+    Pass { name : "merge-blocks", description : "merge the blocks with the only block that branches to them", run : merge::run },
+    Pass { name : "dead-blocks", description : "remove the blocks that are never reached", run : dead_blocks::run }
+];
+
+/// the highest optimization level
+pub const MAX_LEVEL : u8 = 2;
+
+/// the rounds of the passes stop when nothing changes or after this count
+const MAX_ROUNDS : usize = 8;
+
+pub fn pass_by_name(name : &str) -> Result<&'static Pass> {
+    PASSES.iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow!("unknown pass : {}, the passes are : {}", name, PASSES.iter().map(|p| p.name).collect::<Vec<_>>().join(", ")))
+}
+
+/// the passes to run on an object and the flags that selected them
+pub struct PassManager {
+    passes : Vec<&'static Pass>,
+    /// run the passes again until they don't change the object
+    repeat : bool,
+    flags : String
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PassManager {
+    /// no optimization
+    pub fn new() -> Self {
+        Self {
+            passes : vec![],
+            repeat : false,
+            flags : "-O0".to_string()
+        }
+    }
+
+    /// the passes of an optimization level (-O0, -O1 or -O2)
+    pub fn for_level(level : u8) -> Result<Self> {
+        let names : &[&str] = match level {
+            0 => &[],
+            1 => &["const-fold", "stack-ops", "dead-blocks"],
+            2 => &["const-fold", "stack-ops", "jump-threading", "merge-blocks", "dead-blocks"],
+            _ => bail!("unknown optimization level : {}, the levels are 0 to {}", level, MAX_LEVEL)
+        };
+        Ok(Self {
+            passes : names.iter().map(|n| pass_by_name(n)).collect::<Result<_>>()?,
+            repeat : level == MAX_LEVEL,
+            flags : format!("-O{}", level)
+        })
+    }
+
+    /// the passes of a comma separated list, they run once in the order of the list
+    pub fn from_list(list : &str) -> Result<Self> {
+        let passes = list.split(',')
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .map(pass_by_name)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            flags : format!("--passes={}", passes.iter().map(|p| p.name).collect::<Vec<_>>().join(",")),
+            passes,
+            repeat : false
+        })
+    }
+
+    pub fn passes(&self) -> impl Iterator<Item = &'static Pass> + '_ {
+        self.passes.iter().copied()
+    }
+
+    /// the command line flags that select the passes, written in the build_flags metadata
+    pub fn flags(&self) -> &str {
+        &self.flags
+    }
+
+    /// run the passes, the source map is dropped if the object changes because the passes don't track the instructions
+    pub fn run(&self, object : &mut IrObject) -> bool {
+        let mut changed = false;
+        for _ in 0..MAX_ROUNDS {
+            let mut round = false;
+            for pass in self.passes.iter() {
+                round |= (pass.run)(object);
+            }
+            changed |= round;
+            if !round || !self.repeat {
+                break;
+            }
+        }
+        if changed {
+            object.source_map.clear();
+        }
+        changed
+    }
+}
+
+fn instructions(object : &IrObject, id : usize) -> Vec<Instruction> {
+    object.blocks[id].instructions.iter().copied().collect()
+}
+
+fn set_instructions(object : &mut IrObject, id : usize, instructions : Vec<Instruction>) {
+    let list = &mut object.blocks[id].instructions;
+    list.clear();
+    for i in instructions {
+        list.insert_last(i);
+    }
+}
+
+fn successors(object : &IrObject, id : usize) -> Vec<BlkId> {
+    match object.blocks[id].instructions.get_last() {
+        Some(Instruction::Br(target)) => vec![*target],
+        Some(Instruction::Briz(target1, target2)) => vec![*target1, *target2],
+        _ => vec![]
+    }
+}
+
+/// change the targets of the branches of every block
+fn map_targets(object : &mut IrObject, map : impl Fn(BlkId) -> BlkId) {
+    for id in 0..object.blocks.len() {
+        let instructions = instructions(object, id).into_iter()
+            .map(|i| match i {
+                Instruction::Br(target) => Instruction::Br(map(target)),
+                Instruction::Briz(target1, target2) => Instruction::Briz(map(target1), map(target2)),
+                i => i
+            })
+            .collect();
+        set_instructions(object, id, instructions);
+    }
+}
+
+/// keep the blocks where keep is true and renumber the others, the removed blocks must not be branch targets
+fn retain_blocks(object : &mut IrObject, keep : &[bool]) {
+    let mut ids = vec![BlkId::MAX; keep.len()];
+    let mut next = 0;
+    for (id, kept) in keep.iter().enumerate() {
+        if *kept {
+            ids[id] = next;
+            next += 1;
+        }
+    }
+    let blocks = std::mem::take(&mut object.blocks);
+    object.blocks = blocks.into_iter()
+        .zip(keep)
+        .filter(|(_, kept)| **kept)
+        .map(|(b, _)| b)
+        .collect();
+    map_targets(object, |target| ids.get(target as usize).copied().unwrap_or(target));
+    object.source_map.retain_mut(|l| match ids.get(l.block as usize) {
+        Some(&id) if id != BlkId::MAX => {
+            l.block = id;
+            true
+        },
+        _ => false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{assembler::assemble, verifier::errors};
+
+    fn optimize(manager : &PassManager, text : &str) -> String {
+        let mut object = assemble(text).unwrap();
+        assert_eq!(errors(&object), vec![]);
+        manager.run(&mut object);
+        assert_eq!(errors(&object), vec![], "{:?}", object);
+        format!("{:?}", object)
+    }
+
+    fn main(body : &str) -> String {
+        format!("extern fn print (i64) -> void;\nfn main () -> i64 {{\n\tvar 0 8\n{}}}", body)
+    }
+
+    #[test]
+    fn test_passes() {
+        let cases = [
+            ("const-fold", "B0 : \n\ticonst 8 2\n\ticonst 8 3\n\timul 8\n\ticonst 8 1\n\tisub 8\n\tineg 8\n\tiret 8\n",
+                "B0 : \n\ticonst 8 -5\n\tiret 8\n"),
+            ("const-fold", "B0 : \n\ticonst 1 127\n\ticonst 1 1\n\tiadd 1\n\ticonv 1 8\n\tiret 8\n",
+                "B0 : \n\ticonst 8 -128\n\tiret 8\n"),
+            ("const-fold", "B0 : \n\ticonst 8 3\n\ticonst 8 2\n\tigt 8\n\tbriz 1 2\nB1 : \n\ticonst 8 0\n\tiret 8\nB2 : \n\ticonst 8 1\n\tiret 8\n",
+                "B0 : \n\tbr 2\nB1 : \n\ticonst 8 0\n\tiret 8\nB2 : \n\ticonst 8 1\n\tiret 8\n"),
+            // the errors of the runtime are kept
+            ("const-fold", "B0 : \n\ticonst 8 1\n\ticonst 8 0\n\tidiv 8\n\tiret 8\n",
+                "B0 : \n\ticonst 8 1\n\ticonst 8 0\n\tidiv 8\n\tiret 8\n"),
+            ("stack-ops", "B0 : \n\ticonst 8 1\n\tdup 8\n\tpop 8\n\tnop\n\ticonst 8 2\n\tswap 8\n\tswap 8\n\tisub 8\n\tdup 8\n\tswap 8\n\tiadd 8\n\ticonst 8 7\n\tpop 8\n\tiret 8\n",
+                "B0 : \n\ticonst 8 1\n\ticonst 8 2\n\tisub 8\n\tdup 8\n\tiadd 8\n\tiret 8\n"),
+            ("dead-blocks", "B0 : \n\tbr 2\nB1 : \n\tbr 1\nB2 : \n\ticonst 8 0\n\tiret 8\n",
+                "B0 : \n\tbr 1\nB1 : \n\ticonst 8 0\n\tiret 8\n"),
+            ("jump-threading", "B0 : \n\ticonst 8 5\n\ticonst 8 0\n\tbriz 1 2\nB1 : \n\tbr 2\nB2 : \n\tbr 3\nB3 : \n\tiret 8\n",
+                "B0 : \n\ticonst 8 5\n\ticonst 8 0\n\tbriz 3 3\nB1 : \n\tbr 3\nB2 : \n\tbr 3\nB3 : \n\tiret 8\n"),
+            // a loop of empty blocks is kept
+            ("jump-threading", "B0 : \n\tbr 1\nB1 : \n\tbr 2\nB2 : \n\tbr 1\n",
+                "B0 : \n\tbr 1\nB1 : \n\tbr 2\nB2 : \n\tbr 1\n"),
+            ("merge-blocks", "B0 : \n\ticonst 8 1\n\tbr 1\nB1 : \n\ticonst 8 2\n\tiadd 8\n\tbr 2\nB2 : \n\tdup 8\n\tbriz 3 2\nB3 : \n\tiret 8\n",
+                "B0 : \n\ticonst 8 1\n\ticonst 8 2\n\tiadd 8\n\tbr 1\nB1 : \n\tdup 8\n\tbriz 2 1\nB2 : \n\tiret 8\n"),
+        ];
+        for (pass, body, expected) in cases {
+            let manager = PassManager::from_list(pass).unwrap();
+            assert_eq!(optimize(&manager, &main(body)), main(expected), "{}", pass);
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        let body = "B0 : \n\ticonst 4 0\n\tvarref\n\ticonst 8 4\n\ticonst 8 6\n\tiadd 8\n\tistore 8\n\ticonst 8 1\n\tbriz 1 2\nB1 : \n\tbr 3\nB2 : \n\tbr 3\nB3 : \n\ticonst 4 0\n\tvarref\n\tiload 8\n\tdup 8\n\tcall 0\n\tiret 8\n";
+        assert_eq!(optimize(&PassManager::for_level(0).unwrap(), &main(body)), main(body));
+        assert_eq!(optimize(&PassManager::for_level(1).unwrap(), &main(body)),
+            main("B0 : \n\ticonst 4 0\n\tvarref\n\ticonst 8 10\n\tistore 8\n\tbr 1\nB1 : \n\tbr 2\nB2 : \n\ticonst 4 0\n\tvarref\n\tiload 8\n\tdup 8\n\tcall 0\n\tiret 8\n"));
+        assert_eq!(optimize(&PassManager::for_level(2).unwrap(), &main(body)),
+            main("B0 : \n\ticonst 4 0\n\tvarref\n\ticonst 8 10\n\tistore 8\n\ticonst 4 0\n\tvarref\n\tiload 8\n\tdup 8\n\tcall 0\n\tiret 8\n"));
+        assert_eq!(PassManager::for_level(2).unwrap().flags(), "-O2");
+        assert_eq!(PassManager::from_list("stack-ops, dead-blocks").unwrap().flags(), "--passes=stack-ops,dead-blocks");
+        assert_eq!(PassManager::for_level(3).err().unwrap().to_string(), "unknown optimization level : 3, the levels are 0 to 2");
+        assert_eq!(PassManager::from_list("inline").err().unwrap().to_string(),
+            "unknown pass : inline, the passes are : const-fold, stack-ops, jump-threading, merge-blocks, dead-blocks");
+    }
+}
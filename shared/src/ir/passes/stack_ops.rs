@@ -0,0 +1,39 @@
+//! removal of the stack operations that cancel each other
+use crate::ir::instructions::Instruction;
+use super::{instructions, set_instructions, IrObject};
+
+/// simplify the end of the instructions, returns true if it changed
+fn simplify_last(out : &mut Vec<Instruction>) -> bool {
+    let n = out.len();
+    match out[n.saturating_sub(2)..] {
+        [.., Instruction::Nop()] => out.truncate(n - 1),
+        // a value pushed and popped
+        [Instruction::Dup(size), Instruction::Pop(popped)]
+            | [Instruction::Iconst(size, _), Instruction::Pop(popped)]
+            if size == popped => out.truncate(n - 2),
+        [Instruction::Swap(_), Instruction::Swap(_)] => out.truncate(n - 2),
+        // the two values are the same
+        [Instruction::Dup(_), Instruction::Swap(_)] => out.truncate(n - 1),
+        _ => return false
+    }
+    true
+}
+
+pub fn run(object : &mut IrObject) -> bool {
+    let mut changed = false;
+    for id in 0..object.blocks.len() {
+        let mut out = vec![];
+        let mut simplified = false;
+        for instruction in instructions(object, id) {
+            out.push(instruction);
+            while simplify_last(&mut out) {
+                simplified = true;
+            }
+        }
+        if simplified {
+            set_instructions(object, id, out);
+            changed = true;
+        }
+    }
+    changed
+}
@@ -0,0 +1,34 @@
+//! jump threading : the branches to a block that only contains a br go to its target
+use crate::ir::instructions::{operand::BlkId, Instruction};
+use super::{instructions, map_targets, successors, IrObject};
+
+pub fn run(object : &mut IrObject) -> bool {
+    let forwards : Vec<Option<BlkId>> = (0..object.blocks.len())
+        .map(|id| match instructions(object, id).as_slice() {
+            [Instruction::Br(target)] => Some(*target),
+            _ => None
+        })
+        .collect();
+    // the final target of a chain of forwarding blocks, the loops of forwarding blocks are kept
+    let resolve = |target : BlkId| {
+        let mut visited = vec![target];
+        let mut current = target;
+        while let Some(Some(next)) = forwards.get(current as usize) {
+            if visited.contains(next) {
+                return target
+            }
+            visited.push(*next);
+            current = *next;
+        }
+        current
+    };
+    let resolved : Vec<BlkId> = (0..object.blocks.len() as BlkId).map(resolve).collect();
+    let threaded = (0..object.blocks.len())
+        .flat_map(|id| successors(object, id))
+        .any(|target| resolved.get(target as usize).is_some_and(|t| *t != target));
+    if !threaded {
+        return false
+    }
+    map_targets(object, |target| resolved.get(target as usize).copied().unwrap_or(target));
+    true
+}
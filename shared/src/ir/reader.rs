@@ -1,121 +1,534 @@
-use crate::{ir::{block::Block, instructions::{Opcodes, Instruction}}, VERSION, VERSION_STR};
-use super::IrObject;
-use std::{cell::RefCell, io::{Read, Seek, SeekFrom}};
-use anyhow::{anyhow, Context, Ok, Result};
-use index_list::IndexList;
+use crate::{ir::{block::Block, instructions::{Instruction, INSTRUCTION_SIZE}}, VERSION, VERSION_STR};
+use super::{legacy::read_legacy, writer::{checksum, CHECKSUM_POS, HEADER_SIZE, MAGIC}, IrExternalFn, IrObject};
+use super::section::{self, section_name, tag_to_string, RawSection, SectionEntry, SourceLocation, ENTRY_SIZE, SOURCE_LOCATION_SIZE};
+use std::io::{Read, Seek, SeekFrom};
+use anyhow::{anyhow, bail, Context, Result};
 
+/// the first version that writes the layout 2, the objects of the previous versions use the layout 1
+pub const V2_VERSION : (u8, u8) = (0, 4);
+
+/// a part of the object, the errors name it
+pub(super) struct Table<'a> {
+    pub name : String,
+    pub data : &'a [u8],
+    /// offset of the table in the object
+    pub offset : usize
+}
+
+impl<'a> Table<'a> {
+    /// the table at the offset, its length must be in the object
+    pub fn new(object : &'a [u8], name : impl Into<String>, offset : usize, len : usize) -> Result<Self> {
+        let name = name.into();
+        let data = offset.checked_add(len)
+            .and_then(|end| object.get(offset..end))
+            .ok_or_else(|| anyhow!("the {} ends at {} but the object is only {} bytes", name, offset.saturating_add(len), object.len()))?;
+        Ok(Self { name, data, offset })
+    }
+
+    /// a table with count entries of at least min_size bytes
+    pub fn with_entries(object : &'a [u8], name : impl Into<String>, offset : usize, count : usize, min_size : usize) -> Result<Self> {
+        let name = name.into();
+        let len = count.checked_mul(min_size)
+            .ok_or_else(|| anyhow!("the {} has too many entries : {}", name, count))?;
+        if offset.checked_add(len).is_none_or(|end| end > object.len()) {
+            bail!("the {} has {} entries at offset {} but the object is only {} bytes", name, count, offset, object.len())
+        }
+        // the entries may be larger, they are checked when they are read
+        Ok(Self { data : &object[offset..], name, offset })
+    }
+
+    pub fn slice(&self, start : usize, len : usize) -> Result<&'a [u8]> {
+        start.checked_add(len)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or_else(|| anyhow!("the {} ends in the middle of an entry (offset {}, {} bytes)", self.name, self.offset.saturating_add(start), len))
+    }
+
+    pub fn u32(&self, pos : usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.slice(pos, 4)?.try_into()?))
+    }
+
+    pub fn u64(&self, pos : usize) -> Result<usize> {
+        let value = u64::from_le_bytes(self.slice(pos, 8)?.try_into()?);
+        usize::try_from(value).map_err(|_| anyhow!("invalid count or offset in the {} : {}", self.name, value))
+    }
+
+    /// a string prefixed by its 4-bytes size, returns the string and the position after it
+    pub fn string(&self, pos : usize) -> Result<(String, usize)> {
+        let size = self.u32(pos)? as usize;
+        let string = String::from_utf8(self.slice(pos + 4, size)?.to_vec())
+            .map_err(|_| anyhow!("the {} has a string that is not valid utf-8 at offset {}", self.name, self.offset + pos + 4))?;
+        Ok((string, pos + 4 + size))
+    }
+
+    /// the strings until the end of the table
+    pub fn strings(&self) -> Result<Vec<String>> {
+        let mut strings = vec![];
+        let mut pos = 0;
+        while pos < self.data.len() {
+            let (string, next) = self.string(pos)?;
+            strings.push(string);
+            pos = next;
+        }
+        Ok(strings)
+    }
+
+    /// the entries of a table made of entries of the same size
+    pub fn chunks(&self, size : usize) -> Result<std::slice::ChunksExact<'a, u8>> {
+        if !self.data.len().is_multiple_of(size) {
+            bail!("the length of the {} ({} bytes) is not a multiple of {}", self.name, self.data.len(), size)
+        }
+        Ok(self.data.chunks_exact(size))
+    }
+}
+
+fn u32_at(object : &[u8], pos : usize) -> Result<u32> {
+    let bytes = object.get(pos..pos + 4)
+        .ok_or_else(|| anyhow!("the object is too small for its header ({} bytes)", object.len()))?;
+    Ok(u32::from_le_bytes(bytes.try_into()?))
+}
+
+/// the layouts of the objects, the version in the header tells which one an object uses
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Layout {
+    /// 8-bytes table entries and 32-bytes instructions, written up to jolang 0.3.0-beta
+    V1,
+    /// the layout of bytecode_spec.md, written since jolang 0.4.0
+    V2
+}
+
+impl Layout {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::V1 => "8-bytes table entries, 32-bytes instructions (upgraded when read)",
+            Self::V2 => "section directory, 16-bytes instructions, checksum"
+        }
+    }
+}
+
+/// find the layout and the version of an object
+pub fn layout(object : &[u8]) -> Result<(Layout, [u8; 3])> {
+    if object.len() < 7 || object[..4] != MAGIC[..] {
+        return Err(anyhow!("bad header"))
+    }
+    let version : [u8; 3] = object[4..7].try_into()?;
+    let (major, minor) = (version[0] as u64, version[1] as u64);
+    if major > VERSION.major || (major == VERSION.major && minor > VERSION.minor) {
+        return Err(anyhow!("the object was written by jolang {}.{}.{} which is newer than this version ({}), update jolang to read it",
+            version[0], version[1], version[2], VERSION_STR))
+    }
+    if (version[0], version[1]) < V2_VERSION {
+        Ok((Layout::V1, version))
+    }else {
+        Ok((Layout::V2, version))
+    }
+}
+
+fn read_all<T>(input : &mut T) -> Result<Vec<u8>>
+where T: Read + Seek {
+    let mut object = Vec::new();
+    input.seek(SeekFrom::Start(0))?;
+    input.read_to_end(&mut object)?;
+    Ok(object)
+}
+
+/// the layout and the version of an object
+pub fn read_layout<T>(input : &mut T) -> Result<(Layout, [u8; 3])>
+where T: Read + Seek {
+    layout(&read_all(input)?)
+}
+
+/// the section directory of an object of the layout 2, the sections are in the object
+pub fn directory(object : &[u8]) -> Result<Vec<SectionEntry>> {
+    let layout = layout(object)?.0;
+    if layout != Layout::V2 {
+        bail!("the objects of the layout {:?} have no section directory", layout)
+    }
+    if object.len() < HEADER_SIZE {
+        bail!("the object is too small for its header ({} bytes)", object.len())
+    }
+    let count = u32_at(object, 7)? as usize;
+    let table = Table::with_entries(object, "section directory", HEADER_SIZE, count, ENTRY_SIZE)?;
+    let mut entries = vec![];
+    for i in 0..count {
+        let pos = ENTRY_SIZE * i;
+        let entry = SectionEntry {
+            tag : table.slice(pos, 4)?.try_into()?,
+            offset : table.u32(pos + 4)?,
+            length : table.u32(pos + 8)?,
+            flags : table.u32(pos + 12)?
+        };
+        let name = section_name(&entry.tag).unwrap_or("user-defined");
+        Table::new(object, format!("section {} ({})", tag_to_string(&entry.tag), name), entry.offset as usize, entry.length as usize)?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// the section directory of an object
+pub fn read_directory<T>(input : &mut T) -> Result<Vec<SectionEntry>>
+where T: Read + Seek {
+    directory(&read_all(input)?)
+}
+
+/// read an object of any layout
 pub fn read<T>(input : &mut T) -> Result<IrObject>
 where T: Read + Seek {
-    let mut header : [u8;4] = [0;4];
-    input.read_exact(&mut header[..])?;
-    if header[..] != "\0JOO".as_bytes()[..] {
-        return Err(anyhow!("bad header"))
+    let object = read_all(input)?;
+    match layout(&object)?.0 {
+        Layout::V1 => read_legacy(&object).context("cannot upgrade the object"),
+        Layout::V2 => read_v2(&object)
     }
-    let mut version : [u8;3] = [0;3];
-    input.read_exact(&mut version[..])?;
-    if version[0] as u64 != VERSION.major || version[1] as u64 > VERSION.minor {
-        return Err(anyhow!("unsupported version : {}.{}.{}", version[0], version[1], version[2]))
-            .context(format!("the program version is {}", VERSION_STR))
-    }
-    let mut tables : [u8;32] = [0;32];
-    input.read_exact(&mut tables[..])?;
-    let ext_fn_count = u64::from_le_bytes(tables[0..8].try_into()?);
-    let ext_fn_pos = u64::from_le_bytes(tables[8..16].try_into()?);
-    let block_count = u64::from_le_bytes(tables[16..24].try_into()?);
-    let block_pos = u64::from_le_bytes(tables[24..32].try_into()?);
-    let mut object = IrObject::new();
-    object.ext_fn.reserve(ext_fn_count as usize);
-    object.blocks.reserve(block_count as usize);
-    input.seek(SeekFrom::Start(ext_fn_pos))?;
-    for _ in 0..ext_fn_count {
-        let mut buffer : [u8;4] = [0;4];
-        input.read_exact(&mut buffer)?;
-        let name_size = u32::from_le_bytes(buffer);
-        let mut name_buffer = vec![0;name_size as usize];
-        input.read_exact(&mut name_buffer[..])?;
-        let name = String::from_utf8(name_buffer)?;
-        let mut info_buf = [0;2];
-        input.read_exact(&mut info_buf)?;
-        let argc = info_buf[0];
-        let returns = info_buf[1] != 0;
-        object.ext_fn.push((name, argc, returns));
-    }
-    input.seek(SeekFrom::Start(block_pos))?;
-    for _ in 0..block_count {
-        let mut buffer : [u8;16] = [0;16];
-        input.read_exact(&mut buffer)?;
-        let block_size = u64::from_le_bytes(buffer[..8].try_into()?);
-        let block_argc = u64::from_le_bytes(buffer[8..16].try_into()?);
-        let mut block_sizes = Vec::new();
-        block_sizes.reserve(block_argc as usize);
-        for _ in 0..block_argc {
-            let mut buffer : [u8;8] = [0;8];
-            input.read_exact(&mut buffer)?;
-            block_sizes.push(u64::from_le_bytes(buffer));
+}
+
+/// read the instructions of a block from the instructions table
+fn read_block(instructions : &Table, start : usize, count : usize, id : usize) -> Result<Block> {
+    let len = count.checked_mul(INSTRUCTION_SIZE)
+        .ok_or_else(|| anyhow!("the block {} has too many instructions : {}", id, count))?;
+    let data = start.checked_add(len)
+        .and_then(|end| instructions.data.get(start..end))
+        .ok_or_else(|| anyhow!("the {} instructions of the block {} at offset {} are outside of the {} ({} bytes)",
+            count, id, start, instructions.name, instructions.data.len()))?;
+    let mut block = Block::new();
+    for chunk in data.chunks_exact(INSTRUCTION_SIZE) {
+        let instruction = Instruction::from_bytes(chunk.try_into()?)
+            .ok_or_else(|| anyhow!("bad opcode {} in the block {}", chunk[3], id))?;
+        block.instructions.insert_last(instruction);
+    }
+    Ok(block)
+}
+
+fn read_v2(object : &[u8]) -> Result<IrObject> {
+    let entries = directory(object)?;
+    if object[CHECKSUM_POS..HEADER_SIZE] != checksum(object) {
+        bail!("the checksum of the object doesn't match its content, the object is damaged")
+    }
+    let mut ir = IrObject::new();
+    let mut known : Vec<([u8; 4], Table)> = vec![];
+    for entry in entries {
+        let tag = tag_to_string(&entry.tag);
+        let name = section_name(&entry.tag);
+        let table = Table::new(object, format!("section {} ({})", tag, name.unwrap_or("user-defined")), entry.offset as usize, entry.length as usize)?;
+        if name.is_none() {
+            if entry.is_required() {
+                bail!("the section {} is required but unknown, it was written by a newer version", tag)
+            }
+            ir.sections.push(RawSection {
+                tag : entry.tag,
+                flags : entry.flags,
+                data : table.data.to_vec()
+            });
+        }else if known.iter().any(|(t, _)| *t == entry.tag) {
+            bail!("the section {} is present twice", tag)
+        }else {
+            known.push((entry.tag, table));
         }
-        let mut buffer = [0;8];
-        input.read_exact(&mut buffer)?;
-        let block_pos = u64::from_le_bytes(buffer[..].try_into()?);
-        let mut block = Block::new(block_sizes);
-        let mut instructions = Vec::new();
-        instructions.reserve(block_size as usize);
-        let next_pos = input.stream_position()?;
-        input.seek(SeekFrom::Start(block_pos))?;
-        for _ in 0..block_size {
-            let mut buffer = [0;32];
-            input.read_exact(&mut buffer)?;
-            let opcode : Opcodes = buffer[7].try_into()?;
-            instructions.push(match opcode {
-                Opcodes::Ret => Instruction::Ret(),
-                Opcodes::Reti => Instruction::Reti(),
-                Opcodes::Iconst => {
-                    let size = u64::from_le_bytes(buffer[8..16].try_into()?);
-                    let value = u128::from_le_bytes(buffer[16..32].try_into()?);
-                    Instruction::Iconst(size, value)
-                },
-                Opcodes::Br => {
-                    let block = u64::from_le_bytes(buffer[8..16].try_into()?);
-                    Instruction::Br(block)
-                },
-                Opcodes::Dup => Instruction::Dup(),
-                Opcodes::Dupx => {
-                    let offset = u64::from_le_bytes(buffer[8..16].try_into()?);
-                    Instruction::Dupx(offset)
-                },
-                Opcodes::Icast => {
-                    let size = u64::from_le_bytes(buffer[8..16].try_into()?);
-                    Instruction::Icast(size)
-                },
-                Opcodes::Swap => Instruction::Swap(),
-                Opcodes::Call => {
-                    let id = u64::from_le_bytes(buffer[8..16].try_into()?);
-                    Instruction::Call(id)
-                },
-                Opcodes::Neg => Instruction::Neg(),
-                Opcodes::Add => Instruction::Add(),
-                Opcodes::Sub => Instruction::Sub(),
-                Opcodes::Mul => Instruction::Mul(),
-                Opcodes::Div => Instruction::Div(),
-                Opcodes::Eq => Instruction::Eq(),
-                Opcodes::Ne => Instruction::Ne(),
-                Opcodes::Gt => Instruction::Gt(),
-                Opcodes::Ge => Instruction::Ge(),
-                Opcodes::Le => Instruction::Le(),
-                Opcodes::Lt => Instruction::Lt(),
-                Opcodes::Lsh => Instruction::Lsh(),
-                Opcodes::Rsh => Instruction::Rsh(),
-                Opcodes::Briz => {
-                    let b1 = u64::from_le_bytes(buffer[8..16].try_into()?);
-                    let b2 = u64::from_le_bytes(buffer[16..24].try_into()?);
-                    Instruction::Briz(b1, b2)
-                },
-                _ => return Err(anyhow!("bad opcode {}", buffer[7]))
-            })
+    }
+    let section = |tag : [u8; 4]| known.iter().find(|(t, _)| *t == tag).map(|(_, table)| table);
+    let required = |tag : [u8; 4]| section(tag)
+        .ok_or_else(|| anyhow!("the section {} ({}) is missing", tag_to_string(&tag), section_name(&tag).unwrap_or_default()));
+
+    let externs = required(section::EXTERNS)?;
+    let strings = externs.strings()?;
+    if strings.len() % 2 != 0 {
+        bail!("the {} has a function without signature", externs.name)
+    }
+    for pair in strings.chunks_exact(2) {
+        ir.ext_fn.push(IrExternalFn::new(pair[0].clone(), pair[1].parse().with_context(|| format!("in the {}", externs.name))?));
+    }
+    let instructions = required(section::INSTRUCTIONS)?;
+    for (i, entry) in required(section::BLOCKS)?.chunks(8)?.enumerate() {
+        let count = u32::from_le_bytes(entry[0..4].try_into()?) as usize;
+        let start = u32::from_le_bytes(entry[4..8].try_into()?) as usize;
+        ir.blocks.push(read_block(instructions, start, count, i)?);
+    }
+    ir.local_vars = required(section::LOCAL_VARS)?.data.to_vec();
+    if let Some(source_map) = section(section::SOURCE_MAP) {
+        for l in source_map.chunks(SOURCE_LOCATION_SIZE)? {
+            let value = |i : usize| u32::from_le_bytes(l[4 * i..4 * i + 4].try_into().unwrap());
+            ir.source_map.push(SourceLocation {
+                block : value(0),
+                instruction : value(1),
+                line : value(2),
+                column : value(3)
+            });
         }
-        block.instructions = IndexList::from_iter(instructions.into_iter());
-        object.blocks.push(block);
-        input.seek(SeekFrom::Start(next_pos))?;
+    }
+    if let Some(strings) = section(section::STRINGS) {
+        ir.strings = strings.data.to_vec();
+    }
+    if let Some(metadata) = section(section::METADATA) {
+        let strings = metadata.strings()?;
+        if strings.len() % 2 != 0 {
+            bail!("the {} has a key without value", metadata.name)
+        }
+        ir.metadata = strings.chunks_exact(2).map(|kv| (kv[0].clone(), kv[1].clone())).collect();
+    }
+    Ok(ir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::writer::write;
+    use std::io::Cursor;
+
+    /// one instruction of each opcode
+    fn all_instructions() -> Vec<Instruction> {
+        vec![
+            Instruction::Nop(),
+            Instruction::Pop(1),
+            Instruction::Dup(2),
+            Instruction::Swap(4),
+            Instruction::Br(1),
+            Instruction::Briz(0, 1),
+            Instruction::Call(1),
+            Instruction::Varref(),
+            Instruction::Iconst(8, -0x0123_4567_89AB_CDEF),
+            Instruction::Iload(8),
+            Instruction::Istore(0),
+            Instruction::Iret(8),
+            Instruction::Inot(1),
+            Instruction::Ior(2),
+            Instruction::Iand(4),
+            Instruction::Ixor(8),
+            Instruction::Ilshr(1),
+            Instruction::Iashr(2),
+            Instruction::Ishl(4),
+            Instruction::Ineg(8),
+            Instruction::Iadd(1),
+            Instruction::Isub(2),
+            Instruction::Imul(4),
+            Instruction::Idiv(8),
+            Instruction::Udiv(1),
+            Instruction::Irem(2),
+            Instruction::Urem(4),
+            Instruction::Ieq(8),
+            Instruction::Ine(1),
+            Instruction::Ige(2),
+            Instruction::Igt(4),
+            Instruction::Uge(8),
+            Instruction::Ugt(1),
+            Instruction::Ilt(2),
+            Instruction::Ile(4),
+            Instruction::Ule(8),
+            Instruction::Ult(1),
+            Instruction::Iconv(8, 1),
+            Instruction::Uconv(u32::MAX, 2),
+        ]
+    }
+
+    fn object() -> IrObject {
+        let mut ir = IrObject::new();
+        ir.ext_fn.push(IrExternalFn::new("print".to_string(), "void/i64".parse().unwrap()));
+        ir.ext_fn.push(IrExternalFn::new("pow".to_string(), "i64/i64/i64".parse().unwrap()));
+        ir.add_var(8);
+        ir.add_var(0);
+        let b0 = ir.append_block();
+        for i in all_instructions() {
+            ir.get_block_mut(b0).instructions.insert_last(i);
+        }
+        let b1 = ir.append_block();
+        ir.get_block_mut(b1).instructions.insert_last(Instruction::Iret(8));
+        ir
+    }
+
+    fn round_trip(ir : IrObject) -> (Vec<u8>, IrObject) {
+        let mut output : Cursor<Vec<u8>> = Cursor::new(vec![]);
+        write(ir, &mut output).unwrap();
+        let bytes = output.into_inner();
+        let ir = read(&mut Cursor::new(bytes.clone())).unwrap();
+        (bytes, ir)
     }
 
-    Ok(object) 
+    #[test]
+    fn test_round_trip() {
+        let printed = format!("{:?}", object());
+        let (_, ir) = round_trip(object());
+        assert_eq!(format!("{:?}", ir), printed);
+        assert_eq!(ir.ext_fn, object().ext_fn);
+        assert_eq!(ir.local_vars, vec![8, 0]);
+        assert_eq!(ir.blocks[0].instructions.iter().copied().collect::<Vec<_>>(), all_instructions());
+        let (_, empty) = round_trip(IrObject::new());
+        assert!(empty.blocks.is_empty() && empty.ext_fn.is_empty());
+    }
+
+    #[test]
+    fn test_instructions() {
+        let instructions = all_instructions();
+        assert_eq!(instructions.len(), 39);
+        for (opcode, i) in instructions.iter().enumerate() {
+            assert_eq!(u8::from(i.opcode()), opcode as u8);
+            let mut bytes = i.to_bytes();
+            assert_eq!(bytes[3], opcode as u8);
+            // the padding is ignored
+            bytes[0..3].copy_from_slice(&[0xAA; 3]);
+            assert_eq!(Instruction::from_bytes(&bytes), Some(*i));
+        }
+        assert_eq!(Instruction::Briz(3, 7).to_bytes(), [0, 0, 0, 0x05, 3, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&Instruction::Iconst(2, -2).to_bytes()[4..], &[2, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let mut reserved = [0; INSTRUCTION_SIZE];
+        reserved[3] = 0x27;
+        assert_eq!(Instruction::from_bytes(&reserved), None);
+    }
+
+    fn header(version : [u8; 3]) -> Vec<u8> {
+        let mut header = b"\0JOO".to_vec();
+        header.extend(version);
+        header
+    }
+
+    /// update the checksum of a modified object
+    fn seal(mut object : Vec<u8>) -> Vec<u8> {
+        let checksum = checksum(&object);
+        object[CHECKSUM_POS..HEADER_SIZE].copy_from_slice(&checksum);
+        object
+    }
+
+    fn current_version() -> [u8; 3] {
+        [VERSION.major as u8, VERSION.minor as u8, VERSION.patch as u8]
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut ir = IrObject::new();
+        ir.ext_fn.push(IrExternalFn::new("input".to_string(), "i64".parse().unwrap()));
+        ir.add_var(4);
+        let b0 = ir.append_block();
+        ir.get_block_mut(b0).instructions.insert_last(Instruction::Iret(8));
+        let (bytes, _) = round_trip(ir);
+        let mut expected = header(current_version());
+        // section count and directory offset
+        expected.extend([4, 0, 0, 0, 47, 0, 0, 0]);
+        // the checksum, computed at the end
+        expected.extend([0; 32]);
+        expected.extend(b"EXTF");
+        expected.extend([111, 0, 0, 0, 16, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend(b"BLKS");
+        expected.extend([127, 0, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend(b"VARS");
+        expected.extend([135, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend(b"INST");
+        expected.extend([136, 0, 0, 0, 16, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend([5, 0, 0, 0]);
+        expected.extend(b"input");
+        expected.extend([3, 0, 0, 0]);
+        expected.extend(b"i64");
+        expected.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend([4]);
+        expected.extend([0, 0, 0, 0x0B, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let hash = blake3::hash(&expected);
+        expected[CHECKSUM_POS..HEADER_SIZE].copy_from_slice(hash.as_bytes());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_sections() {
+        let mut ir = object();
+        ir.source_map.push(SourceLocation { block : 0, instruction : 3, line : 2, column : 7 });
+        ir.strings = b"hello\0world\0".to_vec();
+        ir.metadata.push(("compiler".to_string(), "0.3.0".to_string()));
+        ir.metadata.push(("flags".to_string(), String::new()));
+        ir.sections.push(RawSection { tag : *b"user", flags : 0, data : vec![1, 2, 3] });
+        let (bytes, read_ir) = round_trip(ir);
+        assert_eq!(read_ir.source_map, vec![SourceLocation { block : 0, instruction : 3, line : 2, column : 7 }]);
+        assert_eq!(read_ir.strings, b"hello\0world\0");
+        assert_eq!(read_ir.get_metadata("compiler"), Some("0.3.0"));
+        assert_eq!(read_ir.get_metadata("flags"), Some(""));
+        assert_eq!(read_ir.sections, vec![RawSection { tag : *b"user", flags : 0, data : vec![1, 2, 3] }]);
+        let tags : Vec<[u8; 4]> = directory(&bytes).unwrap().iter().map(|e| e.tag).collect();
+        assert_eq!(tags, vec![*b"EXTF", *b"BLKS", *b"VARS", *b"INST", *b"SMAP", *b"STRS", *b"META", *b"user"]);
+
+        // unknown sections are skipped unless they are required
+        let mut required = object();
+        required.sections.push(RawSection { tag : *b"NEW!", flags : section::REQUIRED, data : vec![] });
+        let mut output : Cursor<Vec<u8>> = Cursor::new(vec![]);
+        write(required, &mut output).unwrap();
+        assert_eq!(read(&mut output).map(|_| ()).unwrap_err().to_string(),
+            "the section NEW! is required but unknown, it was written by a newer version");
+        let mut missing = bytes.clone();
+        // rename the blocks section
+        missing[HEADER_SIZE + ENTRY_SIZE] = b'X';
+        assert_eq!(read(&mut Cursor::new(seal(missing))).map(|_| ()).unwrap_err().to_string(), "the section XLKS is required but unknown, it was written by a newer version");
+        let mut missing = bytes;
+        missing[HEADER_SIZE + ENTRY_SIZE] = b'X';
+        missing[HEADER_SIZE + ENTRY_SIZE + 12] = 0;
+        assert_eq!(read(&mut Cursor::new(seal(missing))).map(|_| ()).unwrap_err().to_string(), "the section BLKS (blocks) is missing");
+    }
+
+    #[test]
+    fn test_layout_detection() {
+        let (bytes, _) = round_trip(object());
+        assert_eq!(layout(&bytes).unwrap(), (Layout::V2, current_version()));
+        let with_version = |major : u8, minor : u8| {
+            let mut bytes = bytes.clone();
+            bytes[4..7].copy_from_slice(&[major, minor, 0]);
+            layout(&bytes).map(|l| l.0).map_err(|e| e.to_string())
+        };
+        assert_eq!(with_version(VERSION.major as u8, VERSION.minor as u8 + 1).unwrap_err(), format!(
+            "the object was written by jolang {}.{}.0 which is newer than this version ({}), update jolang to read it",
+            VERSION.major, VERSION.minor + 1, VERSION_STR));
+        assert!(with_version(VERSION.major as u8 + 1, 0).unwrap_err().contains("newer than this version"));
+        // the layout only depends on the version
+        assert_eq!(with_version(0, 3), Ok(Layout::V1));
+        assert_eq!(with_version(0, 4), Ok(Layout::V2));
+        assert!(directory(&header([0, 3, 0])).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let (bytes, _) = round_trip(object());
+        let read_bytes = |bytes : &[u8]| format!("{:#}", read(&mut Cursor::new(bytes.to_vec())).map(|_| ()).unwrap_err());
+        assert_eq!(read_bytes(b"\0JOB"), "bad header");
+        let instructions = directory(&bytes).unwrap()[3];
+        assert_eq!(read_bytes(&bytes[..bytes.len() - 1]), format!("the section INST (instructions) ends at {} but the object is only {} bytes",
+            instructions.offset + instructions.length, bytes.len() - 1));
+        let mut bad_opcode = bytes.clone();
+        let last = bad_opcode.len() - INSTRUCTION_SIZE;
+        bad_opcode[last + 3] = 0x30;
+        assert_eq!(read_bytes(&seal(bad_opcode)), "bad opcode 48 in the block 1");
+        let mut bad_signature = bytes;
+        let externs = directory(&bad_signature).unwrap()[0].offset as usize;
+        bad_signature[externs + 4 + 5 + 4] = b'/';
+        let message = read_bytes(&seal(bad_signature));
+        assert!(message.starts_with("in the section EXTF (external functions): ") && message.contains("signature"), "{}", message);
+    }
+
+    #[test]
+    fn test_validation() {
+        let (bytes, _) = round_trip(object());
+        let read_bytes = |bytes : Vec<u8>| read(&mut Cursor::new(bytes)).map(|_| ()).unwrap_err().to_string();
+        let mut damaged = bytes.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert_eq!(read_bytes(damaged), "the checksum of the object doesn't match its content, the object is damaged");
+        assert_eq!(read_bytes(bytes[..HEADER_SIZE - 1].to_vec()), format!("the object is too small for its header ({} bytes)", HEADER_SIZE - 1));
+        let mut huge_count = bytes.clone();
+        huge_count[7..11].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(read_bytes(seal(huge_count)),
+            format!("the section directory has {} entries at offset {} but the object is only {} bytes", u32::MAX, HEADER_SIZE, bytes.len()));
+        let mut blocks = bytes.clone();
+        let entry = directory(&bytes).unwrap()[1].offset as usize;
+        blocks[entry..entry + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(read_bytes(seal(blocks)), format!("the {} instructions of the block 0 at offset 0 are outside of the section INST (instructions) ({} bytes)",
+            u32::MAX, INSTRUCTION_SIZE * 40));
+        let mut odd_blocks = bytes.clone();
+        odd_blocks[HEADER_SIZE + ENTRY_SIZE + 8] = 7;
+        assert_eq!(read_bytes(seal(odd_blocks)), "the length of the section BLKS (blocks) (7 bytes) is not a multiple of 8");
+        let mut string = bytes.clone();
+        let externs = directory(&bytes).unwrap()[0].offset as usize;
+        string[externs..externs + 4].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(read_bytes(seal(string)),
+            format!("the section EXTF (external functions) ends in the middle of an entry (offset {}, 1000 bytes)", externs + 4));
+
+        // any damage is an error, never a panic
+        for pos in 0..bytes.len() {
+            for value in [0, 0x7F, 0xFF] {
+                let mut damaged = bytes.clone();
+                damaged[pos] = value;
+                let _ = read(&mut Cursor::new(seal(damaged)));
+            }
+        }
+        for len in 0..bytes.len() {
+            assert!(read(&mut Cursor::new(bytes[..len].to_vec())).is_err());
+        }
+    }
 }
@@ -0,0 +1,72 @@
+//! sections of an object, each one is described by an entry of the section directory
+
+/// size of a directory entry : the tag, the offset, the length and the flags
+pub const ENTRY_SIZE : usize = 16;
+
+/// the reader must understand the section to read the object
+pub const REQUIRED : u32 = 1;
+
+pub const EXTERNS : [u8; 4] = *b"EXTF";
+pub const BLOCKS : [u8; 4] = *b"BLKS";
+pub const LOCAL_VARS : [u8; 4] = *b"VARS";
+pub const INSTRUCTIONS : [u8; 4] = *b"INST";
+pub const SOURCE_MAP : [u8; 4] = *b"SMAP";
+/// reserved for the string constants, kept unchanged by the readers and the writer
+pub const STRINGS : [u8; 4] = *b"STRS";
+pub const METADATA : [u8; 4] = *b"META";
+
+/// size of a source map entry : block, instruction, line and column
+pub const SOURCE_LOCATION_SIZE : usize = 16;
+
+/// the name of a known section
+pub fn section_name(tag : &[u8; 4]) -> Option<&'static str> {
+    Some(match *tag {
+        EXTERNS => "external functions",
+        BLOCKS => "blocks",
+        LOCAL_VARS => "local variable slots",
+        INSTRUCTIONS => "instructions",
+        SOURCE_MAP => "source map",
+        STRINGS => "string data",
+        METADATA => "metadata",
+        _ => return None
+    })
+}
+
+/// the tag as text, the bytes that are not printable are escaped
+pub fn tag_to_string(tag : &[u8; 4]) -> String {
+    tag.iter().map(|b| std::ascii::escape_default(*b).to_string()).collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SectionEntry {
+    pub tag : [u8; 4],
+    /// offset from the start of the file
+    pub offset : u32,
+    pub length : u32,
+    pub flags : u32
+}
+
+impl SectionEntry {
+    pub fn is_required(&self) -> bool {
+        self.flags & REQUIRED != 0
+    }
+}
+
+/// the position in the source of the instruction at the index in the block
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceLocation {
+    pub block : u32,
+    pub instruction : u32,
+    pub line : u32,
+    pub column : u32
+}
+
+/// the data of a section, the user-defined and unknown sections are kept unchanged
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawSection {
+    pub tag : [u8; 4],
+    pub flags : u32,
+    pub data : Vec<u8>
+}
@@ -0,0 +1,75 @@
+//! json and ron versions of the objects for the tools that don't read the binary layout,
+//! the fields that the deserializers don't know are ignored
+use anyhow::{anyhow, Context, Result};
+use super::assembler::check_references;
+use super::{IrObject, Signature};
+
+/// the checks of the assembler, the signatures must also be readable from an object file
+fn check(object : IrObject) -> Result<IrObject> {
+    for f in object.ext_fn.iter() {
+        f.sig.to_string().parse::<Signature>().with_context(|| format!("in the extern {}", f.name))?;
+    }
+    check_references(&object)?;
+    Ok(object)
+}
+
+pub fn to_json(object : &IrObject) -> Result<String> {
+    Ok(serde_json::to_string_pretty(object)?)
+}
+
+pub fn from_json(text : &str) -> Result<IrObject> {
+    check(serde_json::from_str(text).map_err(|e| anyhow!("invalid json object : {}", e))?)
+}
+
+#[cfg(feature = "ron")]
+pub fn to_ron(object : &IrObject) -> Result<String> {
+    Ok(ron::ser::to_string_pretty(object, ron::ser::PrettyConfig::default())?)
+}
+
+#[cfg(feature = "ron")]
+pub fn from_ron(text : &str) -> Result<IrObject> {
+    check(ron::from_str(text).map_err(|e| anyhow!("invalid ron object : {}", e))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::assembler::assemble;
+
+    const TEXT : &str = "extern fn print (i64) -> void;\nfn main () -> i64 {\n\tvar 0 8\nB0 : \n\ticonst 8 -3\n\tcall 0\n\tnop\n\tbriz 1 1\nB1 : \n\ticonst 8 0\n\tiret 8\n}";
+
+    #[test]
+    fn test_json() {
+        let mut object = assemble(TEXT).unwrap();
+        object.metadata.push(("compiler".to_string(), "test".to_string()));
+        let json = to_json(&object).unwrap();
+        let value : serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["ext_fn"][0], serde_json::json!({ "name" : "print", "sig" : { "ret" : "void", "args" : ["i64"] } }));
+        assert_eq!(value["blocks"][0]["instructions"], serde_json::json!([{ "iconst" : [8, -3] }, { "call" : 0 }, { "nop" : [] }, { "briz" : [1, 1] }]));
+        assert_eq!(value["metadata"], serde_json::json!([["compiler", "test"]]));
+        let read = from_json(&json).unwrap();
+        assert_eq!(format!("{:?}", read), TEXT);
+        assert_eq!(read.metadata, object.metadata);
+
+        // the optional sections can be missing and the unknown fields are ignored
+        let minimal = r#"{"header" : {}, "ext_fn" : [], "blocks" : [{"instructions" : [{"iconst" : [8, 0]}, {"iret" : 8}]}], "local_vars" : []}"#;
+        assert_eq!(format!("{:?}", from_json(minimal).unwrap()), "fn main () -> i64 {\nB0 : \n\ticonst 8 0\n\tiret 8\n}");
+        let cases = [
+            (r#"{"ext_fn" : [], "blocks" : [{"instructions" : [{"br" : 1}]}], "local_vars" : []}"#, "B0 : branch to the unknown block B1"),
+            (r#"{"ext_fn" : [{"name" : "f", "sig" : {"ret" : "i64", "args" : ["void"]}}], "blocks" : [], "local_vars" : []}"#,
+                "in the extern f: invalid argument type in the signature : i64/void"),
+        ];
+        for (text, message) in cases {
+            assert_eq!(format!("{:#}", from_json(text).unwrap_err()), message);
+        }
+        assert!(from_json(r#"{"ext_fn" : [], "blocks" : [{"instructions" : [{"iadd8" : 8}]}], "local_vars" : []}"#)
+            .unwrap_err().to_string().starts_with("invalid json object : unknown variant `iadd8`"));
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_ron() {
+        let object = assemble(TEXT).unwrap();
+        assert_eq!(format!("{:?}", from_ron(&to_ron(&object).unwrap()).unwrap()), TEXT);
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, PartialEq, Eq)]
+use std::str::FromStr;
+use anyhow::{anyhow, Error};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Signature {
     pub ret : String,
     pub args : Vec<String>
@@ -17,3 +21,19 @@ impl Signature {
             .fold(self.ret.clone(), |s1, s2| s1 + "/" + s2)
     }
 }
+
+/// parse a signature written by `to_string`
+impl FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let mut types = s.split('/').map(|t| t.to_string());
+        let ret = types.next().filter(|t| !t.is_empty())
+            .ok_or_else(|| anyhow!("missing return type in the signature : {}", s))?;
+        let args : Vec<String> = types.collect();
+        if args.iter().any(|a| a.is_empty() || a == "void") {
+            return Err(anyhow!("invalid argument type in the signature : {}", s))
+        }
+        Ok(Self::new(ret, args))
+    }
+}
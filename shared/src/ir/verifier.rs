@@ -0,0 +1,420 @@
+//! static checks of an object, they find the errors that the runtimes would only find while lowering or running it.
+//! the stack is followed from the entry block : each block is entered with the stack of the first branch to it
+//! and every other branch to the block must pass the same stack. the blocks that are never reached are only checked for their terminator
+use std::fmt;
+use anyhow::{anyhow, Result};
+use super::instructions::{operand::{BlkId, Size}, Instruction};
+use super::IrObject;
+
+/// size of a reference on the stack and of the variables that hold one
+pub const REF_SIZE : Size = 0;
+
+fn is_int(size : Size) -> bool {
+    matches!(size, 1 | 2 | 4 | 8)
+}
+
+/// the size of a type of a signature
+pub fn type_size(name : &str) -> Option<Size> {
+    match name {
+        "i8" => Some(1),
+        "i16" => Some(2),
+        "i32" => Some(4),
+        "i64" => Some(8),
+        _ => None
+    }
+}
+
+/// the name of the type of a value on the stack
+pub fn type_name(size : Size) -> String {
+    match size {
+        REF_SIZE => "ref".to_string(),
+        size => format!("i{}", size * 8)
+    }
+}
+
+/// the type with its article, for the messages
+fn a_type(size : Size) -> String {
+    match size {
+        REF_SIZE => "a ref".to_string(),
+        size => format!("an {}", type_name(size))
+    }
+}
+
+fn stack_to_string(stack : &[Size]) -> String {
+    format!("[{}]", stack.iter().map(|s| type_name(*s)).collect::<Vec<_>>().join(", "))
+}
+
+fn is_terminator(instruction : &Instruction) -> bool {
+    matches!(instruction, Instruction::Br(_) | Instruction::Briz(_, _) | Instruction::Iret(_))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VerifyError {
+    pub block : Option<BlkId>,
+    /// index of the instruction in the block
+    pub instruction : Option<(usize, Instruction)>,
+    pub message : String
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.block, self.instruction) {
+            (Some(block), Some((index, instruction))) => write!(f, "B{} instruction {} ({}) : {}", block, index, instruction, self.message),
+            (Some(block), None) => write!(f, "B{} : {}", block, self.message),
+            _ => write!(f, "{}", self.message)
+        }
+    }
+}
+
+struct Verifier<'a> {
+    object : &'a IrObject,
+    /// the stack when entering each block and the block of the first branch to it
+    entries : Vec<Option<(Vec<Size>, Option<BlkId>)>>,
+    max_depths : Vec<usize>,
+    queue : Vec<BlkId>,
+    errors : Vec<VerifyError>
+}
+
+fn pop(stack : &mut Vec<Size>, size : Size) -> Result<(), String> {
+    match stack.pop() {
+        None => Err("the stack is empty".to_string()),
+        Some(s) if s == size => Ok(()),
+        Some(s) => Err(format!("expected {} on the stack but found {}", a_type(size), a_type(s)))
+    }
+}
+
+fn pop_int(stack : &mut Vec<Size>) -> Result<Size, String> {
+    match stack.pop() {
+        None => Err("the stack is empty".to_string()),
+        Some(REF_SIZE) => Err("expected an integer on the stack but found a ref".to_string()),
+        Some(s) => Ok(s)
+    }
+}
+
+fn int_operand(size : Size) -> Result<(), String> {
+    match is_int(size) {
+        true => Ok(()),
+        false => Err(format!("invalid integer size : {}", size))
+    }
+}
+
+/// the size of pop and dup is the size of the value, 0 for a reference
+fn value_operand(size : Size) -> Result<(), String> {
+    match size == REF_SIZE || is_int(size) {
+        true => Ok(()),
+        false => Err(format!("invalid size : {}", size))
+    }
+}
+
+impl<'a> Verifier<'a> {
+    fn error(&mut self, block : BlkId, instruction : Option<(usize, Instruction)>, message : String) {
+        self.errors.push(VerifyError { block : Some(block), instruction, message });
+    }
+
+    /// the branch passes the stack to the block
+    fn branch(&mut self, from : BlkId, index : usize, instruction : Instruction, target : BlkId, stack : &[Size]) {
+        let Some(entry) = self.entries.get_mut(target as usize) else {
+            // reported with the terminators
+            return
+        };
+        match entry {
+            None => {
+                *entry = Some((stack.to_vec(), Some(from)));
+                self.queue.push(target);
+            },
+            Some((expected, first)) if expected != stack => {
+                let first = match first {
+                    Some(b) => format!("from B{}", b),
+                    None => "as the entry block".to_string()
+                };
+                let message = format!("B{} is entered with the stack {} {} but this branch passes {}",
+                    target, stack_to_string(expected), first, stack_to_string(stack));
+                self.error(from, Some((index, instruction)), message);
+            },
+            Some(_) => ()
+        }
+    }
+
+    fn instruction(&mut self, id : BlkId, index : usize, stack : &mut Vec<Size>, previous : Option<Instruction>, instruction : Instruction) -> Result<(), String> {
+        match instruction {
+            Instruction::Nop() => (),
+            Instruction::Pop(size) => {
+                value_operand(size)?;
+                pop(stack, size)?;
+            },
+            Instruction::Dup(size) => {
+                value_operand(size)?;
+                pop(stack, size)?;
+                stack.extend([size, size]);
+            },
+            Instruction::Swap(_) => {
+                if stack.len() < 2 {
+                    return Err(format!("swap needs 2 values but the stack has {}", stack.len()))
+                }
+                let len = stack.len();
+                stack.swap(len - 1, len - 2);
+            },
+            Instruction::Br(target) => self.branch(id, index, instruction, target, stack),
+            Instruction::Briz(target1, target2) => {
+                pop_int(stack)?;
+                self.branch(id, index, instruction, target1, stack);
+                self.branch(id, index, instruction, target2, stack);
+            },
+            Instruction::Call(f) => {
+                let func = self.object.ext_fn.get(f as usize)
+                    .ok_or_else(|| format!("call of the unknown extern {}", f))?;
+                for (i, arg) in func.sig.args.iter().enumerate().rev() {
+                    let size = type_size(arg)
+                        .ok_or_else(|| format!("the argument {} of {} has the unsupported type {}", i, func.name, arg))?;
+                    pop(stack, size).map_err(|e| format!("{} for the argument {} of {}", e, i, func.name))?;
+                }
+                if func.sig.ret != "void" {
+                    stack.push(type_size(&func.sig.ret)
+                        .ok_or_else(|| format!("{} returns the unsupported type {}", func.name, func.sig.ret))?);
+                }
+            },
+            Instruction::Varref() => {
+                if let Some(Instruction::Iconst(_, var)) = previous {
+                    if var < 0 || var as usize >= self.object.local_vars.len() {
+                        return Err(format!("reference to the unknown variable {}", var))
+                    }
+                }
+                pop_int(stack)?;
+                stack.push(REF_SIZE);
+            },
+            Instruction::Iconst(size, _) => {
+                int_operand(size)?;
+                stack.push(size);
+            },
+            Instruction::Iload(size) => {
+                int_operand(size)?;
+                pop(stack, REF_SIZE)?;
+                stack.push(size);
+            },
+            Instruction::Istore(size) => {
+                int_operand(size)?;
+                pop(stack, size)?;
+                pop(stack, REF_SIZE)?;
+            },
+            Instruction::Iret(size) => {
+                int_operand(size)?;
+                pop(stack, size)?;
+            },
+            Instruction::Inot(size)
+                | Instruction::Ineg(size)
+                => {
+                int_operand(size)?;
+                pop(stack, size)?;
+                stack.push(size);
+            },
+            Instruction::Ior(size)
+                | Instruction::Iand(size)
+                | Instruction::Ixor(size)
+                | Instruction::Ilshr(size)
+                | Instruction::Iashr(size)
+                | Instruction::Ishl(size)
+                | Instruction::Iadd(size)
+                | Instruction::Isub(size)
+                | Instruction::Imul(size)
+                | Instruction::Idiv(size)
+                | Instruction::Udiv(size)
+                | Instruction::Irem(size)
+                | Instruction::Urem(size)
+                => {
+                int_operand(size)?;
+                pop(stack, size)?;
+                pop(stack, size)?;
+                stack.push(size);
+            },
+            Instruction::Ieq(size)
+                | Instruction::Ine(size)
+                | Instruction::Ige(size)
+                | Instruction::Igt(size)
+                | Instruction::Uge(size)
+                | Instruction::Ugt(size)
+                | Instruction::Ilt(size)
+                | Instruction::Ile(size)
+                | Instruction::Ule(size)
+                | Instruction::Ult(size)
+                => {
+                int_operand(size)?;
+                pop(stack, size)?;
+                pop(stack, size)?;
+                stack.push(1);
+            },
+            Instruction::Iconv(from, to)
+                | Instruction::Uconv(from, to)
+                => {
+                int_operand(from)?;
+                int_operand(to)?;
+                pop(stack, from)?;
+                stack.push(to);
+            }
+        }
+        Ok(())
+    }
+
+    /// follow the stack through a reached block, it stops at the first error or terminator
+    fn block(&mut self, id : BlkId) {
+        let mut stack = match &self.entries[id as usize] {
+            Some((stack, _)) => stack.clone(),
+            None => return
+        };
+        let instructions : Vec<Instruction> = self.object.blocks[id as usize].instructions.iter().copied().collect();
+        let mut previous = None;
+        self.max_depths[id as usize] = stack.len();
+        for (index, instruction) in instructions.into_iter().enumerate() {
+            if let Err(message) = self.instruction(id, index, &mut stack, previous, instruction) {
+                self.error(id, Some((index, instruction)), message);
+                return
+            }
+            self.max_depths[id as usize] = self.max_depths[id as usize].max(stack.len());
+            if is_terminator(&instruction) {
+                return
+            }
+            previous = Some(instruction);
+        }
+    }
+
+    /// the terminators and the branch targets of a block, reached or not
+    fn terminators(&mut self, id : BlkId) {
+        let block = &self.object.blocks[id as usize];
+        let count = block.instructions.len();
+        let blocks = self.object.blocks.len();
+        let mut errors = vec![];
+        for (index, instruction) in block.instructions.iter().enumerate() {
+            let targets = match *instruction {
+                Instruction::Br(target) => vec![target],
+                Instruction::Briz(target1, target2) => vec![target1, target2],
+                _ => vec![]
+            };
+            for target in targets {
+                if target as usize >= blocks {
+                    errors.push((Some((index, *instruction)), format!("branch to the unknown block B{}", target)));
+                }
+            }
+            if is_terminator(instruction) && index + 1 != count {
+                errors.push((Some((index, *instruction)), "the block continues after its terminator".to_string()));
+            }
+        }
+        if !block.instructions.iter().any(is_terminator) {
+            errors.push((None, "the block doesn't end with br, briz or iret".to_string()));
+        }
+        for (instruction, message) in errors {
+            self.error(id, instruction, message);
+        }
+    }
+}
+
+fn run(object : &IrObject) -> Verifier<'_> {
+    let mut verifier = Verifier {
+        object,
+        entries : vec![None; object.blocks.len()],
+        max_depths : vec![0; object.blocks.len()],
+        queue : vec![],
+        errors : vec![]
+    };
+    for (i, size) in object.local_vars.iter().enumerate() {
+        if *size as Size != REF_SIZE && !is_int(*size as Size) {
+            verifier.errors.push(VerifyError { block : None, instruction : None, message : format!("the variable {} has the invalid size {}", i, size) });
+        }
+    }
+    if object.blocks.is_empty() {
+        verifier.errors.push(VerifyError { block : None, instruction : None, message : "the object has no entry block".to_string() });
+        return verifier
+    }
+    for id in 0..object.blocks.len() {
+        verifier.terminators(id as BlkId);
+    }
+    verifier.entries[0] = Some((vec![], None));
+    verifier.queue.push(0);
+    while let Some(id) = verifier.queue.pop() {
+        verifier.block(id);
+    }
+    verifier
+}
+
+/// every error of the object, sorted by block and instruction
+pub fn errors(object : &IrObject) -> Vec<VerifyError> {
+    let mut errors = run(object).errors;
+    errors.sort_by_key(|e| (e.block, e.instruction.map(|i| i.0)));
+    errors
+}
+
+/// what the verifier finds about the blocks
+pub struct Analysis {
+    /// the stack each block receives from its predecessors (the block arguments), none for the blocks that are never reached
+    pub entries : Vec<Option<Vec<Size>>>,
+    /// the highest number of values on the stack in each block
+    pub max_depths : Vec<usize>
+}
+
+pub fn analyze(object : &IrObject) -> Analysis {
+    let verifier = run(object);
+    Analysis {
+        entries : verifier.entries.into_iter().map(|e| e.map(|(stack, _)| stack)).collect(),
+        max_depths : verifier.max_depths
+    }
+}
+
+/// check an object before it is run, the error is the first one found
+pub fn verify(object : &IrObject) -> Result<()> {
+    let errors = errors(object);
+    match errors.as_slice() {
+        [] => Ok(()),
+        [error] => Err(anyhow!("invalid ir : {}", error)),
+        [error, others @ ..] => Err(anyhow!("invalid ir : {} (and {} other errors)", error, others.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::assembler::assemble;
+
+    fn check(body : &str) -> Vec<String> {
+        let text = format!("extern fn print (i64) -> void;\nextern fn input () -> i64;\nfn main () -> i64 {{\n\tvar 0 8\n{}\n}}", body);
+        errors(&assemble(&text).unwrap()).iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let loop_body = "B0 :\n\ticonst 8 3\n\tbr 1\nB1 :\n\tdup 8\n\tbriz 3 2\nB2 :\n\ticonst 8 1\n\tisub 8\n\tbr 1\nB3 :\n\tiret 8";
+        assert_eq!(check(loop_body), Vec::<String>::new());
+        let variables = "B0 :\n\ticonst 4 0\n\tvarref\n\tcall 1\n\tistore 8\n\ticonst 4 0\n\tvarref\n\tiload 8\n\tdup 8\n\tcall 0\n\ticonst 8 2\n\tigt 8\n\tuconv 1 8\n\tiret 8";
+        assert_eq!(check(variables), Vec::<String>::new());
+        // the unreached blocks only need a terminator
+        assert_eq!(check("B0 :\n\ticonst 8 0\n\tiret 8\nB1 :\n\tiadd 8\n\tbr 0"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("B0 :\n\tiadd 8\n\tiret 8", "B0 instruction 0 (iadd 8) : the stack is empty"),
+            ("B0 :\n\ticonst 4 1\n\ticonst 8 1\n\tiadd 8\n\tiret 8", "B0 instruction 2 (iadd 8) : expected an i64 on the stack but found an i32"),
+            ("B0 :\n\ticonst 3 1\n\tiret 8", "B0 instruction 0 (iconst 3 1) : invalid integer size : 3"),
+            ("B0 :\n\ticonst 4 1\n\tvarref\n\tiret 8", "B0 instruction 1 (varref) : reference to the unknown variable 1"),
+            ("B0 :\n\ticonst 4 0\n\tvarref\n\tiret 8", "B0 instruction 2 (iret 8) : expected an i64 on the stack but found a ref"),
+            ("B0 :\n\ticonst 4 0\n\tcall 0\n\ticonst 8 0\n\tiret 8", "B0 instruction 1 (call 0) : expected an i64 on the stack but found an i32 for the argument 0 of print"),
+            ("B0 :\n\ticonst 8 0\n\tcall 0\n\tiret 8", "B0 instruction 2 (iret 8) : the stack is empty"),
+            ("B0 :\n\ticonst 8 0\n\tiret 8\n\tnop", "B0 instruction 1 (iret 8) : the block continues after its terminator"),
+            ("B0 :\n\ticonst 8 0", "B0 : the block doesn't end with br, briz or iret"),
+            ("B0 :\n\ticonst 8 0\n\tbriz 1 2\nB1 :\n\ticonst 8 1\n\tbr 2\nB2 :\n\ticonst 8 0\n\tiret 8",
+                "B1 instruction 1 (br 2) : B2 is entered with the stack [] from B0 but this branch passes [i64]"),
+            ("B0 :\n\ticonst 8 0\n\tbr 0", "B0 instruction 1 (br 0) : B0 is entered with the stack [] as the entry block but this branch passes [i64]"),
+        ];
+        for (body, message) in cases {
+            assert_eq!(check(body), vec![message.to_string()], "{}", body);
+        }
+        // the assembler rejects the unknown blocks
+        let mut unknown = IrObject::new();
+        let b0 = unknown.append_block();
+        unknown.get_block_mut(b0).instructions.insert_last(Instruction::Br(4));
+        assert_eq!(verify(&unknown).unwrap_err().to_string(), "invalid ir : B0 instruction 0 (br 4) : branch to the unknown block B4");
+        let mut invalid = IrObject::new();
+        invalid.add_var(3);
+        assert_eq!(errors(&invalid).iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            vec!["the variable 0 has the invalid size 3", "the object has no entry block"]);
+        assert_eq!(verify(&invalid).unwrap_err().to_string(), "invalid ir : the variable 0 has the invalid size 3 (and 1 other errors)");
+    }
+}
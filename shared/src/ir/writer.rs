@@ -1,81 +1,107 @@
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Error, ErrorKind, Seek, Write};
 use crate::VERSION;
 
-use super::{instructions::Instruction, IrObject};
+use super::{section::{self, RawSection, ENTRY_SIZE, REQUIRED}, IrObject};
 
-pub fn write<T>(ir : IrObject, target : &mut T) -> std::io::Result<()>
-where T : Write + Seek {
-    target.write("\0JOO".as_bytes())?;
-    let version : [u8;3] = [
-        VERSION.major as u8,
-        VERSION.minor as u8,
-        VERSION.patch as u8
-    ];
-    target.write(&version[..])?;
-    target.write(&(ir.ext_fn.len() as u64).to_le_bytes())?;
-    let ext_pos = target.stream_position()?;
-    target.write(&[0;8][..])?;
-    target.write(&(ir.blocks.len() as u64).to_le_bytes())?;
-    let block_pos = target.stream_position()?;
-    target.write(&[0;8][..])?;
-    let pos = target.stream_position()?;
-    target.seek(SeekFrom::Start(ext_pos))?;
-    target.write(&pos.to_le_bytes())?;
-    target.seek(SeekFrom::Start(pos))?;
-    for f in ir.ext_fn {
-        target.write(&(f.0.len() as u32).to_le_bytes())?;
-        target.write(f.0.as_bytes())?;
-        target.write(&[f.1][..])?;
-        target.write(&[f.2 as u8][..])?;
+pub const MAGIC : &[u8; 4] = b"\0JOO";
+/// position of the checksum in the header
+pub const CHECKSUM_POS : usize = 4 + 3 + 4 + 4;
+/// magic, version, section count, offset of the section directory and checksum
+pub const HEADER_SIZE : usize = CHECKSUM_POS + blake3::OUT_LEN;
+
+/// blake3 hash of the object where the checksum is replaced by zeros
+pub fn checksum(object : &[u8]) -> [u8; blake3::OUT_LEN] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&object[..CHECKSUM_POS]);
+    hasher.update(&[0; blake3::OUT_LEN]);
+    hasher.update(&object[HEADER_SIZE..]);
+    *hasher.finalize().as_bytes()
+}
+
+fn u32_of(value : usize) -> std::io::Result<u32> {
+    u32::try_from(value).map_err(|_| Error::new(ErrorKind::InvalidData, "the object is too large"))
+}
+
+fn push_u32(data : &mut Vec<u8>, value : usize) -> std::io::Result<()> {
+    data.extend_from_slice(&u32_of(value)?.to_le_bytes());
+    Ok(())
+}
+
+fn push_string(data : &mut Vec<u8>, string : &str) -> std::io::Result<()> {
+    push_u32(data, string.len())?;
+    data.extend_from_slice(string.as_bytes());
+    Ok(())
+}
+
+/// the sections of the object, the optional sections are only written if they are not empty
+fn sections(ir : IrObject) -> std::io::Result<Vec<RawSection>> {
+    let mut externs = vec![];
+    for f in ir.ext_fn.iter() {
+        push_string(&mut externs, &f.name)?;
+        push_string(&mut externs, &f.sig.to_string())?;
     }
-    let pos = target.stream_position()?;
-    target.seek(SeekFrom::Start(block_pos))?;
-    target.write(&pos.to_le_bytes())?;
-    target.seek(SeekFrom::Start(pos))?;
+    let mut blocks = vec![];
+    let mut instructions = vec![];
     for blk in ir.blocks.iter() {
-        target.write(&blk.instructions.len().to_le_bytes())?;
-        target.write(&blk.args.len().to_le_bytes())?;
-        for arg in blk.args.iter() {
-            target.write(&arg.to_le_bytes())?;
+        push_u32(&mut blocks, blk.instructions.len())?;
+        push_u32(&mut blocks, instructions.len())?;
+        for i in blk.instructions.iter() {
+            instructions.extend_from_slice(&i.to_bytes());
         }
-        target.write(&(0u64).to_le_bytes())?;
     }
-    target.seek(SeekFrom::Start(pos))?;
-    for blk in ir.blocks {
-        let entry_pos = target.stream_position()?;
-        let instr_pos = target.seek(SeekFrom::End(0))?;
-        for i in &blk.instructions {
-            target.write_all(&[0;7][..])?;
-            target.write(&[i.opcode().into()][..])?;
-            match i {
-                Instruction::Iconst(size, value)
-                    => {
-                        target.write(&size.to_le_bytes())?;
-                        target.write(&value.to_le_bytes())
-                    },
-                Instruction::Dupx(op)
-                    | Instruction::Icast(op)
-                    => {
-                        target.write(&op.to_le_bytes())?;
-                        target.write(&[0x00;16])
-                    },
-                Instruction::Br(op)
-                    | Instruction::Call(op)
-                    => {
-                        target.write(&op.to_le_bytes())?;
-                        target.write(&[0x00;16])
-                    },
-                Instruction::Briz(b1, b2) => {
-                    target.write(&b1.to_le_bytes())?;
-                    target.write(&b2.to_le_bytes())?;
-                    target.write(&[0x00;8])
-                },
-                _ => target.write(&[0x00;24][..])
-            }?;
+    let section = |tag, flags, data| RawSection { tag, flags, data };
+    let mut sections = vec![
+        section(section::EXTERNS, REQUIRED, externs),
+        section(section::BLOCKS, REQUIRED, blocks),
+        section(section::LOCAL_VARS, REQUIRED, ir.local_vars),
+        section(section::INSTRUCTIONS, REQUIRED, instructions)
+    ];
+    if !ir.source_map.is_empty() {
+        let mut source_map = vec![];
+        for l in ir.source_map {
+            for value in [l.block, l.instruction, l.line, l.column] {
+                source_map.extend_from_slice(&value.to_le_bytes());
+            }
         }
-        target.seek(SeekFrom::Start(entry_pos))?;
-        target.seek_relative(16 + 8 * (blk.args.len() as i64))?;
-        target.write(&instr_pos.to_le_bytes())?;
+        sections.push(section(section::SOURCE_MAP, 0, source_map));
     }
-    Ok(())
+    if !ir.strings.is_empty() {
+        sections.push(section(section::STRINGS, 0, ir.strings));
+    }
+    if !ir.metadata.is_empty() {
+        let mut metadata = vec![];
+        for (key, value) in ir.metadata.iter() {
+            push_string(&mut metadata, key)?;
+            push_string(&mut metadata, value)?;
+        }
+        sections.push(section(section::METADATA, 0, metadata));
+    }
+    sections.extend(ir.sections);
+    Ok(sections)
+}
+
+pub fn write<T>(ir : IrObject, target : &mut T) -> std::io::Result<()>
+where T : Write + Seek {
+    let sections = sections(ir)?;
+    let mut output = Vec::new();
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&[VERSION.major as u8, VERSION.minor as u8, VERSION.patch as u8]);
+    push_u32(&mut output, sections.len())?;
+    push_u32(&mut output, HEADER_SIZE)?;
+    output.extend_from_slice(&[0; blake3::OUT_LEN]);
+    let mut offset = HEADER_SIZE + ENTRY_SIZE * sections.len();
+    for s in sections.iter() {
+        output.extend_from_slice(&s.tag);
+        push_u32(&mut output, offset)?;
+        push_u32(&mut output, s.data.len())?;
+        output.extend_from_slice(&s.flags.to_le_bytes());
+        offset += s.data.len();
+    }
+    for s in sections {
+        output.extend_from_slice(&s.data);
+    }
+    u32_of(output.len())?;
+    let checksum = checksum(&output);
+    output[CHECKSUM_POS..HEADER_SIZE].copy_from_slice(&checksum);
+    target.write_all(&output)
 }
@@ -0,0 +1,43 @@
+use std::{fs::{read_to_string, OpenOptions}, path::PathBuf};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use clio::{ClioPath, OutputPath};
+use jolang_shared::ir::{assembler::assemble, serialize::from_json, writer::write};
+
+#[derive(Args)]
+pub struct AsmArgs {
+    /// path to the textual ir to assemble
+    #[clap(value_parser = clap::value_parser!(ClioPath).exists().is_file())]
+    pub file : ClioPath,
+    /// path of the generated object, the file with the joo extension by default
+    #[clap(short, long, value_parser)]
+    pub object_file : Option<OutputPath>,
+    /// the file is the json of an object, as printed by `jolang show --format json`
+    #[clap(long)]
+    pub from_json : bool
+}
+
+pub fn assemble_file(args : &AsmArgs) -> Result<()> {
+    if !args.file.is_local() {
+        return Err(anyhow!("please input a local file"))
+    }
+    let path = PathBuf::from(args.file.as_os_str());
+    let object_path = match &args.object_file {
+        Some(p) if p.is_local() => PathBuf::from(p.path().as_os_str()),
+        _ => path.with_extension("joo")
+    };
+    let text = read_to_string(&path)?;
+    let object = if args.from_json {
+        from_json(&text)
+    }else {
+        assemble(&text)
+    }.map_err(|e| anyhow!("{} : {:#}", path.display(), e))?;
+    println!("assembling {} to {}...", path.display(), object_path.display());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(object_path)?;
+    write(object, &mut file)?;
+    Ok(())
+}
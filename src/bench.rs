@@ -0,0 +1,192 @@
+use std::{io::Cursor, time::{Duration, Instant}};
+use anyhow::Result;
+use clap::Args;
+use clio::ClioPath;
+use jolang_runtime::{default_runtime, runtime_by_name, Runtime};
+use jolang_shared::ffi::jolang_std::{capture_output, provide_input};
+use jolang_shared::ir::{reader::read, writer::write};
+use jolangc::ast::AstBuilder;
+use jolangc::compiler_error::CompilerError;
+use jolangc::generator::{Generate, IrGenerator};
+use jolangc::hir::HirBuilder;
+use jolangc::lexer::Lexer;
+use jolangc::source_buffer::SourceBuffer;
+use serde_json::{json, Value};
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// path to the source to benchmark
+    #[clap(value_parser = clap::value_parser!(ClioPath).exists().is_file())]
+    pub file : ClioPath,
+    /// number of measured iterations
+    #[clap(short = 'n', long, default_value_t = 10)]
+    pub iterations : usize,
+    /// number of iterations run before the measures
+    #[clap(short, long, default_value_t = 2)]
+    pub warmup : usize,
+    /// runtime used to run the program, the default one of the platform if not given
+    #[clap(short, long)]
+    pub runtime : Option<String>,
+    /// line read by input, can be repeated
+    #[clap(short, long)]
+    pub input : Vec<String>,
+    /// print the results as json
+    #[clap(long)]
+    pub json : bool
+}
+
+/// the measured phases, parse includes the lexing because the parser reads the tokens from the lexer
+pub const PHASES : [&str; 8] = ["lex", "parse", "analyze", "generate", "write", "read", "prepare", "execute"];
+
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub mean : Duration,
+    pub median : Duration,
+    pub stddev : Duration,
+    pub min : Duration,
+    pub max : Duration
+}
+
+impl Stats {
+    pub fn new(samples : &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len().max(1) as f64;
+        let mean = sorted.iter().map(|d| d.as_secs_f64()).sum::<f64>() / n;
+        let variance = sorted.iter().map(|d| (d.as_secs_f64() - mean).powi(2)).sum::<f64>() / n;
+        let median = match sorted.len() {
+            0 => Duration::ZERO,
+            len if len % 2 == 0 => (sorted[len / 2 - 1] + sorted[len / 2]) / 2,
+            len => sorted[len / 2]
+        };
+        Self {
+            mean : Duration::from_secs_f64(mean),
+            median,
+            stddev : Duration::from_secs_f64(variance.sqrt()),
+            min : sorted.first().copied().unwrap_or_default(),
+            max : sorted.last().copied().unwrap_or_default()
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "mean_ns" : self.mean.as_nanos() as u64,
+            "median_ns" : self.median.as_nanos() as u64,
+            "stddev_ns" : self.stddev.as_nanos() as u64,
+            "min_ns" : self.min.as_nanos() as u64,
+            "max_ns" : self.max.as_nanos() as u64
+        })
+    }
+}
+
+fn timed<T>(f : impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// compile and run the source once, returns the duration of each phase in the order of PHASES,
+/// none for the preparation when the runtime doesn't have one
+pub fn iteration(source : &SourceBuffer, runtime : &mut dyn Runtime, input : &[String]) -> Result<[Option<Duration>; 8]> {
+    let (tokens, lex) = timed(|| Lexer::new(source).collect::<Result<Vec<_>, CompilerError>>());
+    let tokens = tokens?;
+    let (program, parse) = timed(|| AstBuilder::from_tokens(source, tokens).parse_program());
+    let program = program?;
+    let (hir, analyze) = timed(|| HirBuilder::new().build(&program));
+    let hir = hir?;
+    let (object, generate) = timed(|| {
+        let mut generator = IrGenerator::from(&hir);
+        hir.generate(&mut generator).map(|_| generator.into_ir())
+    });
+    let object = object.map_err(|e| e.with_source(source))?;
+    let mut buffer : Cursor<Vec<u8>> = Cursor::new(vec![]);
+    let (result, write_time) = timed(|| write(object, &mut buffer));
+    result?;
+    let bytes = buffer.into_inner();
+    let (object, read_time) = timed(|| read(&mut Cursor::new(bytes)));
+    let object = object?;
+    // the output of the program is not part of the results
+    let (result, _) = provide_input(input.to_vec(), || capture_output(|| runtime.run_timed(object)));
+    let (_, times) = result?;
+    Ok([Some(lex), Some(parse), Some(analyze), Some(generate), Some(write_time), Some(read_time), times.prepare, Some(times.execute)])
+}
+
+/// run the iterations after the warmup, each one with a new runtime.
+/// the stats of a phase are none when the runtime doesn't have it
+pub fn bench(source : &SourceBuffer, args : &BenchArgs) -> Result<Vec<Option<Stats>>> {
+    let mut samples = vec![Some(vec![]); PHASES.len()];
+    for i in 0..args.warmup + args.iterations {
+        let mut runtime = match &args.runtime {
+            Some(name) => runtime_by_name(name)?,
+            None => default_runtime()
+        };
+        let times = iteration(source, runtime.as_mut(), &args.input)?;
+        if i >= args.warmup {
+            for (phase, time) in samples.iter_mut().zip(times) {
+                match time {
+                    Some(time) => if let Some(phase) = phase {
+                        phase.push(time)
+                    },
+                    None => *phase = None
+                }
+            }
+        }
+    }
+    Ok(samples.iter().map(|s| s.as_deref().map(Stats::new)).collect())
+}
+
+pub fn print_results(args : &BenchArgs, stats : &[Option<Stats>]) {
+    let runtime = args.runtime.as_deref().unwrap_or("default");
+    if args.json {
+        let phases : serde_json::Map<String, Value> = PHASES.iter()
+            .zip(stats)
+            .map(|(name, s)| (name.to_string(), s.as_ref().map_or(Value::Null, Stats::to_json)))
+            .collect();
+        println!("{}", json!({
+            "file" : args.file.path().display().to_string(),
+            "runtime" : runtime,
+            "iterations" : args.iterations,
+            "warmup" : args.warmup,
+            "phases" : phases
+        }));
+        return
+    }
+    println!("{} ({} runtime, {} iterations after {} warmup)", args.file.path().display(), runtime, args.iterations, args.warmup);
+    println!("{:<10}{:>14}{:>14}{:>14}{:>14}{:>14}", "phase", "mean", "median", "stddev", "min", "max");
+    for (name, s) in PHASES.iter().zip(stats) {
+        match s {
+            Some(s) => println!("{:<10}{:>14?}{:>14?}{:>14?}{:>14?}{:>14?}", name, s.mean, s.median, s.stddev, s.min, s.max),
+            None => println!("{:<10}{:>14}", name, "unsupported")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let samples : Vec<Duration> = [4, 1, 3, 2].iter().map(|ms| Duration::from_millis(*ms)).collect();
+        let stats = Stats::new(&samples);
+        assert_eq!(stats.mean, Duration::from_micros(2500));
+        assert_eq!(stats.median, Duration::from_micros(2500));
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(4));
+        // sqrt(1.25) ms
+        assert_eq!(stats.stddev.as_micros(), 1118);
+        assert_eq!(Stats::new(&samples[..3]).median, Duration::from_millis(3));
+        assert_eq!(Stats::new(&[]).mean, Duration::ZERO);
+        assert_eq!(stats.to_json()["median_ns"], 2_500_000);
+    }
+
+    #[test]
+    fn test_iteration() {
+        let source = SourceBuffer::from_string("bench.jol", "let a = input();\nprint(a * 2);\nreturn 0;".to_string());
+        let mut runtime = runtime_by_name("interpreter").unwrap();
+        let times = iteration(&source, runtime.as_mut(), &["21".to_string()]).unwrap();
+        // the interpreter has no preparation
+        let phases : Vec<bool> = times.iter().map(|t| t.is_some()).collect();
+        assert_eq!(phases, [true, true, true, true, true, true, false, true]);
+    }
+}
@@ -0,0 +1,13 @@
+use clap::{Args, Subcommand};
+
+#[derive(Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command : CacheCommands
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// remove every cached object
+    Clean
+}
@@ -0,0 +1,111 @@
+use std::{path::{Path, PathBuf}, sync::atomic::{AtomicUsize, Ordering}, thread};
+use anyhow::Result;
+use clap::Args;
+use clio::ClioPath;
+use jolangc::analyze_all;
+use jolangc::source_buffer::SourceBuffer;
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// source files or directories to check, the current directory by default.
+    /// every error of the statements is reported but a syntax error stops the check of its file
+    #[clap(value_parser = clap::value_parser!(ClioPath).exists())]
+    pub files : Vec<ClioPath>,
+    /// number of files checked at the same time, the number of cpus by default
+    #[clap(short, long)]
+    pub jobs : Option<usize>
+}
+
+/// analyze a source without generating it, returns the diagnostics of every statement.
+/// a syntax error stops the analysis so it is the last diagnostic
+pub fn check_file(path : &Path) -> Result<Vec<String>> {
+    let source = SourceBuffer::open(path.to_path_buf())?;
+    Ok(analyze_all(&source).err().unwrap_or_default().iter().map(|e| e.to_string()).collect())
+}
+
+/// check the files in parallel, the results are in the order of the files
+pub fn check_files(files : &[PathBuf], jobs : usize) -> Vec<Result<Vec<String>>> {
+    let next = AtomicUsize::new(0);
+    let mut results : Vec<(usize, Result<Vec<String>>)> = thread::scope(|scope| {
+        let workers : Vec<_> = (0..jobs.clamp(1, files.len().max(1)))
+            .map(|_| scope.spawn(|| {
+                let mut results = vec![];
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    match files.get(i) {
+                        Some(path) => results.push((i, check_file(path))),
+                        None => return results
+                    }
+                }
+            }))
+            .collect();
+        workers.into_iter()
+            .flat_map(|w| w.join().expect("a check thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|r| r.0);
+    results.into_iter().map(|r| r.1).collect()
+}
+
+/// print the diagnostics of the files, returns true if none has an error
+pub fn run_check(args : &CheckArgs) -> Result<bool> {
+    let paths : Vec<PathBuf> = if args.files.is_empty() {
+        vec![PathBuf::from(".")]
+    }else {
+        args.files.iter().map(|f| PathBuf::from(f.as_os_str())).collect()
+    };
+    let files = crate::test::find_sources(&paths)?;
+    let jobs = args.jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    let mut failed = 0;
+    for (path, result) in files.iter().zip(check_files(&files, jobs)) {
+        match result {
+            Ok(diagnostics) if diagnostics.is_empty() => (),
+            Ok(diagnostics) => {
+                for diagnostic in diagnostics {
+                    eprint!("{}", diagnostic);
+                }
+                failed += 1;
+            },
+            Err(e) => {
+                eprintln!("error : cannot read {} : {}", path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    eprintln!("checked {} files, {} with errors", files.len(), failed);
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_check_files() {
+        let dir = std::env::temp_dir().join(format!("jolang-check-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.jol"), "let a = 1;\nprint(a);").unwrap();
+        fs::write(dir.join("sub/b.jol"), "print(b);").unwrap();
+        fs::write(dir.join("sub/c.jol"), "let c = ;").unwrap();
+        fs::write(dir.join("sub/d.jol"), "let d = e;\nprint(d);\nif (1) { print(f); }\nlet g : i9 = 0;\nprint(g);").unwrap();
+        fs::write(dir.join("notes.txt"), "not a source").unwrap();
+        // the file given with its directory is only checked once
+        let files = crate::test::find_sources(&[dir.join("a.jol"), dir.clone()]).unwrap();
+        assert_eq!(files.len(), 4);
+        for jobs in [1, 2, 8] {
+            let results : Vec<Vec<String>> = check_files(&files, jobs).into_iter().map(|r| r.unwrap()).collect();
+            assert_eq!(results[0], Vec::<String>::new());
+            assert_eq!(results[1].len(), 1);
+            assert!(results[1][0].contains("unknown variable : b"));
+            assert_eq!(results[2].len(), 1);
+            // the invalid declarations still declare their variables
+            let messages : Vec<&str> = results[3].iter().map(|d| d.lines().next().unwrap_or_default()).collect();
+            assert_eq!(messages.len(), 3, "{:?}", results[3]);
+            assert!(messages[0].contains("unknown variable : e"), "{}", messages[0]);
+            assert!(messages[1].contains("unknown variable : f"), "{}", messages[1]);
+            assert!(messages[2].contains("unknown type : i9"), "{}", messages[2]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
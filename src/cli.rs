@@ -2,6 +2,15 @@ use clap::{Parser, Subcommand};
 use crate::run::RunArgs;
 use crate::compile::CompileArgs;
 use crate::show::ShowArgs;
+use crate::explain::ExplainArgs;
+use crate::fix::FixArgs;
+use crate::fmt::FmtArgs;
+use crate::cache::CacheArgs;
+use crate::test::TestArgs;
+use crate::bench::BenchArgs;
+use crate::check::CheckArgs;
+use crate::asm::AsmArgs;
+use crate::verify::VerifyArgs;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -18,5 +27,27 @@ pub enum Commands {
     /// run objects
     Run(RunArgs),
     /// print the ir
-    Show(ShowArgs)
+    Show(ShowArgs),
+    /// print the long description of an error code
+    Explain(ExplainArgs),
+    /// apply the suggested fixes of the compiler errors
+    Fix(FixArgs),
+    /// format source files
+    Fmt(FmtArgs),
+    /// start a language server on the standard input and output
+    Lsp,
+    /// start an interactive session
+    Repl,
+    /// manage the cache of the objects compiled by run
+    Cache(CacheArgs),
+    /// run the test blocks and the golden tests of sources
+    Test(TestArgs),
+    /// measure the time of each phase of the compilation and of the run of a source
+    Bench(BenchArgs),
+    /// report the errors of sources without compiling them
+    Check(CheckArgs),
+    /// assemble textual ir into an object file
+    Asm(AsmArgs),
+    /// check the ir of objects without running them
+    Verify(VerifyArgs)
 }
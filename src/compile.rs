@@ -1,5 +1,7 @@
+use anyhow::Result;
 use clap::Args;
 use clio::{ClioPath, OutputPath};
+use jolang_shared::ir::passes::{PassManager, MAX_LEVEL};
 
 #[derive(Args)]
 pub struct CompileArgs {
@@ -8,5 +10,23 @@ pub struct CompileArgs {
     pub file : ClioPath,
     /// path of the generated object path
     #[clap(short, long, value_parser)]
-    pub object_file : Option<OutputPath>
+    pub object_file : Option<OutputPath>,
+    /// run again when the file changes
+    #[clap(short, long)]
+    pub watch : bool,
+    /// optimization level, from 0 (no optimization) to 2
+    #[clap(short = 'O', default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=MAX_LEVEL as i64))]
+    pub opt_level : u8,
+    /// comma separated optimization passes to run instead of the ones of the level
+    #[clap(long, conflicts_with = "opt_level")]
+    pub passes : Option<String>
+}
+
+impl CompileArgs {
+    pub fn pass_manager(&self) -> Result<PassManager> {
+        match &self.passes {
+            Some(list) => PassManager::from_list(list),
+            None => PassManager::for_level(self.opt_level)
+        }
+    }
 }
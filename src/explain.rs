@@ -0,0 +1,7 @@
+use clap::Args;
+
+#[derive(Args)]
+pub struct ExplainArgs {
+    /// error code to explain (e.g. E0001)
+    pub code : String,
+}
@@ -0,0 +1,38 @@
+use std::{fs::write, path::PathBuf};
+use anyhow::Result;
+use clap::Args;
+use clio::ClioPath;
+use jolangc::fix::{diff, fix_source};
+use jolangc::source_buffer::SourceBuffer;
+
+#[derive(Args)]
+pub struct FixArgs {
+    /// path to the file to fix
+    #[clap(value_parser = clap::value_parser!(ClioPath).exists().is_file())]
+    pub file : ClioPath,
+    /// print the changes as a diff instead of writing them
+    #[clap(long)]
+    pub dry_run : bool
+}
+
+/// fix a source in place or print the diff, returns false if an error can't be fixed, it is printed
+pub fn run_fix(args : &FixArgs) -> Result<bool> {
+    let path = PathBuf::from(args.file.as_os_str());
+    let source = SourceBuffer::open(path.clone())?;
+    let result = fix_source(&source);
+    for e in &result.fixed {
+        eprintln!("fixed {} at line {} : {}", e.kind().code(), e.line_number(), e.message());
+    }
+    if args.dry_run {
+        print!("{}", diff(path.to_str().unwrap_or("error"), &source.buffer, &result.source));
+    }else if result.source != source.buffer {
+        write(&path, &result.source)?;
+    }
+    match result.remaining {
+        Some(e) => {
+            eprint!("{}", e);
+            Ok(false)
+        },
+        None => Ok(true)
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fs::write, path::PathBuf};
+use anyhow::Result;
+use clap::Args;
+use clio::ClioPath;
+use jolangc::fix::diff;
+use jolangc::formatter::format;
+use jolangc::source_buffer::SourceBuffer;
+
+#[derive(Args)]
+pub struct FmtArgs {
+    /// paths to the files to format, the standard input is formatted if there is none or with "-"
+    #[clap(value_parser = clap::value_parser!(ClioPath))]
+    pub files : Vec<ClioPath>,
+    /// print the changes as a diff and fail if a file isn't formatted instead of writing it
+    #[clap(long)]
+    pub check : bool
+}
+
+/// the formatted source, none if it has errors, they are printed
+fn format_source(source : &SourceBuffer) -> Option<String> {
+    match format(source) {
+        Ok(formatted) => Some(formatted),
+        Err(e) => {
+            eprint!("{}", e);
+            None
+        }
+    }
+}
+
+/// format the files in place or check them, the standard input is printed formatted,
+/// returns false if a source has errors or isn't formatted with --check
+pub fn run_fmt(args : &FmtArgs) -> Result<bool> {
+    if args.files.is_empty() || args.files.iter().any(|f| f.is_std()) {
+        let source = SourceBuffer::from_stdin()?;
+        let Some(formatted) = format_source(&source) else {
+            return Ok(false)
+        };
+        if !args.check {
+            print!("{}", formatted);
+            return Ok(true)
+        }
+        print!("{}", diff("<stdin>", &source.buffer, &formatted));
+        return Ok(formatted == source.buffer)
+    }
+    let mut formatted_all = true;
+    for file in &args.files {
+        let path = PathBuf::from(file.as_os_str());
+        let source = SourceBuffer::open(path.clone())?;
+        let Some(formatted) = format_source(&source) else {
+            return Ok(false)
+        };
+        if formatted == source.buffer {
+            continue
+        }
+        if args.check {
+            print!("{}", diff(path.to_str().unwrap_or("error"), &source.buffer, &formatted));
+            formatted_all = false;
+        }else {
+            write(&path, &formatted)?;
+        }
+    }
+    Ok(formatted_all)
+}
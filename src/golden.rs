@@ -0,0 +1,143 @@
+//! golden tests : programs with their expected input, output and exit code in header comments
+//! ```text
+//! // stdin: 10
+//! // expect: 55
+//! // exit: 1
+//! ```
+//! there is one directive per line of input or output, the exit code is 0 by default
+use std::io::Cursor;
+use anyhow::Result;
+use jolang_shared::ffi::jolang_std::{capture_output, provide_input};
+use jolang_shared::ir::{reader::read, writer::write, IrObject};
+use jolangc::compile;
+use jolangc::fix::diff;
+use jolangc::source_buffer::SourceBuffer;
+
+const STDIN : &str = "// stdin:";
+const EXPECT : &str = "// expect:";
+const EXIT : &str = "// exit:";
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Expectations {
+    pub stdin : Vec<String>,
+    pub stdout : String,
+    pub exit : i64
+}
+
+/// the lines of the header : the comments before the code
+fn header(source : &str) -> impl Iterator<Item = &str> {
+    source.lines()
+        .enumerate()
+        .take_while(|(i, l)| l.trim_start().starts_with("//") || (*i == 0 && l.starts_with("#!")))
+        .map(|(_, l)| l)
+}
+
+fn directive<'a>(line : &'a str, name : &str) -> Option<&'a str> {
+    let value = line.trim().strip_prefix(name)?;
+    Some(value.strip_prefix(' ').unwrap_or(value))
+}
+
+impl Expectations {
+    /// the expectations of a source, none if its header has no directive
+    pub fn parse(source : &str) -> Result<Option<Self>> {
+        let mut expectations = Self::default();
+        let mut found = false;
+        for line in header(source) {
+            if let Some(value) = directive(line, STDIN) {
+                expectations.stdin.push(value.to_string());
+            }else if let Some(value) = directive(line, EXPECT) {
+                expectations.stdout.push_str(value);
+                expectations.stdout.push('\n');
+            }else if let Some(value) = directive(line, EXIT) {
+                expectations.exit = value.trim().parse()
+                    .map_err(|_| anyhow::anyhow!("invalid exit code : {}", value))?;
+            }else {
+                continue;
+            }
+            found = true;
+        }
+        Ok(if found { Some(expectations) } else { None })
+    }
+}
+
+/// replace the expected output and exit code of a source, the other comments are kept
+pub fn bless(source : &str, stdout : &str, exit : i64) -> String {
+    let header_len = header(source).count();
+    let mut lines : Vec<String> = source.lines()
+        .take(header_len)
+        .filter(|l| directive(l, EXPECT).is_none() && directive(l, EXIT).is_none())
+        .map(|l| l.to_string())
+        .collect();
+    for line in stdout.lines() {
+        lines.push(format!("{} {}", EXPECT, line).trim_end().to_string());
+    }
+    if exit != 0 {
+        lines.push(format!("{} {}", EXIT, exit));
+    }
+    lines.extend(source.lines().skip(header_len).map(|l| l.to_string()));
+    let mut blessed = lines.join("\n");
+    if source.ends_with('\n') {
+        blessed.push('\n');
+    }
+    blessed
+}
+
+/// compile a source to an object, read it back and run it with the expected input
+/// returns the output and the exit code
+pub fn run_golden(source : &SourceBuffer, stdin : &[String], run : &dyn Fn(IrObject) -> Result<i64>) -> Result<(String, i64)> {
+    let mut object : Cursor<Vec<u8>> = Cursor::new(vec![]);
+    write(compile(source)?, &mut object)?;
+    let object = read(&mut Cursor::new(object.into_inner()))?;
+    let (code, output) = provide_input(stdin.to_vec(), || capture_output(|| run(object)));
+    Ok((output, code?))
+}
+
+/// the differences between the expected and the actual results, none if they are the same
+pub fn compare(name : &str, expected : &Expectations, stdout : &str, exit : i64) -> Option<String> {
+    let mut message = String::new();
+    if expected.stdout != stdout {
+        message.push_str(&diff(name, &expected.stdout, stdout));
+    }
+    if expected.exit != exit {
+        message.push_str(&format!("expected the exit code {} but found {}\n", expected.exit, exit));
+    }
+    if message.is_empty() { None } else { Some(message) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE : &str = "// sum of two numbers\n// stdin: 1\n// stdin:  2\n// expect: 3\n// exit: 1\nprint(input() + input());\n// expect: not in the header\nreturn 1;\n";
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Expectations::parse(SOURCE).unwrap(), Some(Expectations {
+            stdin : vec!["1".to_string(), " 2".to_string()],
+            stdout : "3\n".to_string(),
+            exit : 1
+        }));
+        assert_eq!(Expectations::parse("// a comment\nprint(1);").unwrap(), None);
+        assert_eq!(Expectations::parse("#!/usr/bin/env jolang\n// exit: 2\n").unwrap().unwrap().exit, 2);
+        assert!(Expectations::parse("// exit: two").is_err());
+    }
+
+    #[test]
+    fn test_bless() {
+        let blessed = bless(SOURCE, "4\n\n5\n", 0);
+        assert_eq!(blessed, "// sum of two numbers\n// stdin: 1\n// stdin:  2\n// expect: 4\n// expect:\n// expect: 5\nprint(input() + input());\n// expect: not in the header\nreturn 1;\n");
+        let expectations = Expectations::parse(&blessed).unwrap().unwrap();
+        assert_eq!(expectations.stdout, "4\n\n5\n");
+        assert_eq!(expectations.exit, 0);
+        assert_eq!(bless(&blessed, "4\n\n5\n", 0), blessed);
+    }
+
+    #[test]
+    fn test_compare() {
+        let expected = Expectations::parse(SOURCE).unwrap().unwrap();
+        assert_eq!(compare("sum.jol", &expected, "3\n", 1), None);
+        let message = compare("sum.jol", &expected, "4\n", 0).unwrap();
+        assert!(message.contains("-3\n+4\n"));
+        assert!(message.ends_with("expected the exit code 1 but found 0\n"));
+    }
+}
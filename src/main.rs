@@ -1,24 +1,103 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 pub mod compile;
 mod cli;
 pub mod run;
 pub mod show;
+pub mod explain;
+pub mod fix;
+pub mod fmt;
+pub mod repl;
+pub mod cache;
+pub mod watch;
+pub mod test;
+pub mod golden;
+pub mod bench;
+pub mod check;
+pub mod asm;
+pub mod verify;
 use cli::{Cli, Commands};
-use jolangc::build;
-use std::{i32, path::PathBuf, process::exit, fs::OpenOptions};
+use cache::CacheCommands;
+use jolangc::{build, compile};
+use jolangc::cache::Cache;
+use jolangc::compiler_error::CompilerError;
+use jolangc::compiler_error::CompilerErrorKind;
+use jolangc::source_buffer::SourceBuffer;
+use std::{i32, env::args_os, ffi::OsString, fs::File, path::{Path, PathBuf}, process::exit, io::{stdin, stdout, Read}};
 use anyhow::{anyhow, Result};
 use clio::OutputPath;
-use jolang_runtime::run;
-use jolang_shared::ir::reader::read;
+use jolang_runtime::{run, run_object};
+use jolang_shared::ir::passes::PassManager;
+use jolang_shared::ir::writer::MAGIC;
+
+/// compile a source into an object file, the errors are printed
+fn build_file(path : &Path, object_path : &Path, passes : &PassManager) -> bool {
+    println!("building {} to {}...", path.to_str().unwrap_or("error"), object_path.to_str().unwrap_or("error"));
+    match build(path.to_path_buf(), object_path.to_path_buf(), passes) {
+        Ok(()) => true,
+        Err(e) => {
+            eprint!("{}", e);
+            false
+        }
+    }
+}
+
+/// true if the file starts like an object, the other files are sources
+fn is_object(path : &Path) -> bool {
+    let mut magic = [0; 4];
+    File::open(path).and_then(|mut f| f.read_exact(&mut magic)).is_ok() && &magic == MAGIC
+}
+
+/// `jolang script` (used by the shebang of scripts) is `jolang run script`,
+/// the first argument is a script if it is a .jol or an existing file that isn't named like a command
+fn insert_implicit_run(args : &mut Vec<OsString>) {
+    let is_script = args.get(1).is_some_and(|a| {
+        let path = Path::new(a);
+        path.extension().is_some_and(|e| e == "jol")
+            || (path.is_file() && Cli::command().find_subcommand(a).is_none())
+    });
+    if is_script {
+        args.insert(1, OsString::from("run"));
+    }
+}
+
+/// run an object or a source, returns the exit code of the program or none if the source has errors, they are printed
+fn run_file(path : &Path, source : Option<SourceBuffer>, cache : Option<&Cache>) -> Result<Option<i64>> {
+    // sources are compiled in memory
+    let source = match source {
+        Some(source) => source,
+        None if !is_object(path) => SourceBuffer::open(path.to_path_buf())?,
+        None => {
+            println!("loading {}...", path.to_str().unwrap_or("error"));
+            return Ok(Some(run(path.to_path_buf())?))
+        }
+    };
+    let object = match cache {
+        Some(cache) => cache.compile(&source),
+        None => compile(&source).map_err(|e| e.into())
+    };
+    match object {
+        Ok(object) => Ok(Some(run_object(object)?)),
+        Err(e) => match e.downcast_ref::<CompilerError>() {
+            Some(e) => {
+                eprint!("{}", e);
+                Ok(None)
+            },
+            None => Err(e)
+        }
+    }
+}
 
 fn main() -> Result<()>{
-    let cli = Cli::parse();
+    let mut args : Vec<OsString> = args_os().collect();
+    insert_implicit_run(&mut args);
+    let cli = Cli::parse_from(args);
     match cli.command {
         Commands::Compile(args) => {
             if !args.file.is_local() {
                 return Err(anyhow!("please input a local file"))
             }
             let path = PathBuf::from(args.file.as_os_str());
+            let passes = args.pass_manager()?;
             let mut object_file = match args.object_file {
                 Some(p) => p,
                 None => OutputPath::std()
@@ -33,16 +112,44 @@ fn main() -> Result<()>{
                     }
                 }
             }
-            println!("building {} to {}...", path.to_str().unwrap_or("error"), object_file);
-            return build(path, PathBuf::from(object_file.path().as_os_str()));
+            let object_path = PathBuf::from(object_file.path().as_os_str());
+            if args.watch {
+                return watch::watch(vec![path.clone()], || {
+                    build_file(&path, &object_path, &passes);
+                    Ok(())
+                })
+            }
+            if !build_file(&path, &object_path, &passes) {
+                exit(1)
+            }
+            Ok(())
         }
         Commands::Run(args) => {
-            if !args.file.is_local() {
+            if !args.file.is_local() && !args.file.is_std() {
                 return Err(anyhow!("please input a local file"))
             }
             let path = PathBuf::from(args.file.as_os_str());
-            println!("loading {}...", path.to_str().unwrap_or("error"));
-            let code = run(path)?;
+            let cache = Cache::user().filter(|_| !args.no_cache);
+            if args.watch {
+                if args.file.is_std() {
+                    return Err(anyhow!("the standard input can't be watched"))
+                }
+                return watch::watch(vec![path.clone()], || {
+                    if let Some(code) = run_file(&path, None, cache.as_ref())? {
+                        println!("[exited with code {}]", code);
+                    }
+                    Ok(())
+                })
+            }
+            let source = if args.file.is_std() {
+                Some(SourceBuffer::from_stdin()?)
+            }else {
+                None
+            };
+            let code = match run_file(&path, source, cache.as_ref())? {
+                Some(code) => code,
+                None => exit(1)
+            };
             if code >= i32::MAX.into() {
                 exit(i32::MAX)
             }else if code <= i32::MIN.into() {
@@ -51,19 +158,108 @@ fn main() -> Result<()>{
                 exit(code as i32);
             }
         },
-        Commands::Show(args) => {
+        Commands::Show(args) => show::run_show(&args),
+        Commands::Explain(args) => {
+            match CompilerErrorKind::from_code(&args.code) {
+                Some(kind) => {
+                    println!("{}\n", kind.code());
+                    print!("{}", kind.explain());
+                    Ok(())
+                },
+                None => Err(anyhow!("unknown error code : {}", args.code))
+            }
+        },
+        Commands::Fix(args) => {
             if !args.file.is_local() {
                 return Err(anyhow!("please input a local file"))
             }
-            let path = PathBuf::from(args.file.as_os_str());
-            let mut file = OpenOptions::new()
-                .read(true)
-                .write(false)
-                .create(false)
-                .open(path)?;
-            let object = read(&mut file)?;
-            println!("{:?}", object);
+            if !fix::run_fix(&args)? {
+                exit(1)
+            }
+            Ok(())
+        },
+        Commands::Fmt(args) => {
+            if !fmt::run_fmt(&args)? {
+                exit(1)
+            }
+            Ok(())
+        },
+        Commands::Lsp => {
+            let code = jolangc::lsp::run(&mut stdin().lock(), &mut stdout().lock())?;
+            exit(code)
+        },
+        Commands::Repl => repl::run_repl(),
+        Commands::Cache(args) => {
+            let cache = Cache::user().ok_or_else(|| anyhow!("no cache directory, set JOLANG_CACHE_DIR"))?;
+            match args.command {
+                CacheCommands::Clean => {
+                    let count = cache.clean()?;
+                    println!("removed {} objects from {}", count, cache.dir().display());
+                }
+            }
+            Ok(())
+        },
+        Commands::Test(args) => {
+            if !test::run_tests(&args, &run_object)? {
+                exit(1)
+            }
+            Ok(())
+        },
+        Commands::Bench(args) => {
+            let source = SourceBuffer::open(PathBuf::from(args.file.as_os_str()))?;
+            let stats = match bench::bench(&source, &args) {
+                Ok(stats) => stats,
+                Err(e) => match e.downcast_ref::<CompilerError>() {
+                    Some(e) => {
+                        eprint!("{}", e);
+                        exit(1)
+                    },
+                    None => return Err(e)
+                }
+            };
+            bench::print_results(&args, &stats);
+            Ok(())
+        },
+        Commands::Check(args) => {
+            if !check::run_check(&args)? {
+                exit(1)
+            }
+            Ok(())
+        },
+        Commands::Asm(args) => asm::assemble_file(&args),
+        Commands::Verify(args) => {
+            if !verify::run_verify(&args) {
+                exit(1)
+            }
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_implicit_run() {
+        let dir = std::env::temp_dir().join(format!("jolang-main-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("script");
+        std::fs::write(&script, "#!/usr/bin/env jolang\nprint(1);\n").unwrap();
+        let object = dir.join("object");
+        std::fs::write(&object, b"\0JOO\x00\x03\x00").unwrap();
+        let args = |first : &Path| {
+            let mut args = vec![OsString::from("jolang"), first.as_os_str().to_os_string()];
+            insert_implicit_run(&mut args);
+            args
+        };
+        let results = (args(&script), args(Path::new("missing.jol")), args(Path::new("missing")), args(Path::new("run")));
+        let objects = (is_object(&script), is_object(&object));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(results.0, ["jolang", "run", script.to_str().unwrap()]);
+        assert_eq!(results.1, ["jolang", "run", "missing.jol"]);
+        assert_eq!(results.2, ["jolang", "missing"]);
+        assert_eq!(results.3, ["jolang", "run"]);
+        assert_eq!(objects, (false, true));
+    }
+}
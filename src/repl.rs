@@ -0,0 +1,254 @@
+use std::io::{stdin, stdout, BufRead, Write};
+use anyhow::{anyhow, Result};
+use jolang_runtime::{interpreter::Interpreter, Runtime};
+use jolang_shared::ir::IrObject;
+use jolangc::ast::{AstBuilder, Program};
+use jolangc::generator::{Generate, IrGenerator};
+use jolangc::hir::{Expr, ExprKind, Hir, HirBuilder, Stmt, Type};
+use jolangc::lexer::Lexer;
+use jolangc::source_buffer::SourceBuffer;
+
+const HELP : &str = "\
+enter statements or expressions, the value of an expression is printed
+:ir [code]    print the ir of the code without running it, or the ir of the session
+:type <code>  print the type of an expression
+:reset        forget the variables
+:quit         exit the repl";
+
+/// the input can be evaluated : braces and parentheses are balanced
+pub fn is_complete(input : &str) -> bool {
+    let mut depth = 0;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.find(|c| *c == '\n');
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            },
+            _ => ()
+        }
+    }
+    depth <= 0
+}
+
+fn type_name(ty : Type) -> String {
+    match ty {
+        Type::Int(size) => format!("i{}", size * 8),
+        Type::Void => "void".to_string(),
+        Type::Never => "never".to_string()
+    }
+}
+
+/// the variables and the runtime kept between the inputs
+pub struct Session {
+    hir : Option<Hir>,
+    runtime : Interpreter
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            hir : None,
+            runtime : Interpreter::new()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.hir = None;
+        self.runtime.reset();
+    }
+
+    /// analyze the code after the previous inputs, the state is not changed
+    fn analyze(&self, code : &str) -> Result<Hir> {
+        let mut buffer = code.trim_end().to_string();
+        // the semicolon of the last expression is optional
+        if !buffer.is_empty() && !buffer.ends_with(';') && !buffer.ends_with('}') {
+            buffer.push(';');
+        }
+        let source = SourceBuffer::from_string("<repl>", buffer);
+        let program = AstBuilder::from(Lexer::new(&source)).parse_program()?;
+        let builder = match &self.hir {
+            Some(hir) => HirBuilder::resume(hir.clone()),
+            None => HirBuilder::new()
+        };
+        Ok(builder.build(&program)?)
+    }
+
+    fn generate(hir : &Hir) -> Result<IrObject> {
+        let mut generator = IrGenerator::from(hir);
+        hir.generate(&mut generator)?;
+        Ok(generator.into_ir())
+    }
+
+    /// run the code, returns the value of the last statement if it is an expression with a value
+    pub fn eval(&mut self, code : &str) -> Result<Option<i64>> {
+        let mut hir = self.analyze(code)?;
+        // the value is returned by the program
+        let value = match hir.body.pop() {
+            Some(Stmt::Expr(e)) if e.ty.size().is_some() => {
+                let value = match e.ty {
+                    Type::Int(8) => e,
+                    _ => Expr::new(ExprKind::Cast(Box::new(e)), Type::Int(8))
+                };
+                hir.body.push(Stmt::Return(value));
+                true
+            },
+            Some(s) => {
+                hir.body.push(s);
+                false
+            },
+            None => false
+        };
+        let object = Self::generate(&hir)?;
+        let result = self.runtime.run(object)?;
+        // the variables are declared once the run succeeded
+        self.hir = Some(hir);
+        Ok(if value { Some(result) } else { None })
+    }
+
+    /// type of the last statement of the code
+    pub fn type_of(&self, code : &str) -> Result<String> {
+        let hir = self.analyze(code)?;
+        Ok(match hir.body.last() {
+            Some(Stmt::Expr(e)) => type_name(e.ty),
+            Some(Stmt::Decl(id, _)) => type_name(Type::Int(hir.symbols[*id as usize].size)),
+            _ => type_name(Type::Void)
+        })
+    }
+
+    pub fn ir(&self, code : &str) -> Result<IrObject> {
+        Self::generate(&self.analyze(code)?)
+    }
+
+    /// handle a command or some code, returns the text to print
+    pub fn input(&mut self, input : &str) -> Result<Option<String>> {
+        let input = input.trim();
+        let (command, code) = match input.strip_prefix(':') {
+            Some(command) => command.split_once(char::is_whitespace).unwrap_or((command, "")),
+            None => return Ok(self.eval(input)?.map(|v| v.to_string()))
+        };
+        match command {
+            "ir" => Ok(Some(format!("{:?}", match &self.hir {
+                _ if !code.is_empty() => self.ir(code)?,
+                Some(hir) => Self::generate(hir)?,
+                None => Self::generate(&HirBuilder::new().build(&Program::new(vec![]))?)?
+            }))),
+            "type" if !code.is_empty() => Ok(Some(self.type_of(code)?)),
+            "reset" => {
+                self.reset();
+                Ok(None)
+            },
+            "help" => Ok(Some(HELP.to_string())),
+            "type" => Err(anyhow!("usage : :type <code>")),
+            _ => Err(anyhow!("unknown command : {}, try :help", command))
+        }
+    }
+}
+
+/// read the inputs from the standard input until the end or :quit
+pub fn run_repl() -> Result<()> {
+    let mut session = Session::new();
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { ">> " } else { ".. " });
+        stdout().flush()?;
+        let mut line = String::new();
+        if stdin().lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(())
+        }
+        input.push_str(&line);
+        if !is_complete(&input) {
+            continue;
+        }
+        let code = std::mem::take(&mut input);
+        match code.trim() {
+            "" => (),
+            ":quit" | ":q" => return Ok(()),
+            code => match session.input(code) {
+                Ok(Some(output)) => println!("{}", output),
+                Ok(None) => (),
+                Err(e) => match e.downcast_ref::<jolangc::compiler_error::CompilerError>() {
+                    Some(e) => eprint!("{}", e),
+                    None => eprintln!("error : {}", e)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(session : &mut Session, input : &str) -> Option<String> {
+        session.input(input).unwrap()
+    }
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new();
+        assert_eq!(output(&mut session, "let a = 2;"), None);
+        assert_eq!(output(&mut session, "a * 3"), Some("6".to_string()));
+        assert_eq!(output(&mut session, "a = a + 1;"), None);
+        assert_eq!(output(&mut session, "let b : i8 = 100; b + b"), Some("-56".to_string()));
+        // shadowing a previous input
+        assert_eq!(output(&mut session, "let a = a * 10;"), None);
+        assert_eq!(output(&mut session, "if (a == 30) { b } else { 0 }"), Some("100".to_string()));
+        assert_eq!(output(&mut session, "{\n    let c = a;\n    c + 1\n}"), Some("31".to_string()));
+        assert!(session.input("c").is_err());
+        assert!(session.input("1 / 0").is_err());
+        assert!(session.input("let d = 1 / 0;").is_err());
+        // a failed input doesn't change the variables
+        assert_eq!(output(&mut session, "a"), Some("30".to_string()));
+        assert!(session.input("d").is_err());
+        assert_eq!(output(&mut session, ":reset"), None);
+        assert!(session.input("a").is_err());
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut session = Session::new();
+        output(&mut session, "let a : i16 = 1;");
+        assert_eq!(output(&mut session, ":type a"), Some("i16".to_string()));
+        assert_eq!(output(&mut session, ":type a == 1"), Some("i8".to_string()));
+        assert_eq!(output(&mut session, ":type let b = input();"), Some("i64".to_string()));
+        assert_eq!(output(&mut session, ":type print(a)"), Some("void".to_string()));
+        assert!(output(&mut session, ":ir a + 1").unwrap().contains("iadd 2"));
+        // :type and :ir don't declare the variables
+        assert!(session.input("b").is_err());
+        assert!(session.input(":type").is_err());
+        // the ir of the session
+        assert!(output(&mut session, ":ir").unwrap().contains("istore 2"));
+        assert!(Session::new().input(":ir").is_ok());
+        assert!(session.input(":unknown").is_err());
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("let a = 1;"));
+        assert!(!is_complete("if (a) {"));
+        assert!(!is_complete("if (a) {\n print(a);"));
+        assert!(is_complete("if (a) {\n print(a);\n}"));
+        assert!(is_complete("let a = 1; // {"));
+        assert!(!is_complete("{ /* } */"));
+        assert!(!is_complete("print(1,"));
+    }
+}
@@ -3,7 +3,13 @@ use clio::ClioPath;
 
 #[derive(Args)]
 pub struct RunArgs {
-    /// path to the file to run
+    /// path to the object or the source to run (every file that is not an object is a source), - reads a source from the standard input
     #[clap(value_parser = clap::value_parser!(ClioPath).exists().is_file())]
     pub file : ClioPath,
+    /// always compile the source instead of using the cached object
+    #[clap(long)]
+    pub no_cache : bool,
+    /// run again when the file changes
+    #[clap(short, long)]
+    pub watch : bool
 }
@@ -1,9 +1,502 @@
-use clap::Args;
+use std::{collections::HashMap, fs::{read, read_to_string}, io::Cursor, path::{Path, PathBuf}};
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, ValueEnum};
 use clio::ClioPath;
+use serde_json::{json, Value};
+use jolang_shared::ir::instructions::{operand::BlkId, Instruction};
+use jolang_shared::ir::reader::{self, directory, layout, Layout};
+use jolang_shared::ir::section::{section_name, tag_to_string, SectionEntry, REQUIRED};
+use jolang_shared::ir::verifier::{analyze, type_name, Analysis};
+use jolang_shared::ir::writer::{CHECKSUM_POS, HEADER_SIZE};
+use jolang_shared::ir::dot::to_dot;
+use jolang_shared::ir::IrObject;
 
 #[derive(Args)]
 pub struct ShowArgs {
     /// path to the file to run
     #[clap(value_parser = clap::value_parser!(ClioPath).exists().is_file())]
     pub file : ClioPath,
+    /// print the layout and the version of the object instead of its ir
+    #[clap(long)]
+    pub format_version : bool,
+    /// list the sections of the object instead of its ir
+    #[clap(long)]
+    pub sections : bool,
+    /// print the control-flow graph in the graphviz dot format instead of the ir
+    #[clap(long)]
+    pub cfg : bool,
+    /// only print the block with this id
+    #[clap(long)]
+    pub block : Option<BlkId>,
+    /// the format of the ir, the text can be assembled again unless --raw is used
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    pub format : Format,
+    /// print the bytes of each instruction next to it
+    #[clap(long)]
+    pub raw : bool,
+    /// the source printed next to the instructions, the file with the jol extension by default
+    #[clap(long, value_parser = clap::value_parser!(ClioPath).exists().is_file())]
+    pub source : Option<ClioPath>
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json
+}
+
+/// the header of the object as written in the file
+pub struct Header {
+    pub layout : Layout,
+    pub version : [u8; 3],
+    pub size : usize,
+    /// the sections of the layout 2, empty for the older layout
+    pub sections : Vec<SectionEntry>,
+    pub checksum : Option<String>
+}
+
+impl Header {
+    pub fn read(object : &[u8]) -> Result<Self> {
+        let (layout, version) = layout(object)?;
+        let (sections, checksum) = if layout == Layout::V2 {
+            let sections = directory(object)?;
+            let checksum = object.get(CHECKSUM_POS..HEADER_SIZE).map(|c| c.iter().map(|b| format!("{:02x}", b)).collect());
+            (sections, checksum)
+        }else {
+            (vec![], None)
+        };
+        Ok(Self { layout, version, size : object.len(), sections, checksum })
+    }
+
+    fn version(&self) -> String {
+        format!("{}.{}.{}", self.version[0], self.version[1], self.version[2])
+    }
+}
+
+/// the compiler that wrote the object, the header only has the release of jolang without the pre-release
+fn writer(version : [u8; 3], object : Option<&IrObject>) -> String {
+    match object.and_then(|o| o.get_metadata("compiler")) {
+        Some(compiler) => compiler.to_string(),
+        None => format!("jolang {}.{}.{}", version[0], version[1], version[2])
+    }
+}
+
+/// what is printed of the object
+pub struct Options<'a> {
+    pub block : Option<BlkId>,
+    pub raw : bool,
+    /// the lines of the source of the object, the source map gives the line of each instruction
+    pub source : Option<&'a [String]>
+}
+
+fn flags_to_string(flags : u32) -> String {
+    match flags {
+        0 => "-".to_string(),
+        REQUIRED => "required".to_string(),
+        flags => format!("{:#x}", flags)
+    }
+}
+
+fn section_lines(entries : &[SectionEntry]) -> Vec<String> {
+    let mut lines = vec![format!("{:<6}{:>10}{:>10}  {:<10}content", "tag", "offset", "length", "flags")];
+    for e in entries {
+        lines.push(format!("{:<6}{:>10}{:>10}  {:<10}{}", tag_to_string(&e.tag), e.offset, e.length, flags_to_string(e.flags),
+            section_name(&e.tag).unwrap_or("user-defined")));
+    }
+    lines
+}
+
+pub fn print_sections(entries : &[SectionEntry]) {
+    for line in section_lines(entries) {
+        println!("{}", line);
+    }
+}
+
+fn successors(object : &IrObject, id : usize) -> Vec<BlkId> {
+    match object.blocks[id].instructions.get_last() {
+        Some(Instruction::Br(target)) => vec![*target],
+        Some(Instruction::Briz(target1, target2)) => vec![*target1, *target2],
+        _ => vec![]
+    }
+}
+
+/// the statistics of each block : the blocks it branches to and the blocks that branch to it
+struct Graph {
+    successors : Vec<Vec<BlkId>>,
+    predecessors : Vec<Vec<BlkId>>,
+    analysis : Analysis
+}
+
+impl Graph {
+    fn new(object : &IrObject) -> Self {
+        let successors : Vec<_> = (0..object.blocks.len()).map(|id| successors(object, id)).collect();
+        let mut predecessors = vec![vec![]; object.blocks.len()];
+        for (id, targets) in successors.iter().enumerate() {
+            for target in targets {
+                let list : &mut Vec<BlkId> = match predecessors.get_mut(*target as usize) {
+                    Some(list) => list,
+                    None => continue
+                };
+                if !list.contains(&(id as BlkId)) {
+                    list.push(id as BlkId);
+                }
+            }
+        }
+        Self { successors, predecessors, analysis : analyze(object) }
+    }
+
+    /// the stack of the block when it is entered, none if the block is unreachable
+    fn arguments(&self, id : usize) -> Option<Vec<String>> {
+        self.analysis.entries[id].as_ref().map(|stack| stack.iter().map(|s| type_name(*s)).collect())
+    }
+}
+
+fn block_list(ids : &[BlkId]) -> String {
+    ids.iter().map(|id| format!("B{}", id)).collect::<Vec<_>>().join(" ")
+}
+
+fn hex(bytes : &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+/// the name of the extern called by the instruction
+fn callee(object : &IrObject, instruction : &Instruction) -> Option<String> {
+    match instruction {
+        Instruction::Call(id) => Some(object.ext_fn.get(*id as usize).map_or_else(|| "unknown extern".to_string(), |f| f.name.clone())),
+        _ => None
+    }
+}
+
+/// the line of the source of each instruction
+fn lines(object : &IrObject) -> HashMap<(u32, u32), u32> {
+    object.source_map.iter().map(|l| ((l.block, l.instruction), l.line)).collect()
+}
+
+/// the ids of the blocks to print
+fn selected(object : &IrObject, block : Option<BlkId>) -> Result<Vec<usize>> {
+    match block {
+        Some(id) if (id as usize) < object.blocks.len() => Ok(vec![id as usize]),
+        Some(id) => bail!("the object has no block B{} ({} blocks)", id, object.blocks.len()),
+        None => Ok((0..object.blocks.len()).collect())
+    }
+}
+
+fn header_text(header : &Header, object : &IrObject) -> String {
+    let mut text = format!("// layout {:?} : {}\n", header.layout, header.layout.describe());
+    text += &format!("// written by {}, {} bytes\n", writer(header.version, Some(object)), header.size);
+    if let Some(checksum) = &header.checksum {
+        text += &format!("// {} sections, directory at offset {}, checksum {}\n", header.sections.len(), HEADER_SIZE, checksum);
+        for line in section_lines(&header.sections) {
+            text += &format!("//   {}\n", line);
+        }
+    }
+    for (key, value) in object.metadata.iter() {
+        text += &format!("// {} : {}\n", key, value);
+    }
+    text
+}
+
+fn block_text(object : &IrObject, graph : &Graph, id : usize, options : &Options) -> String {
+    let block = &object.blocks[id];
+    let count = block.instructions.len();
+    let mut stats = vec![format!("{} instruction{}", count, if count == 1 { "" } else { "s" })];
+    match graph.arguments(id) {
+        Some(args) => {
+            stats.push(format!("arguments ({})", args.join(", ")));
+            stats.push(format!("max stack depth {}", graph.analysis.max_depths[id]));
+        },
+        None => stats.push("unreachable".to_string())
+    }
+    if !graph.predecessors[id].is_empty() {
+        stats.push(format!("from {}", block_list(&graph.predecessors[id])));
+    }
+    if !graph.successors[id].is_empty() {
+        stats.push(format!("to {}", block_list(&graph.successors[id])));
+    }
+    let mut text = format!("B{} : // {}\n", id, stats.join(", "));
+    let lines = lines(object);
+    let mut last_line = None;
+    for (index, instruction) in block.instructions.iter().enumerate() {
+        if let Some(&line) = lines.get(&(id as u32, index as u32)) {
+            if last_line != Some(line) {
+                let source = options.source.and_then(|s| s.get((line as usize).wrapping_sub(1)));
+                match source {
+                    Some(source) => text += &format!("\t// {} : {}\n", line, source.trim()),
+                    None => text += &format!("\t// line {}\n", line)
+                }
+                last_line = Some(line);
+            }
+        }
+        let comment = callee(object, instruction).map(|name| format!(" // {}", name)).unwrap_or_default();
+        if options.raw {
+            text += &format!("\t{}  {}{}\n", hex(&instruction.to_bytes()), instruction, comment);
+        }else {
+            text += &format!("\t{}{}\n", instruction, comment);
+        }
+    }
+    text
+}
+
+/// the ir of the object with comments : the header, the statistics of the blocks, the called externs and the source lines
+pub fn disassemble(header : &Header, object : &IrObject, options : &Options) -> Result<String> {
+    let blocks = selected(object, options.block)?;
+    let graph = Graph::new(object);
+    if options.block.is_some() {
+        return Ok(blocks.into_iter().map(|id| block_text(object, &graph, id, options)).collect())
+    }
+    let mut text = header_text(header, object);
+    for f in object.ext_fn.iter() {
+        text += &format!("extern fn {} ({}) -> {};\n", f.name, f.sig.args.join(", "), f.sig.ret);
+    }
+    text += "fn main () -> i64 {\n";
+    for (i, size) in object.local_vars.iter().enumerate() {
+        text += &format!("\tvar {} {}\n", i, size);
+    }
+    for id in blocks {
+        text += &block_text(object, &graph, id, options);
+    }
+    text += "}\n";
+    Ok(text)
+}
+
+/// the statistics of the block and the comments of its instructions
+fn block_json(object : &IrObject, graph : &Graph, id : usize, options : &Options) -> Value {
+    let lines = lines(object);
+    let annotations : Vec<Value> = object.blocks[id].instructions.iter().enumerate().map(|(index, instruction)| {
+        let mut value = json!({ "text" : instruction.to_string() });
+        if let Some(name) = callee(object, instruction) {
+            value["extern"] = json!(name);
+        }
+        if let Some(line) = lines.get(&(id as u32, index as u32)) {
+            value["line"] = json!(line);
+            if let Some(source) = options.source.and_then(|s| s.get((*line as usize).wrapping_sub(1))) {
+                value["source"] = json!(source.trim());
+            }
+        }
+        if options.raw {
+            value["bytes"] = json!(hex(&instruction.to_bytes()));
+        }
+        value
+    }).collect();
+    json!({
+        "id" : id,
+        "arguments" : graph.arguments(id),
+        "max_stack_depth" : graph.arguments(id).map(|_| graph.analysis.max_depths[id]),
+        "predecessors" : graph.predecessors[id],
+        "successors" : graph.successors[id],
+        "annotations" : annotations
+    })
+}
+
+/// the serialized object with its header and the analysis of its blocks, it can be assembled again with `jolang asm --from-json`.
+/// with a block, only the analysis and the instructions of the block
+pub fn disassemble_json(header : &Header, object : &IrObject, options : &Options) -> Result<Value> {
+    let blocks = selected(object, options.block)?;
+    let graph = Graph::new(object);
+    if let Some(id) = options.block {
+        let mut value = block_json(object, &graph, id as usize, options);
+        value["instructions"] = serde_json::to_value(&object.blocks[id as usize])?["instructions"].take();
+        return Ok(value)
+    }
+    let sections : Vec<Value> = header.sections.iter().map(|e| json!({
+        "tag" : tag_to_string(&e.tag),
+        "offset" : e.offset,
+        "length" : e.length,
+        "flags" : flags_to_string(e.flags),
+        "content" : section_name(&e.tag).unwrap_or("user-defined")
+    })).collect();
+    let mut value = serde_json::to_value(object)?;
+    value["header"] = json!({
+        "layout" : format!("{:?}", header.layout),
+        "version" : header.version(),
+        "writer" : writer(header.version, Some(object)),
+        "size" : header.size,
+        "checksum" : header.checksum,
+        "sections" : sections
+    });
+    value["analysis"] = blocks.into_iter().map(|id| block_json(object, &graph, id, options)).collect();
+    Ok(value)
+}
+
+/// the lines of the source given by the user or of the source next to the object, none if it isn't the source of the object
+fn source(args : &ShowArgs, object : &IrObject, path : &Path) -> Result<Option<Vec<String>>> {
+    if object.source_map.is_empty() {
+        return Ok(None)
+    }
+    let source_path = match &args.source {
+        Some(p) => PathBuf::from(p.as_os_str()),
+        None => path.with_extension("jol")
+    };
+    let text = match read_to_string(&source_path) {
+        Ok(text) => text,
+        Err(e) if args.source.is_some() => return Err(anyhow!("cannot read {} : {}", source_path.display(), e)),
+        Err(_) => return Ok(None)
+    };
+    if let Some(hash) = object.get_metadata("source_hash") {
+        if blake3::hash(text.as_bytes()).to_hex().as_str() != hash {
+            if args.source.is_some() {
+                bail!("{} is not the source of the object, it changed since the object was compiled", source_path.display())
+            }
+            return Ok(None)
+        }
+    }
+    Ok(Some(text.lines().map(|l| l.to_string()).collect()))
+}
+
+pub fn run_show(args : &ShowArgs) -> Result<()> {
+    if !args.file.is_local() {
+        return Err(anyhow!("please input a local file"))
+    }
+    let path = PathBuf::from(args.file.as_os_str());
+    let bytes = read(&path)?;
+    if args.format_version {
+        let (layout, version) = layout(&bytes)?;
+        println!("layout {:?} : {}", layout, layout.describe());
+        let object = reader::read(&mut Cursor::new(&bytes)).ok();
+        println!("written by {}", writer(version, object.as_ref()));
+        return Ok(())
+    }
+    if args.sections {
+        print_sections(&directory(&bytes)?);
+        return Ok(())
+    }
+    let object = reader::read(&mut Cursor::new(&bytes))?;
+    if args.cfg {
+        print!("{}", to_dot(&object));
+        return Ok(())
+    }
+    let header = Header::read(&bytes)?;
+    let source = source(args, &object, &path)?;
+    let options = Options { block : args.block, raw : args.raw, source : source.as_deref() };
+    match args.format {
+        Format::Text => print!("{}", disassemble(&header, &object, &options)?),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&disassemble_json(&header, &object, &options)?)?)
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jolang_shared::ir::{assembler::assemble, passes::PassManager, section::SourceLocation, serialize::from_json, writer::write};
+
+    const TEXT : &str = "extern fn print (i64) -> void;\nfn main () -> i64 {\n\tvar 0 8\nB0 : \n\ticonst 8 1\n\tbriz 1 2\nB1 : \n\ticonst 8 2\n\tcall 0\n\tbr 2\nB2 : \n\ticonst 8 0\n\tiret 8\n}";
+
+    fn object() -> (Header, IrObject) {
+        let mut object = assemble(TEXT).unwrap();
+        object.metadata.push(("compiler".to_string(), "test".to_string()));
+        let mut bytes = Cursor::new(vec![]);
+        write(object, &mut bytes).unwrap();
+        let bytes = bytes.into_inner();
+        (Header::read(&bytes).unwrap(), reader::read(&mut Cursor::new(&bytes)).unwrap())
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let (header, object) = object();
+        let options = Options { block : None, raw : false, source : None };
+        let text = disassemble(&header, &object, &options).unwrap();
+        assert!(text.starts_with("// layout V2 : section directory, 16-bytes instructions, checksum\n"), "{}", text);
+        assert!(text.contains("// compiler : test\n"), "{}", text);
+        assert!(text.contains("// written by test, "), "{}", text);
+        // without metadata, the release of the header
+        assert_eq!(writer([0, 4, 0], None), "jolang 0.4.0");
+        assert!(text.contains(concat!(
+            "B0 : // 2 instructions, arguments (), max stack depth 1, to B1 B2\n",
+            "\ticonst 8 1\n",
+            "\tbriz 1 2\n",
+            "B1 : // 3 instructions, arguments (), max stack depth 1, from B0, to B2\n",
+            "\ticonst 8 2\n",
+            "\tcall 0 // print\n",
+            "\tbr 2\n",
+            "B2 : // 2 instructions, arguments (), max stack depth 1, from B0 B1\n")), "{}", text);
+        // the comments are ignored by the assembler
+        assert_eq!(format!("{:?}", assemble(&text).unwrap()), TEXT);
+
+        let options = Options { block : Some(1), raw : true, source : None };
+        assert_eq!(disassemble(&header, &object, &options).unwrap(), concat!(
+            "B1 : // 3 instructions, arguments (), max stack depth 1, from B0, to B2\n",
+            "\t00 00 00 08 08 00 00 00 02 00 00 00 00 00 00 00  iconst 8 2\n",
+            "\t00 00 00 06 00 00 00 00 00 00 00 00 00 00 00 00  call 0 // print\n",
+            "\t00 00 00 04 02 00 00 00 00 00 00 00 00 00 00 00  br 2\n"));
+        let options = Options { block : Some(3), raw : false, source : None };
+        assert_eq!(disassemble(&header, &object, &options).unwrap_err().to_string(), "the object has no block B3 (3 blocks)");
+    }
+
+    #[test]
+    fn test_source_and_json() {
+        let (header, mut object) = object();
+        object.source_map = vec![
+            SourceLocation { block : 1, instruction : 0, line : 2, column : 1 },
+            SourceLocation { block : 1, instruction : 1, line : 2, column : 1 },
+            SourceLocation { block : 1, instruction : 2, line : 3, column : 1 }
+        ];
+        let source = vec!["if input() {".to_string(), "    print(2);".to_string()];
+        let options = Options { block : Some(1), raw : false, source : Some(&source) };
+        assert_eq!(disassemble(&header, &object, &options).unwrap(), concat!(
+            "B1 : // 3 instructions, arguments (), max stack depth 1, from B0, to B2\n",
+            "\t// 2 : print(2);\n",
+            "\ticonst 8 2\n",
+            "\tcall 0 // print\n",
+            "\t// line 3\n",
+            "\tbr 2\n"));
+        let json = disassemble_json(&header, &object, &options).unwrap();
+        assert_eq!(json["predecessors"], json!([0]));
+        assert_eq!(json["instructions"], json!([{ "iconst" : [8, 2] }, { "call" : 0 }, { "br" : 2 }]));
+        assert_eq!(json["annotations"][1], json!({ "text" : "call 0", "extern" : "print", "line" : 2, "source" : "print(2);" }));
+
+        // the whole object is assembled again
+        let options = Options { block : None, raw : true, source : None };
+        let json = disassemble_json(&header, &object, &options).unwrap();
+        assert_eq!(json["header"]["layout"], "V2");
+        assert_eq!(json["analysis"].as_array().unwrap().len(), 3);
+        assert_eq!(format!("{:?}", from_json(&json.to_string()).unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_compiled_source() {
+        let dir = std::env::temp_dir().join(format!("jolang-show-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source_path = dir.join("countdown.jol");
+        std::fs::write(&source_path, "let a = input();\nwhile (a > 0) {\n    print(a);\n    a = a - 1;\n}\n").unwrap();
+        let object_path = dir.join("countdown.joo");
+        let built = jolangc::build(source_path, object_path.clone(), &PassManager::new());
+        let shown = built.and_then(|_| {
+            // the source next to the object is found and checked against its hash
+            let args = ShowArgs {
+                file : ClioPath::new(&object_path)?,
+                format_version : false,
+                sections : false,
+                cfg : false,
+                block : Some(3),
+                format : Format::Text,
+                raw : false,
+                source : None
+            };
+            let bytes = read(&object_path)?;
+            let object = reader::read(&mut Cursor::new(&bytes))?;
+            let source = source(&args, &object, &object_path)?;
+            let options = Options { block : args.block, raw : false, source : source.as_deref() };
+            disassemble(&Header::read(&bytes)?, &object, &options)
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(shown.unwrap(), concat!(
+            "B3 : // 13 instructions, arguments (), max stack depth 3, from B2, to B2\n",
+            "\t// 3 : print(a);\n",
+            "\ticonst 4 0\n",
+            "\tvarref\n",
+            "\tiload 8\n",
+            "\tcall 1 // print\n",
+            "\t// 4 : a = a - 1;\n",
+            "\ticonst 4 0\n",
+            "\tvarref\n",
+            "\ticonst 4 0\n",
+            "\tvarref\n",
+            "\tiload 8\n",
+            "\ticonst 8 1\n",
+            "\tisub 8\n",
+            "\tistore 8\n",
+            "\t// 2 : while (a > 0) {\n",
+            "\tbr 2\n"));
+    }
 }
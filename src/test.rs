@@ -0,0 +1,246 @@
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+use anyhow::Result;
+use clap::Args;
+use clio::ClioPath;
+use jolang_shared::{ffi::jolang_std::capture_output, ir::IrObject};
+use jolangc::generator::{Generate, IrGenerator};
+use jolangc::hir::{Hir, ASSERTION_FAILED};
+use jolangc::source_buffer::SourceBuffer;
+use crate::golden::{bless, compare, run_golden, Expectations};
+
+#[derive(Args)]
+pub struct TestArgs {
+    /// source files or directories containing the tests, the current directory by default
+    #[clap(value_parser = clap::value_parser!(ClioPath).exists())]
+    pub files : Vec<ClioPath>,
+    /// only run the tests whose name contains one of the filters, the name of a golden test is its path
+    #[clap(short, long)]
+    pub filter : Vec<String>,
+    /// replace the expected output and exit code of the golden tests by the actual ones
+    #[clap(long)]
+    pub bless : bool
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(String)
+}
+
+/// the sources (.jol) of the paths, the directories are searched recursively
+pub fn find_sources(paths : &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut sources = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            sources.push(path.clone());
+            continue;
+        }
+        let mut entries : Vec<PathBuf> = fs::read_dir(path)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() {
+                sources.extend(find_sources(&[entry])?);
+            }else if entry.extension().is_some_and(|e| e == "jol") {
+                sources.push(entry);
+            }
+        }
+    }
+    // a file given with its directory (e.g. `f.jol .`) is only found once
+    let mut found = HashSet::new();
+    sources.retain(|s| found.insert(fs::canonicalize(s).unwrap_or_else(|_| s.clone())));
+    Ok(sources)
+}
+
+/// run a test in its own program with the given runtime, the output of print is captured
+pub fn run_test(hir : &Hir, index : usize, run : &dyn Fn(IrObject) -> Result<i64>) -> Outcome {
+    let test = &hir.tests[index];
+    let program = hir.test_program(index);
+    let mut generator = IrGenerator::from(&program);
+    if let Err(e) = program.generate(&mut generator) {
+        return Outcome::Failed(format!("generation error at line {} : {}", e.line_number(), e.message()))
+    }
+    let (result, output) = capture_output(|| run(generator.into_ir()));
+    let code = match result {
+        Ok(code) => code,
+        Err(e) => return Outcome::Failed(format!("runtime error : {}", e))
+    };
+    let assertion = code.wrapping_sub(ASSERTION_FAILED);
+    if code != 0 {
+        return Outcome::Failed(match test.assertions.get(assertion as usize) {
+            Some(a) if assertion >= 0 => format!("assertion failed at line {} : {}", a.line, a.message),
+            _ => format!("the test returned {}", code)
+        })
+    }
+    match &test.output {
+        Some(expected) if expected != &output => Outcome::Failed(format!(
+            "the output is not the expected one\nexpected :\n{}\nfound :\n{}",
+            expected.trim_end(),
+            output.trim_end())),
+        _ => Outcome::Passed
+    }
+}
+
+/// run a source with the input of its header and compare the results with the expected ones
+fn test_golden(path : &Path, source : &SourceBuffer, expected : &Expectations, blessing : bool, run : &dyn Fn(IrObject) -> Result<i64>) -> Result<Outcome> {
+    let (stdout, exit) = match run_golden(source, &expected.stdin, run) {
+        Ok(result) => result,
+        Err(e) => return Ok(Outcome::Failed(format!("runtime error : {}", e)))
+    };
+    let differences = compare(&path.display().to_string(), expected, &stdout, exit);
+    Ok(match differences {
+        Some(_) if blessing => {
+            fs::write(path, bless(&source.buffer, &stdout, exit))?;
+            println!("blessed {}", path.display());
+            Outcome::Passed
+        },
+        Some(message) => Outcome::Failed(message),
+        None => Outcome::Passed
+    })
+}
+
+/// the tests of a source and their outcomes, none if the source has errors (they are printed)
+fn test_source(path : &Path, args : &TestArgs, run : &dyn Fn(IrObject) -> Result<i64>) -> Result<Option<Vec<(String, Outcome)>>> {
+    let source = SourceBuffer::open(path.to_path_buf())?;
+    let hir = match jolangc::analyze(&source) {
+        Ok(hir) => hir,
+        Err(e) => {
+            eprint!("{}", e);
+            return Ok(None)
+        }
+    };
+    let selected = |name : &str| args.filter.is_empty() || args.filter.iter().any(|f| name.contains(f.as_str()));
+    let mut results = vec![];
+    let name = path.display().to_string();
+    if let Some(expected) = Expectations::parse(&source.buffer)?.filter(|_| selected(&name)) {
+        let outcome = test_golden(path, &source, &expected, args.bless, run)?;
+        println!("golden {} ... {}", name, if outcome == Outcome::Passed { "ok" } else { "FAILED" });
+        results.push((name, outcome));
+    }
+    for (i, test) in hir.tests.iter().enumerate() {
+        if !selected(&test.name) {
+            continue;
+        }
+        let outcome = run_test(&hir, i, run);
+        println!("test {} ... {}", test.name, if outcome == Outcome::Passed { "ok" } else { "FAILED" });
+        results.push((format!("{} ({}:{})", test.name, path.display(), test.line), outcome));
+    }
+    Ok(Some(results))
+}
+
+/// run the tests of the sources, returns true if they all passed
+pub fn run_tests(args : &TestArgs, run : &dyn Fn(IrObject) -> Result<i64>) -> Result<bool> {
+    let paths : Vec<PathBuf> = if args.files.is_empty() {
+        vec![PathBuf::from(".")]
+    }else {
+        args.files.iter().map(|f| PathBuf::from(f.as_os_str())).collect()
+    };
+    let mut passed = 0;
+    let mut failures = vec![];
+    let mut broken = 0;
+    for path in find_sources(&paths)? {
+        match test_source(&path, args, run)? {
+            Some(results) => for (name, outcome) in results {
+                match outcome {
+                    Outcome::Passed => passed += 1,
+                    Outcome::Failed(message) => failures.push((name, message))
+                }
+            },
+            None => broken += 1
+        }
+    }
+    if !failures.is_empty() {
+        println!("\nfailures :");
+        for (name, message) in &failures {
+            println!("\n---- {} ----\n{}", name, message);
+        }
+    }
+    let ok = failures.is_empty() && broken == 0;
+    print!("\ntest result : {}. {} passed; {} failed", if ok { "ok" } else { "FAILED" }, passed, failures.len());
+    if broken > 0 {
+        print!("; {} sources with errors", broken);
+    }
+    println!();
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jolang_runtime::{interpreter::Interpreter, Runtime};
+
+    fn interpret(object : IrObject) -> Result<i64> {
+        Interpreter::new().run(object)
+    }
+
+    fn outcomes(code : &str) -> Vec<(String, Outcome)> {
+        let source = SourceBuffer::from_string("test.jol", code.to_string());
+        let hir = jolangc::analyze(&source).unwrap();
+        (0..hir.tests.len())
+            .map(|i| (hir.tests[i].name.clone(), run_test(&hir, i, &interpret)))
+            .collect()
+    }
+
+    #[test]
+    fn test_run_test() {
+        let results = outcomes(r#"
+let n = input();
+test "arithmetic" {
+    let a = 6;
+    assert(a * 7 == 42);
+    assert_eq(a / 4, 1, "integer division");
+}
+test "failure" {
+    let a = 2;
+    assert_eq(a, 2);
+    assert_eq(a + 1, 2);
+}
+test "message" {
+    assert(0, "always fails");
+}
+test "return" {
+    return 3;
+}
+test "output" {
+    let i = 0;
+    while (i < 3) {
+        print(i);
+        i = i + 1;
+    }
+    assert_output("0\n1\n2\n");
+}
+test "wrong output" {
+    print(1);
+    assert_output("2\n");
+}
+test "division" {
+    let zero = 0;
+    print(1 / zero);
+}
+"#);
+        assert_eq!(results[0], ("arithmetic".to_string(), Outcome::Passed));
+        assert_eq!(results[1].1, Outcome::Failed("assertion failed at line 11 : assert_eq(a + 1, 2)".to_string()));
+        assert_eq!(results[2].1, Outcome::Failed("assertion failed at line 14 : always fails".to_string()));
+        assert_eq!(results[3].1, Outcome::Failed("the test returned 3".to_string()));
+        assert_eq!(results[4].1, Outcome::Passed);
+        assert_eq!(results[5].1, Outcome::Failed("the output is not the expected one\nexpected :\n2\nfound :\n1".to_string()));
+        assert_eq!(results[6].1, Outcome::Failed("runtime error : division by zero".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let analyze = |code : &str| jolangc::analyze(&SourceBuffer::from_string("test.jol", code.to_string()));
+        // the tests don't see the variables of the program
+        assert!(analyze("let a = 1; test \"a\" { assert(a == 1); }").is_err());
+        assert!(analyze("assert(1);").is_err());
+        assert!(analyze("test \"a\" { assert(1, 2); }").is_err());
+        let arity = |code : &str| analyze(code).unwrap_err().message().to_string();
+        assert_eq!(arity("test \"a\" { assert_eq(1); }"), "assert_eq takes 2 to 3 arguments but 1 were given");
+        assert_eq!(arity("test \"a\" { assert(); }"), "assert takes 1 to 2 arguments but 0 were given");
+        assert_eq!(arity("test \"a\" { assert_output(); }"), "assert_output takes 1 arguments but 0 were given");
+        assert!(analyze("let s = \"text\";").is_err());
+        // test is still a valid variable name
+        assert!(analyze("let test = 1; test = test + 1;").is_ok());
+    }
+}
@@ -0,0 +1,47 @@
+use std::{fs::File, path::{Path, PathBuf}};
+use anyhow::Result;
+use clap::Args;
+use clio::ClioPath;
+use jolang_shared::ir::{reader::read, verifier::errors, IrObject};
+use jolangc::compile;
+use jolangc::source_buffer::SourceBuffer;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// objects or sources to verify, the sources are compiled in memory
+    #[clap(value_parser = clap::value_parser!(ClioPath).exists().is_file(), required = true)]
+    pub files : Vec<ClioPath>
+}
+
+fn load(path : &Path) -> Result<IrObject> {
+    if path.extension().is_some_and(|e| e == "jol") {
+        let source = SourceBuffer::open(path.to_path_buf())?;
+        return Ok(compile(&source)?)
+    }
+    read(&mut File::open(path)?)
+}
+
+/// print the errors of the ir of each file, returns true if every file is valid
+pub fn run_verify(args : &VerifyArgs) -> bool {
+    let mut valid = true;
+    for file in &args.files {
+        let path = PathBuf::from(file.as_os_str());
+        let object = match load(&path) {
+            Ok(object) => object,
+            Err(e) => {
+                eprintln!("{} : {}", path.display(), e);
+                valid = false;
+                continue
+            }
+        };
+        let errors = errors(&object);
+        for e in errors.iter() {
+            eprintln!("{} : {}", path.display(), e);
+        }
+        if errors.is_empty() {
+            println!("{} : ok", path.display());
+        }
+        valid &= errors.is_empty();
+    }
+    valid
+}
@@ -0,0 +1,98 @@
+use std::{fs, io::{stdout, Write}, path::PathBuf, thread::sleep, time::{Duration, SystemTime}};
+use anyhow::Result;
+
+/// delay between two checks of the watched files
+const POLL : Duration = Duration::from_millis(100);
+/// the files must stay unchanged during this delay before running again, editors can save in several writes
+const DEBOUNCE : Duration = Duration::from_millis(200);
+
+/// detect the changes of files by polling their modification times
+pub struct Watcher {
+    files : Vec<(PathBuf, Option<SystemTime>)>
+}
+
+fn modified(path : &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Watcher {
+    pub fn new(paths : Vec<PathBuf>) -> Self {
+        Self {
+            files : paths.into_iter().map(|p| {
+                let time = modified(&p);
+                (p, time)
+            }).collect()
+        }
+    }
+
+    /// a file was modified, created or removed since the last check
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, time) in self.files.iter_mut() {
+            let new_time = modified(path);
+            if new_time != *time {
+                *time = new_time;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// wait for a change followed by the debounce delay without any other change
+    pub fn wait(&mut self) {
+        while !self.changed() {
+            sleep(POLL);
+        }
+        loop {
+            sleep(DEBOUNCE);
+            if !self.changed() {
+                return
+            }
+        }
+    }
+}
+
+pub fn clear_screen() -> Result<()> {
+    print!("\x1b[2J\x1b[H");
+    Ok(stdout().flush()?)
+}
+
+/// run the action now and after every change of the files, it never returns
+pub fn watch(paths : Vec<PathBuf>, mut action : impl FnMut() -> Result<()>) -> Result<()> {
+    let mut watcher = Watcher::new(paths.clone());
+    let names : Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    loop {
+        clear_screen()?;
+        if let Err(e) = action() {
+            eprintln!("error : {}", e);
+        }
+        println!("[watching {} for changes]", names.join(", "));
+        watcher.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher() {
+        let dir = std::env::temp_dir().join(format!("jolang-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.jol");
+        fs::write(&path, "print(1);").unwrap();
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        assert!(!watcher.changed());
+        sleep(Duration::from_millis(20));
+        fs::write(&path, "print(2);").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        // a removed file is a change
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+        fs::write(&path, "print(3);").unwrap();
+        watcher.wait();
+        assert!(!watcher.changed());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}